@@ -0,0 +1,109 @@
+//! The input and output files of `apicli batch`.
+//!
+//! Inputs are numbered by their line in the file, blank lines are skipped. Results are written
+//! one line each as they come in, so the output doubles as the checkpoint a resumed batch picks
+//! up from.
+
+use std::collections::HashSet;
+
+use engine_entities::engine::{BatchInput, RunBatchResponse};
+
+use crate::constants;
+
+/// The lines a previous run of the batch finished, and how much of the output to keep. A line
+/// cut short by a crash is dropped so its input runs again.
+pub fn completed(output: &str) -> (HashSet<u64>, usize) {
+    let mut done = HashSet::new();
+    let mut keep: usize = 0;
+
+    for line in output.split_inclusive('\n') {
+        if !line.ends_with('\n') {
+            break;
+        }
+        keep = keep.saturating_add(line.len());
+
+        let index = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|result| result.get(constants::BATCH_LINE_FIELD)?.as_u64());
+        if let Some(index) = index {
+            done.insert(index);
+        }
+    }
+
+    (done, keep)
+}
+
+/// Every input that hasn't been run yet.
+pub fn pending(inputs: &str, done: &HashSet<u64>) -> Vec<BatchInput> {
+    (1..)
+        .zip(inputs.lines())
+        .filter(|&(index, line)| !line.trim().is_empty() && !done.contains(&index))
+        .map(|(index, line)| BatchInput {
+            index,
+            input: line.to_owned(),
+        })
+        .collect()
+}
+
+/// `{ line, executionId, output }`, or `error` in place of `output` when the run failed.
+pub fn result_line(result: &RunBatchResponse) -> String {
+    let mut line = serde_json::Map::new();
+    line.insert(constants::BATCH_LINE_FIELD.into(), result.index.into());
+    line.insert("executionId".into(), result.execution_id.clone().into());
+
+    if let Some(ref error) = result.error {
+        line.insert("error".into(), error.clone().into());
+    } else {
+        let output = result.output.as_deref().unwrap_or("null");
+        let output = serde_json::from_str(output).unwrap_or_else(|_| output.into());
+        line.insert("output".into(), output);
+    }
+
+    serde_json::Value::Object(line).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_resumes_from_output() {
+        let output = "{\"line\":1,\"executionId\":\"a\",\"output\":{}}\n{\"line\":3,\"executionId\":\"b\",\"error\":\"nope\"}\n{\"line\":4,\"exec";
+        let (done, keep) = completed(output);
+        assert_eq!(HashSet::from([1, 3]), done);
+        assert_eq!(output.rfind('\n').unwrap() + 1, keep);
+
+        let inputs = "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n\n{\"id\":5}\n";
+        let pending: Vec<_> = pending(inputs, &done)
+            .into_iter()
+            .map(|input| (input.index, input.input))
+            .collect();
+        assert_eq!(
+            vec![(2, "{\"id\":2}".to_owned()), (5, "{\"id\":5}".to_owned())],
+            pending
+        );
+    }
+
+    #[test]
+    fn test_result_lines() {
+        let mut result = RunBatchResponse {
+            index: 2,
+            execution_id: "abc".into(),
+            output: Some("{\"items\":[1]}".into()),
+            error: None,
+        };
+        assert_eq!(
+            "{\"line\":2,\"executionId\":\"abc\",\"output\":{\"items\":[1]}}",
+            result_line(&result)
+        );
+
+        result.output = None;
+        result.error = Some("Request failed".into());
+        assert_eq!(
+            "{\"line\":2,\"executionId\":\"abc\",\"error\":\"Request failed\"}",
+            result_line(&result)
+        );
+    }
+}
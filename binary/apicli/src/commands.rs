@@ -1,7 +1,11 @@
 //!
 
-use crate::engine::{self, handle_schema_convert, handle_schema_merge};
-use clap::{Parser, Subcommand};
+use crate::{
+    constants,
+    engine::{self, handle_pack, handle_schema_convert, handle_schema_merge},
+    harness,
+};
+use clap::{Args, Parser, Subcommand};
 
 ///
 #[derive(Debug, Parser)]
@@ -12,6 +16,42 @@ pub struct Cli {
     pub command: Commands,
 }
 
+/// How a connector is run, sent along with its input.
+#[derive(Debug, Args)]
+pub struct RunOptions {
+    ///
+    #[arg(short, long)]
+    pub limit: Option<i32>,
+
+    /// Credential profile to run with, the connector's default when left out.
+    #[arg(short, long)]
+    pub profile: Option<String>,
+
+    /// Environment from the connector's `config.json` to run against.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// Return `{ status, headers, body }` instead of just the body.
+    #[arg(long, default_value_t = false)]
+    pub envelope: bool,
+
+    /// Statuses that fail the run, i.e. `4xx,5xx`, `500-599,429` or `none`.
+    #[arg(long)]
+    pub error_statuses: Option<String>,
+
+    /// Call the API even when the connector caches responses.
+    #[arg(long, default_value_t = false)]
+    pub no_cache: bool,
+
+    /// Save the run's HTTP traffic (redacted) to `DIR/<execution_id>.jsonl`.
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    pub record: Option<String>,
+
+    /// Answer the run's HTTP calls from a recorded cassette instead of the network.
+    #[arg(long, value_name = "CASSETTE")]
+    pub replay: Option<String>,
+}
+
 ///
 #[derive(Debug, Subcommand)]
 pub enum Commands {
@@ -28,6 +68,10 @@ pub enum Commands {
     Oauth {
         ///
         name: String,
+
+        /// Credential profile to authorize, the connector's default when left out.
+        #[arg(short, long)]
+        profile: Option<String>,
     },
 
     ///
@@ -38,9 +82,43 @@ pub enum Commands {
         ///
         input: Option<String>,
 
+        /// Print results as newline delimited JSON while pages come in.
+        #[arg(short, long, default_value_t = false)]
+        stream: bool,
+
+        ///
+        #[command(flatten)]
+        options: RunOptions,
+    },
+
+    /// Run every line of a JSONL file through an operation, printing a result or error per line
+    /// as newline delimited JSON.
+    Batch {
+        ///
+        name: String,
+
         ///
+        inputs: String,
+
+        /// Runs in flight at once.
+        #[arg(short = 'j', long, default_value_t = constants::DEFAULT_BATCH_PARALLELISM)]
+        parallelism: u32,
+
+        /// Runs started per second at most.
+        #[arg(long)]
+        rate: Option<f64>,
+
+        /// Write results to a file instead of stdout.
         #[arg(short, long)]
-        limit: Option<i32>,
+        output: Option<String>,
+
+        /// Skip the lines that already have a result in `--output`, appending the rest.
+        #[arg(long, default_value_t = false, requires = "output")]
+        resume: bool,
+
+        ///
+        #[command(flatten)]
+        options: RunOptions,
     },
 
     ///
@@ -80,6 +158,39 @@ pub enum Commands {
         name: String,
     },
 
+    /// Serve a connector's operations locally with examples from its spec, point its `baseUrl`
+    /// at the printed address to develop against it.
+    Mock {
+        ///
+        name: String,
+
+        ///
+        #[arg(long, default_value_t = constants::DEFAULT_MOCK_HOST.to_owned())]
+        host: String,
+
+        ///
+        #[arg(short, long, default_value_t = constants::DEFAULT_MOCK_PORT)]
+        port: u16,
+
+        /// How many pages paginated operations return.
+        #[arg(long, default_value_t = constants::DEFAULT_MOCK_PAGES)]
+        pages: u32,
+    },
+
+    /// Run the test cases in a connector's `tests` directory, by name or path.
+    Test {
+        ///
+        name: String,
+
+        ///
+        #[arg(short, long, value_enum, default_value_t = harness::report::Format::Tap)]
+        format: harness::report::Format,
+
+        /// Write the report to a file instead of stdout.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
     ///
     InputPaths {
         ///
@@ -125,6 +236,44 @@ pub enum Commands {
         ///
         input: Option<String>,
     },
+
+    /// Package a connector directory so it can be installed as `maintainer/name:tag`.
+    Pack {
+        ///
+        dir: String,
+
+        ///
+        #[arg(short, long)]
+        maintainer: String,
+
+        ///
+        #[arg(short, long)]
+        tag: String,
+
+        /// Defaults to the directory's name.
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Defaults to `<name>-<tag>.tar.gz`.
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Install a package into the registry, next to any other versions of it.
+    Install {
+        ///
+        package: String,
+
+        /// Replace the version if it's already installed.
+        #[arg(short, long, default_value_t = false)]
+        force: bool,
+    },
+
+    /// List the installed versions of a connector, by `name` or `maintainer/name`.
+    Versions {
+        ///
+        name: String,
+    },
 }
 
 impl Commands {
@@ -133,8 +282,32 @@ impl Commands {
         match self {
             Self::List => engine.handle_list().await?,
             Self::Get { name } => engine.handle_get_service(name).await?,
-            Self::Oauth { name } => engine.handle_auth(name).await?,
-            Self::Run { name, input, limit } => engine.handle_run(name, input, limit).await?,
+            Self::Oauth { name, profile } => engine.handle_auth(name, profile).await?,
+            Self::Run {
+                name,
+                input,
+                stream,
+                options,
+            } => engine.handle_run(name, input, stream, options).await?,
+            Self::Batch {
+                name,
+                inputs,
+                parallelism,
+                rate,
+                output,
+                resume,
+                options,
+            } => {
+                let settings = engine::BatchSettings {
+                    parallelism,
+                    rate,
+                    output,
+                    resume,
+                };
+                engine
+                    .handle_batch(name, &inputs, settings, options)
+                    .await?;
+            }
             Self::RunResult { execution_id } => engine.handle_run_result(execution_id).await?,
             Self::RunStatus { execution_id } => engine.handle_run_status(execution_id).await?,
             Self::ProvideInput {
@@ -143,6 +316,17 @@ impl Commands {
             } => engine.handle_provide_input(execution_id, input).await?,
             Self::InputStub { name, required } => engine.handle_input_stub(name, required).await?,
             Self::OutputStub { name } => engine.handle_output_stub(name).await?,
+            Self::Mock {
+                name,
+                host,
+                port,
+                pages,
+            } => engine.handle_mock(name, host, port, pages).await?,
+            Self::Test {
+                name,
+                format,
+                output,
+            } => engine.handle_test(name, format, output).await?,
             Self::InputPaths { name, required } => {
                 engine.handle_input_paths(name, required).await?;
             }
@@ -155,6 +339,15 @@ impl Commands {
                 api,
                 input,
             } => engine.handle_generate(&template_name, &name, &api, input)?,
+            Self::Pack {
+                dir,
+                maintainer,
+                tag,
+                name,
+                output,
+            } => handle_pack(&dir, maintainer, tag, name, output)?,
+            Self::Install { package, force } => engine.handle_install(&package, force)?,
+            Self::Versions { name } => engine.handle_versions(&name)?,
         }
 
         Ok(())
@@ -13,6 +13,39 @@ pub struct Configuration {
 
     ///
     pub client: ClientConfiguration,
+
+    ///
+    #[serde(default)]
+    pub registry: RegistryConfiguration,
+
+    ///
+    #[serde(default)]
+    pub run: RunConfiguration,
+
+    ///
+    #[serde(default)]
+    pub connectors: ConnectorsConfiguration,
+}
+
+/// Where `apicli test` finds connectors, should match `connector.path` in apid's configuration.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ConnectorsConfiguration {
+    /// `~/connectors` by default.
+    pub path: Option<String>,
+}
+
+/// Defaults for every `apicli run`, flags take precedence.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RunConfiguration {
+    /// Environment runs use unless `--environment` picks another.
+    pub environment: Option<String>,
+}
+
+/// Should match `registry_path` in apid's configuration.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RegistryConfiguration {
+    /// `~/.apid/registry` by default.
+    pub path: Option<String>,
 }
 
 ///
@@ -2,3 +2,84 @@
 
 ///
 pub const APICLI_CONFIG_PATH: &str = "APICLI_CONFIG_PATH";
+
+/// Relative to `$HOME`, shared with apid so installed connectors are picked up.
+pub const DEFAULT_REGISTRY_PATH: &str = ".apid/registry";
+
+///
+pub const DEFAULT_MOCK_HOST: &str = "127.0.0.1";
+
+///
+pub const DEFAULT_MOCK_PORT: u16 = 8089;
+
+/// How many pages a paginated operation has before the mock says there are no more.
+pub const DEFAULT_MOCK_PAGES: u32 = 3;
+
+/// Items per page when neither the request nor the connector says.
+pub const MOCK_PAGE_SIZE: u32 = 5;
+
+/// Added to next page links for styles where the connector doesn't send a page of its own.
+pub const MOCK_PAGE_PARAM: &str = "mock_page";
+
+/// In mebibytes.
+pub const MOCK_BODY_LIMIT: u32 = 10;
+
+///
+pub const MOCK_OK: u16 = 200;
+
+///
+pub const MOCK_BAD_REQUEST: u16 = 400;
+
+///
+pub const MOCK_NOT_FOUND: u16 = 404;
+
+///
+pub const MOCK_SERVER_ERROR: u16 = 500;
+
+///
+pub const LINK_HEADER: &str = "Link";
+
+/// Relative to `$HOME`, same as apid.
+pub const DEFAULT_CONNECTORS_PATH: &str = "connectors";
+
+/// In a connector's directory.
+pub const TESTS_DIR: &str = "tests";
+
+///
+pub const TEST_EXTENSIONS: [&str; 3] = ["json", "yaml", "yml"];
+
+/// The response whose schema `"schema": true` checks against.
+pub const SUCCESS_RESPONSE: &str = "200";
+
+/// Where named schemas are referenced from, `OpenAPI` 3 and then `Swagger` 2.
+pub const SCHEMA_PREFIXES: [&str; 2] = ["#/components/schemas/", "#/definitions/"];
+
+/// How deep schemas are followed before giving up on them.
+pub const MAX_SCHEMA_DEPTH: usize = 64;
+
+/// Locations fetched over HTTP when loading a connector.
+pub const REMOTE_PREFIXES: [&str; 2] = ["http://", "https://"];
+
+///
+pub const PYTHON_LANG: &str = "python";
+
+///
+pub const JAVASCRIPT_LANG: &str = "js";
+
+///
+pub const REPLAY_OPTION: &str = "replay";
+
+/// How many times `apicli test` checks whether the mock is up before giving up.
+pub const MOCK_STARTUP_ATTEMPTS: u32 = 50;
+
+///
+pub const MOCK_STARTUP_INTERVAL_MS: u64 = 100;
+
+/// Runs of a batch in flight at once unless `--parallelism` says otherwise.
+pub const DEFAULT_BATCH_PARALLELISM: u32 = 4;
+
+/// Inputs sent to apid per message of a batch.
+pub const BATCH_CHUNK_SIZE: usize = 100;
+
+/// Where a batch result says which input line it's for.
+pub const BATCH_LINE_FIELD: &str = "line";
@@ -10,27 +10,34 @@ use serde::{Deserialize, Serialize};
 use tera::{Context, Tera};
 
 use std::{
-    collections::HashMap,
-    env, fs, io,
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write as _},
     path::{Path, PathBuf},
     sync::Mutex,
 };
 
-use anyhow::{anyhow, Context as _};
-use core_entities::service::VersionedServiceTree;
+use anyhow::{anyhow, bail, Context as _};
+use core_entities::service::{service_manifest_latest, VersionedServiceTree};
 use credential_entities::credentials::Authentication;
 use engine_entities::engine::{
     engine_client::EngineClient, GetRunResultRequest, GetSerivceRequest, ListRequest,
-    ProvideInputRequest, RunServiceRequest, SaveServiceRequest,
+    ProvideInputRequest, RunBatchRequest, RunServiceRequest, SaveServiceRequest,
 };
+use local_file_loader::packages::{self, Registry};
 use oauth_flow::Authenticator;
 use protobuf::Message;
 use protobuf_json_mapping::PrintOptions;
+use service_loader::identity::ConnectorId;
+use tokio::{runtime::Handle, task};
 use tonic::{transport::Channel, Request};
 
 use crate::{
+    batch,
+    commands::RunOptions,
     config::Configuration,
-    constants,
+    constants, harness, mock,
     path::{get_input_paths, get_output_paths},
     stub::{get_input, get_output},
     template::{Direction, InputDescription},
@@ -57,6 +64,29 @@ fn read_lines_from_stdin() -> io::Result<String> {
     Ok(lines.join("\n"))
 }
 
+/// Relative to where apicli was run from.
+fn absolute(path: String) -> io::Result<String> {
+    Ok(env::current_dir()?
+        .join(path)
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// How `apicli batch` runs its inputs and where the results go.
+pub struct BatchSettings {
+    ///
+    pub parallelism: u32,
+
+    ///
+    pub rate: Option<f64>,
+
+    ///
+    pub output: Option<String>,
+
+    ///
+    pub resume: bool,
+}
+
 ///
 pub struct Cli {
     ///
@@ -68,7 +98,7 @@ pub struct Cli {
 
 impl Cli {
     ///
-    pub async fn init() -> anyhow::Result<Self> {
+    pub fn init() -> anyhow::Result<Self> {
         let config = env::var(constants::APICLI_CONFIG_PATH).unwrap_or_else(|_| {
             let home = env::var("HOME").unwrap_or_else(|_| ".".to_owned());
 
@@ -79,8 +109,9 @@ impl Cli {
 
         let config: Configuration = toml::from_str(&config)?;
 
+        // Connected on first use, `apicli test` and packaging don't need apid running
         let endpoint = format!("http://{}:{}", config.client.host, config.client.port);
-        let client = EngineClient::connect(endpoint).await?;
+        let client = EngineClient::new(Channel::from_shared(endpoint)?.connect_lazy());
 
         Ok(Cli { client, config })
     }
@@ -92,7 +123,15 @@ impl Cli {
         let response = self.client.list(request).await?.into_inner();
 
         for item in response.items {
-            println!("{}", item.name);
+            if item.broken_references.is_empty() {
+                println!("{}", item.name);
+            } else {
+                println!(
+                    "{} [broken: {}]",
+                    item.name,
+                    item.broken_references.join(", ")
+                );
+            }
         }
 
         Ok(())
@@ -100,9 +139,20 @@ impl Cli {
 
     ///
     pub async fn handle_get_service(&mut self, name: String) -> anyhow::Result<()> {
-        let request = Request::new(GetSerivceRequest { name });
+        let request = Request::new(GetSerivceRequest {
+            name,
+            profile: None,
+        });
         let response = self.client.get_service(request).await?.into_inner();
 
+        // Kept off stdout so the manifest can still be piped
+        for broken in &response.broken_references {
+            eprintln!("Broken reference: {broken}");
+        }
+        if !response.profiles.is_empty() {
+            eprintln!("Credential profiles: {}", response.profiles.join(", "));
+        }
+
         let service = VersionedServiceTree::parse_from_bytes(&response.raw_service)?;
         let service = service.v1();
         let manifest = service.manifest.v2();
@@ -117,12 +167,19 @@ impl Cli {
     }
 
     ///
-    pub async fn handle_auth(&mut self, name: String) -> anyhow::Result<()> {
+    pub async fn handle_auth(
+        &mut self,
+        name: String,
+        profile: Option<String>,
+    ) -> anyhow::Result<()> {
         let base_path = self.config.oauth.base_uri.clone();
         let key_path = self.config.oauth.key_path.clone();
         let cert_path = self.config.oauth.cert_path.clone();
 
-        let request = Request::new(GetSerivceRequest { name: name.clone() });
+        let request = Request::new(GetSerivceRequest {
+            name: name.clone(),
+            profile: profile.clone(),
+        });
         let response = self.client.get_service(request).await?.into_inner();
 
         let credentials = response
@@ -148,6 +205,7 @@ impl Cli {
             name,
             raw_service: None,
             raw_credentials: Some(raw_credentials),
+            profile,
         });
 
         self.client.save_service(save_request).await?;
@@ -162,7 +220,8 @@ impl Cli {
         &mut self,
         name: String,
         input: Option<String>,
-        limit: Option<i32>,
+        stream: bool,
+        options: RunOptions,
     ) -> anyhow::Result<()> {
         let input = if let Some(input) = input {
             fs::read_to_string(Path::new(&input))?
@@ -170,12 +229,20 @@ impl Cli {
             read_lines_from_stdin()?
         };
 
-        let request = Request::new(RunServiceRequest {
-            id: name.clone(),
-            input,
-            limit,
-            execution_id: None,
-        });
+        let request = Request::new(self.run_request(name, input, options)?);
+
+        if stream {
+            let mut pages = self.client.run_service_stream(request).await?.into_inner();
+
+            while let Some(page) = pages.message().await? {
+                for item in page.items {
+                    println!("{item}");
+                }
+            }
+
+            return Ok(());
+        }
+
         let response = self.client.run_service(request).await?.into_inner();
 
         println!("{}", response.execution_id);
@@ -183,6 +250,102 @@ impl Cli {
         Ok(())
     }
 
+    ///
+    fn run_request(
+        &self,
+        name: String,
+        input: String,
+        options: RunOptions,
+    ) -> anyhow::Result<RunServiceRequest> {
+        Ok(RunServiceRequest {
+            id: name,
+            input,
+            limit: options.limit,
+            execution_id: None,
+            profile: options.profile,
+            environment: options
+                .environment
+                .or_else(|| self.config.run.environment.clone()),
+            envelope: options.envelope.then_some(true),
+            error_statuses: options.error_statuses,
+            no_cache: options.no_cache.then_some(true),
+            // apid doesn't run from our directory
+            record: options.record.map(absolute).transpose()?,
+            replay: options.replay.map(absolute).transpose()?,
+        })
+    }
+
+    /// Results are written as they come in, in the order the runs finish.
+    pub async fn handle_batch(
+        &mut self,
+        name: String,
+        inputs: &str,
+        settings: BatchSettings,
+        options: RunOptions,
+    ) -> anyhow::Result<()> {
+        let inputs = fs::read_to_string(inputs)
+            .with_context(|| format!("Unable to read inputs from {inputs}"))?;
+
+        let (done, mut output): (_, Box<dyn io::Write>) = match settings.output {
+            Some(ref path) if settings.resume => {
+                let previous = match fs::read_to_string(path) {
+                    Ok(previous) => previous,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+                    Err(err) => return Err(err.into()),
+                };
+                let (done, keep) = batch::completed(&previous);
+
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                file.set_len(u64::try_from(keep)?)?;
+                (done, Box::new(file))
+            }
+            Some(ref path) => (HashSet::new(), Box::new(File::create(path)?)),
+            None => (HashSet::new(), Box::new(io::stdout())),
+        };
+
+        let pending = batch::pending(&inputs, &done);
+        if pending.is_empty() {
+            eprintln!("Nothing left to run");
+            return Ok(());
+        }
+
+        let mut messages: Vec<RunBatchRequest> = pending
+            .chunks(constants::BATCH_CHUNK_SIZE)
+            .map(|inputs| RunBatchRequest {
+                inputs: inputs.to_vec(),
+                ..Default::default()
+            })
+            .collect();
+        if let Some(first) = messages.first_mut() {
+            first.run = Some(self.run_request(name, String::new(), options)?);
+            first.parallelism = Some(settings.parallelism);
+            first.rate = settings.rate;
+        }
+
+        let mut results = self
+            .client
+            .run_batch(tokio_stream::iter(messages))
+            .await?
+            .into_inner();
+
+        let mut ran: usize = 0;
+        let mut failed: usize = 0;
+        while let Some(result) = results.message().await.context(
+            "The batch was interrupted, run it again with --resume to pick up from --output",
+        )? {
+            writeln!(output, "{}", batch::result_line(&result))?;
+            output.flush()?;
+
+            ran = ran.saturating_add(1);
+            if result.error.is_some() {
+                failed = failed.saturating_add(1);
+            }
+        }
+
+        eprintln!("Ran {ran} of {} inputs, {failed} failed", pending.len());
+        Ok(())
+    }
+
     ///
     pub async fn handle_run_result(&mut self, execution_id: String) -> anyhow::Result<()> {
         let request = Request::new(GetRunResultRequest { execution_id });
@@ -263,7 +426,10 @@ impl Cli {
             .get(1)
             .ok_or_else(|| anyhow!("Expected an operation name"))?;
 
-        let request = Request::new(GetSerivceRequest { name });
+        let request = Request::new(GetSerivceRequest {
+            name,
+            profile: None,
+        });
         let response = self.client.get_service(request).await?.into_inner();
 
         let service = VersionedServiceTree::parse_from_bytes(&response.raw_service)?;
@@ -289,7 +455,10 @@ impl Cli {
             .get(1)
             .ok_or_else(|| anyhow!("Expected an operation name"))?;
 
-        let request = Request::new(GetSerivceRequest { name });
+        let request = Request::new(GetSerivceRequest {
+            name,
+            profile: None,
+        });
         let response = self.client.get_service(request).await?.into_inner();
 
         let service = VersionedServiceTree::parse_from_bytes(&response.raw_service)?;
@@ -302,6 +471,61 @@ impl Cli {
         Ok(())
     }
 
+    ///
+    pub async fn handle_mock(
+        &mut self,
+        name: String,
+        host: String,
+        port: u16,
+        pages: u32,
+    ) -> anyhow::Result<()> {
+        let request = Request::new(GetSerivceRequest {
+            name: name.clone(),
+            profile: None,
+        });
+        let response = self.client.get_service(request).await?.into_inner();
+
+        let service = VersionedServiceTree::parse_from_bytes(&response.raw_service)?;
+        if !matches!(
+            service.v1().manifest.v2().value,
+            Some(service_manifest_latest::Value::Swagger(_))
+        ) {
+            bail!("Only swagger connectors can be mocked");
+        }
+
+        println!("Mocking {name} on http://{host}:{port}");
+        println!("Set \"baseUrl\": \"http://{host}:{port}\" in its config.json to use it");
+
+        mock::serve(service, host, port, pages, true).await
+    }
+
+    ///
+    pub async fn handle_test(
+        &self,
+        name: String,
+        format: harness::report::Format,
+        output: Option<String>,
+    ) -> anyhow::Result<()> {
+        let locations = harness::Locations {
+            root: self.connectors()?,
+            registry: self.registry()?,
+        };
+        let runtime = Handle::current();
+        let (report, passed) =
+            task::spawn_blocking(move || harness::run(&name, &locations, &runtime, format))
+                .await??;
+
+        match output {
+            Some(path) => fs::write(path, report)?,
+            None => print!("{report}"),
+        }
+
+        if !passed {
+            bail!("Some tests failed");
+        }
+        Ok(())
+    }
+
     ///
     pub async fn handle_input_paths(&mut self, id: String, required: bool) -> anyhow::Result<()> {
         let parts: Vec<_> = id.split('.').collect();
@@ -315,7 +539,10 @@ impl Cli {
             .get(1)
             .ok_or_else(|| anyhow!("Expected an operation name"))?;
 
-        let request = Request::new(GetSerivceRequest { name });
+        let request = Request::new(GetSerivceRequest {
+            name,
+            profile: None,
+        });
         let response = self.client.get_service(request).await?.into_inner();
 
         let service = VersionedServiceTree::parse_from_bytes(&response.raw_service)?;
@@ -348,7 +575,10 @@ impl Cli {
             .get(1)
             .ok_or_else(|| anyhow!("Expected an operation name"))?;
 
-        let request = Request::new(GetSerivceRequest { name });
+        let request = Request::new(GetSerivceRequest {
+            name,
+            profile: None,
+        });
         let response = self.client.get_service(request).await?.into_inner();
 
         let service = VersionedServiceTree::parse_from_bytes(&response.raw_service)?;
@@ -423,6 +653,78 @@ impl Cli {
 
         Ok(())
     }
+
+    /// Shared with apid, which loads the connectors in it.
+    fn connectors(&self) -> anyhow::Result<PathBuf> {
+        match self.config.connectors.path {
+            Some(ref path) => Ok(PathBuf::from(path)),
+            None => Ok(PathBuf::from(env::var("HOME")?).join(constants::DEFAULT_CONNECTORS_PATH)),
+        }
+    }
+
+    /// Shared with apid, which loads whatever gets installed.
+    fn registry(&self) -> anyhow::Result<Registry> {
+        let path = match self.config.registry.path {
+            Some(ref path) => PathBuf::from(path),
+            None => PathBuf::from(env::var("HOME")?).join(constants::DEFAULT_REGISTRY_PATH),
+        };
+
+        Ok(Registry::from(path))
+    }
+
+    ///
+    pub fn handle_install(&self, package: &str, force: bool) -> anyhow::Result<()> {
+        let id = self
+            .registry()?
+            .install(Path::new(package), force)
+            .with_context(|| format!("Unable to install {package}"))?;
+
+        println!("Installed {id}");
+        Ok(())
+    }
+
+    ///
+    pub fn handle_versions(&self, name: &str) -> anyhow::Result<()> {
+        let id =
+            ConnectorId::parse(name).ok_or_else(|| anyhow!("Invalid connector name: {name}"))?;
+
+        for version in self.registry()?.versions(&id)? {
+            println!("{version}");
+        }
+
+        Ok(())
+    }
+}
+
+///
+pub fn handle_pack(
+    dir: &str,
+    maintainer: String,
+    tag: String,
+    name: Option<String>,
+    output: Option<String>,
+) -> anyhow::Result<()> {
+    let dir = Path::new(dir);
+    let name = match name {
+        Some(name) => name,
+        None => dir
+            .canonicalize()?
+            .file_name()
+            .and_then(std::ffi::OsStr::to_str)
+            .ok_or_else(|| anyhow!("Unable to get a name from {}", dir.display()))?
+            .to_owned(),
+    };
+
+    let id = ConnectorId::new(maintainer, name, tag);
+    if id.install_path().is_none() {
+        return Err(anyhow!("Invalid package id: {id}"));
+    }
+
+    let output = output.unwrap_or_else(|| format!("{}-{}.tar.gz", id.name, id.tag));
+    packages::pack(dir, &id, Path::new(&output))?;
+
+    println!("Packed {id} into {output}");
+    Ok(())
 }
 
 ///
@@ -0,0 +1,278 @@
+//! A test case as written in a connector's `tests/` directory and the checks run on its result.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use super::schema;
+use core_entities::service::VersionedServiceTree;
+
+/// One file, run as `<connector>.<operation>` with the input and options given.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TestCase {
+    /// The file's name without its extension when it isn't given.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    ///
+    pub operation: String,
+
+    ///
+    #[serde(default = "empty_object")]
+    pub input: serde_json::Value,
+
+    /// Passed along as the run's options, i.e. `profile` or `limit`.
+    #[serde(default)]
+    pub options: serde_json::Map<String, serde_json::Value>,
+
+    ///
+    #[serde(default)]
+    pub fixtures: Fixtures,
+
+    ///
+    #[serde(default)]
+    pub expect: Expectations,
+}
+
+///
+fn empty_object() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+/// Where the HTTP traffic comes from, the real API when there's neither.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Fixtures {
+    /// A cassette from `apicli run --record`, relative to the test file.
+    #[serde(default)]
+    pub replay: Option<PathBuf>,
+
+    /// Answer from `apicli mock` instead.
+    #[serde(default)]
+    pub mock: bool,
+}
+
+/// Everything given has to hold for the case to pass.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Expectations {
+    /// The whole output, compared as JSON.
+    #[serde(default)]
+    pub output: Option<serde_json::Value>,
+
+    /// `JMESPath` expressions that have to be truthy against the output.
+    #[serde(default)]
+    pub assertions: Vec<String>,
+
+    ///
+    #[serde(default)]
+    pub schema: Option<SchemaMatch>,
+
+    /// The run has to fail with an error containing this instead.
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// What schema the output has to match.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum SchemaMatch {
+    /// `true` for the operation's `200` response in the spec.
+    Response(bool),
+
+    /// One of the spec's named schemas.
+    Named(String),
+}
+
+/// A case and where it was read from, or why it couldn't be.
+pub struct Discovered {
+    ///
+    pub path: PathBuf,
+
+    ///
+    pub case: Result<TestCase, String>,
+}
+
+impl Discovered {
+    ///
+    pub fn name(&self) -> String {
+        match self.case {
+            Ok(TestCase {
+                name: Some(ref name),
+                ..
+            }) => name.clone(),
+            Ok(_) | Err(_) => self
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Every JSON and YAML file in the directory, in the order of their names.
+pub fn discover(dir: &Path) -> anyhow::Result<Vec<Discovered>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(std::ffi::OsStr::to_str)
+                    .is_some_and(|extension| crate::constants::TEST_EXTENSIONS.contains(&extension))
+        })
+        .collect();
+    paths.sort();
+
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let case = parse(&path);
+            Discovered { path, case }
+        })
+        .collect())
+}
+
+///
+fn parse(path: &Path) -> Result<TestCase, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let case: TestCase = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        serde_json::from_str(&contents).map_err(|err| err.to_string())?
+    } else {
+        serde_yaml::from_str(&contents).map_err(|err| err.to_string())?
+    };
+
+    if case.fixtures.mock && case.fixtures.replay.is_some() {
+        return Err("A case can either replay a cassette or use the mock, not both".into());
+    }
+
+    Ok(case)
+}
+
+/// Why a case failed, nothing when it passed.
+pub fn check(
+    case: &TestCase,
+    result: &Result<serde_json::Value, String>,
+    service: &VersionedServiceTree,
+) -> Vec<String> {
+    let expect = &case.expect;
+    let output = match *result {
+        Ok(ref output) => output,
+        Err(ref err) => {
+            return match expect.error {
+                Some(ref expected) if err.contains(expected.as_str()) => vec![],
+                Some(ref expected) => {
+                    vec![format!(
+                        "Expected an error containing {expected:?}, got: {err}"
+                    )]
+                }
+                None => vec![format!("Run failed: {err}")],
+            };
+        }
+    };
+    if let Some(ref expected) = expect.error {
+        return vec![format!(
+            "Expected an error containing {expected:?} but the run succeeded"
+        )];
+    }
+
+    let mut failures = vec![];
+
+    if let Some(ref expected) = expect.output {
+        if expected != output {
+            failures.push(format!(
+                "Output doesn't match, expected {expected} but got {output}"
+            ));
+        }
+    }
+
+    for assertion in &expect.assertions {
+        let holds = jmespath::compile(assertion)
+            .and_then(|expression| expression.search(output))
+            .map(|found| (found.is_truthy(), found.to_string()));
+        match holds {
+            Ok((true, _)) => {}
+            Ok((false, found)) => {
+                failures.push(format!("Assertion failed: {assertion} was {found}"));
+            }
+            Err(err) => failures.push(format!("Invalid assertion {assertion}: {err}")),
+        }
+    }
+
+    if let Some(ref expected) = expect.schema {
+        match schema::expected(service, &case.operation, expected) {
+            Ok(Some(expected)) => failures.extend(
+                schema::validate(output, &expected, &service.v1().commonApi.schemas)
+                    .into_iter()
+                    .map(|problem| format!("Schema mismatch at {problem}")),
+            ),
+            Ok(None) => {}
+            Err(err) => failures.push(err),
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn case(expect: serde_json::Value) -> TestCase {
+        serde_json::from_value(serde_json::json!({
+            "operation": "list",
+            "expect": expect
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_checks_expectations() {
+        let service = VersionedServiceTree::new();
+        let output = Ok(serde_json::json!({ "items": [1, 2, 3] }));
+
+        let passing = case(serde_json::json!({
+            "output": { "items": [1, 2, 3] },
+            "assertions": ["length(items) == `3`"]
+        }));
+        assert!(check(&passing, &output, &service).is_empty());
+
+        let failing = case(serde_json::json!({
+            "output": { "items": [] },
+            "assertions": ["length(items) > `3`", "items[?"]
+        }));
+        let failures = check(&failing, &output, &service);
+        assert_eq!(3, failures.len());
+        assert!(failures[1].starts_with("Assertion failed: length(items) > `3` was false"));
+        assert!(failures[2].starts_with("Invalid assertion items[?"));
+    }
+
+    #[test]
+    fn test_expected_errors() {
+        let service = VersionedServiceTree::new();
+        let expected = case(serde_json::json!({ "error": "status 404" }));
+
+        let failed = Err("Request failed with status 404: {}".to_owned());
+        assert!(check(&expected, &failed, &service).is_empty());
+        assert_eq!(
+            1,
+            check(&expected, &Ok(serde_json::Value::Null), &service).len()
+        );
+
+        let unexpected = case(serde_json::json!({}));
+        assert_eq!(
+            vec!["Run failed: Request failed with status 404: {}".to_owned()],
+            check(&unexpected, &failed, &service)
+        );
+    }
+}
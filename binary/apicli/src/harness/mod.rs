@@ -0,0 +1,360 @@
+//! `apicli test`, a connector's test cases run through an engine of apicli's own.
+//!
+//! Cases live in the connector's `tests/` directory, one JSON or YAML file each (see [`case`]).
+//! The connector and the services it refers to are loaded from disk the way apid loads them and
+//! runs go through the same runners, so only the HTTP fixtures differ from `apicli run`. Cases
+//! that use the mock go last, once the connector's base URL has been pointed at it.
+
+mod case;
+pub mod report;
+mod schema;
+
+extern crate alloc;
+use alloc::sync::Arc;
+
+use core::{panic::AssertUnwindSafe, time::Duration};
+use std::{
+    collections::HashSet,
+    net::{TcpListener, TcpStream},
+    panic,
+    path::{Component, Path, PathBuf},
+    sync::{PoisonError, RwLock},
+    thread,
+    time::Instant,
+};
+
+use anyhow::{anyhow, bail, Context as _};
+use core_entities::service::VersionedServiceTree;
+use execution_engine::{
+    services::{EngineInputContext, EngineLookup},
+    Engine,
+};
+use in_memory_storage::{repo::InMemoryRepository, OperationRepos};
+use local_file_loader::{bundle::ArchiveFetcher, packages::Registry, LocalFileFetcher};
+use remote_file_loader::HttpFetcher;
+use service_loader::{
+    identity::ConnectorId, routing::CompositeFetcher, LoaderOutput as _, ServiceLoader,
+};
+use tokio::runtime::Handle;
+
+use crate::{constants, mock};
+use case::Discovered;
+use report::{CaseResult, Format};
+
+/// Where connectors are looked for, the same places apid looks.
+pub struct Locations {
+    ///
+    pub root: PathBuf,
+
+    ///
+    pub registry: Registry,
+}
+
+/// Runs every case of a connector, by name or by the path to its directory. Returns the report
+/// and whether everything passed.
+///
+/// Blocks, the engine can't be driven from inside the async runtime. `runtime` is only used to
+/// serve the mock.
+pub fn run(
+    name: &str,
+    locations: &Locations,
+    runtime: &Handle,
+    format: Format,
+) -> anyhow::Result<(String, bool)> {
+    let (connector, dir) = resolve(name, locations)?;
+
+    let tests = dir.join(constants::TESTS_DIR);
+    let cases = case::discover(&tests)
+        .with_context(|| format!("Unable to read tests from {}", tests.display()))?;
+    if cases.is_empty() {
+        bail!("No tests in {}", tests.display());
+    }
+
+    let mut repos = OperationRepos::new(
+        Box::new(InMemoryRepository::new()),
+        Box::new(InMemoryRepository::new()),
+    );
+    load(&connector, &dir, locations, &mut repos)?;
+    let service = repos
+        .services
+        .get(&connector)
+        .ok_or_else(|| anyhow!("{connector} didn't load"))?;
+    let lookup = Arc::clone(&repos.lookup);
+    let engine = construct_engine(lookup)?;
+
+    let (mocked, direct): (Vec<_>, Vec<_>) = cases
+        .iter()
+        .enumerate()
+        .partition(|&(_, found)| found.case.as_ref().is_ok_and(|found| found.fixtures.mock));
+
+    let mut results = vec![];
+    for (index, found) in direct {
+        results.push((index, run_case(&engine, &connector, found, &service, index)));
+    }
+
+    if !mocked.is_empty() {
+        let url = start_mock(runtime, &service)?;
+        point_at(&mut repos, &connector, &service, &url)?;
+
+        for (index, found) in mocked {
+            results.push((index, run_case(&engine, &connector, found, &service, index)));
+        }
+    }
+
+    results.sort_by_key(|&(index, _)| index);
+    let results: Vec<_> = results.into_iter().map(|(_, result)| result).collect();
+    let passed = results.iter().all(|result| result.failures.is_empty());
+
+    Ok((report::render(format, &connector, &results), passed))
+}
+
+/// The connector's name and directory, bundles have nowhere to keep tests. Anything that
+/// isn't a connector name or id is taken as a path.
+fn resolve(name: &str, locations: &Locations) -> anyhow::Result<(String, PathBuf)> {
+    let mut components = Path::new(name).components();
+    let is_name = ConnectorId::parse(name).is_some_and(|id| id.is_versioned())
+        || matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        );
+
+    if let Some(dir) = locate(locations, name).filter(|_| is_name) {
+        if !dir.is_dir() {
+            bail!("{name} is a bundle, tests can only be run from a directory");
+        }
+        return Ok((name.to_owned(), dir));
+    }
+
+    let dir = Path::new(name)
+        .canonicalize()
+        .with_context(|| format!("No connector named {name}"))?;
+    let connector = dir
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .filter(|_| dir.is_dir())
+        .ok_or_else(|| anyhow!("{name} isn't a connector directory"))?;
+
+    Ok((connector, dir))
+}
+
+/// Same as apid, versioned connectors are only ever in the registry and a directory in the
+/// root wins over a bundle.
+fn locate(locations: &Locations, service: &str) -> Option<PathBuf> {
+    if let Some(id) = ConnectorId::parse(service).filter(ConnectorId::is_versioned) {
+        return locations.registry.locate(&id);
+    }
+
+    let dir = locations.root.join(service);
+    if dir.is_dir() {
+        return Some(dir);
+    }
+
+    ArchiveFetcher::locate(&locations.root, service)
+}
+
+/// The connector along with everything it refers to, and what those refer to. A reference
+/// that can't be loaded is left broken so only the cases that need it fail.
+fn load(
+    connector: &str,
+    dir: &Path,
+    locations: &Locations,
+    repos: &mut OperationRepos,
+) -> anyhow::Result<()> {
+    let loader = ServiceLoader::new();
+    let remote = HttpFetcher::new()?;
+
+    let mut pending = vec![connector.to_owned()];
+    let mut seen = HashSet::new();
+    while let Some(service) = pending.pop() {
+        if !seen.insert(service.clone()) {
+            continue;
+        }
+
+        if service == connector {
+            load_from(&loader, &remote, &service, dir, repos)
+                .with_context(|| format!("Unable to load {connector}"))?;
+        } else {
+            let Some(path) = locate(locations, &service) else {
+                continue;
+            };
+            if let Err(err) = load_from(&loader, &remote, &service, &path, repos) {
+                eprintln!("Unable to load {service}: {err}");
+                continue;
+            }
+        }
+
+        pending.extend(
+            repos
+                .dependencies
+                .references(&service)
+                .iter()
+                .map(|reference| reference.service.clone()),
+        );
+    }
+
+    repos.publish();
+    Ok(())
+}
+
+///
+fn load_from(
+    loader: &ServiceLoader,
+    remote: &HttpFetcher,
+    service: &str,
+    path: &Path,
+    repos: &mut OperationRepos,
+) -> anyhow::Result<()> {
+    if path.is_dir() {
+        let local = LocalFileFetcher::from(path.to_path_buf());
+        loader.load(service, &with_remote(&local, remote), repos, true, false)?;
+    } else {
+        let bundle = ArchiveFetcher::open(path)?;
+        loader.load(service, &with_remote(&bundle, remote), repos, true, false)?;
+    }
+
+    Ok(())
+}
+
+/// References to remote documents are fetched alongside.
+fn with_remote<'fetcher, R: std::io::Read + 'fetcher>(
+    fetcher: &'fetcher dyn service_loader::Fetcher<R>,
+    remote: &'fetcher HttpFetcher,
+) -> CompositeFetcher<'fetcher> {
+    constants::REMOTE_PREFIXES
+        .iter()
+        .fold(CompositeFetcher::new(fetcher), |fetcher, prefix| {
+            fetcher.route(prefix, remote)
+        })
+}
+
+/// The runners apid registers, apart from user input since nobody is there to answer.
+fn construct_engine(
+    lookup: Arc<dyn EngineLookup + Send + Sync>,
+) -> anyhow::Result<Arc<RwLock<Engine>>> {
+    let engine = Arc::new(RwLock::new(Engine::new(lookup)));
+
+    #[cfg(feature = "python")]
+    let py_runner = python_runner::PyActionRunner::new(Arc::clone(&engine));
+
+    #[cfg(feature = "javascript")]
+    let js_runner = javascript_runner::JsActionRunner::new(Arc::clone(&engine));
+
+    #[cfg(feature = "wrapper")]
+    let api_wrapper = filtered_runner::APIWrapper::new(Arc::clone(&engine));
+
+    {
+        let mut engine = engine
+            .write()
+            .map_err(|err| anyhow!("Unable to setup execution engine...: {err}"))?;
+        engine.register_connector(Box::new(api_caller::APICaller::new()));
+
+        #[cfg(feature = "python")]
+        engine.register_language(constants::PYTHON_LANG, Box::new(py_runner));
+
+        #[cfg(feature = "javascript")]
+        engine.register_language(constants::JAVASCRIPT_LANG, Box::new(js_runner));
+
+        #[cfg(feature = "wrapper")]
+        engine.register_filtered_runner(Box::new(api_wrapper));
+    };
+
+    Ok(engine)
+}
+
+/// A free port is picked up front since the mock has to be told which one to use.
+fn start_mock(runtime: &Handle, service: &VersionedServiceTree) -> anyhow::Result<String> {
+    let host = constants::DEFAULT_MOCK_HOST;
+    let port = TcpListener::bind((host, 0))?.local_addr()?.port();
+
+    let serving = runtime.spawn(mock::serve(
+        service.clone(),
+        host.to_owned(),
+        port,
+        constants::DEFAULT_MOCK_PAGES,
+        false,
+    ));
+
+    for _ in 0..constants::MOCK_STARTUP_ATTEMPTS {
+        if TcpStream::connect((host, port)).is_ok() {
+            return Ok(format!("http://{host}:{port}"));
+        }
+        if serving.is_finished() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(constants::MOCK_STARTUP_INTERVAL_MS));
+    }
+
+    bail!("The mock server didn't start on port {port}")
+}
+
+/// Every environment too, so cases that pick one still end up at the mock.
+fn point_at(
+    repos: &mut OperationRepos,
+    connector: &str,
+    service: &VersionedServiceTree,
+    url: &str,
+) -> anyhow::Result<()> {
+    let mut mocked = service.clone();
+    let api = mocked.mut_v1().commonApi.mut_or_insert_default();
+    api.set_basePath(url.to_owned());
+    api.environments
+        .values_mut()
+        .for_each(|environment| url.clone_into(environment));
+
+    repos.handle_service(connector, mocked)?;
+    repos.publish();
+    Ok(())
+}
+
+/// A panicking run fails its case rather than the whole suite.
+fn run_case(
+    engine: &RwLock<Engine>,
+    connector: &str,
+    found: &Discovered,
+    service: &VersionedServiceTree,
+    index: usize,
+) -> CaseResult {
+    let name = found.name();
+    let case = match found.case {
+        Ok(ref case) => case,
+        Err(ref err) => {
+            return CaseResult {
+                name,
+                time: Duration::ZERO,
+                failures: vec![format!("Invalid test file {}: {err}", found.path.display())],
+            };
+        }
+    };
+
+    let mut options = case.options.clone();
+    if let Some(ref replay) = case.fixtures.replay {
+        let cassette = found
+            .path
+            .parent()
+            .map_or_else(|| replay.clone(), |dir| dir.join(replay));
+        options.insert(
+            constants::REPLAY_OPTION.to_owned(),
+            cassette.to_string_lossy().into_owned().into(),
+        );
+    }
+
+    let context =
+        EngineInputContext::new(None, format!("test-{}-{index}", std::process::id()), false);
+    let identifier = format!("{connector}.{}", case.operation);
+
+    let started = Instant::now();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let engine = engine.read().unwrap_or_else(PoisonError::into_inner);
+        engine
+            .run(&identifier, case.input.clone(), options.into(), &context)
+            .map_err(|err| err.to_string())
+    }))
+    .unwrap_or_else(|_| Err("The run panicked".into()));
+    let time = started.elapsed();
+
+    CaseResult {
+        name,
+        time,
+        failures: case::check(case, &result, service),
+    }
+}
@@ -0,0 +1,160 @@
+//! Results written as TAP for people and `JUnit` XML for CI.
+
+use core::{fmt::Write as _, time::Duration};
+
+/// How `apicli test` reports.
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Format {
+    ///
+    Tap,
+
+    ///
+    Junit,
+}
+
+/// How a case went, it passed when there are no failures.
+pub struct CaseResult {
+    ///
+    pub name: String,
+
+    ///
+    pub time: Duration,
+
+    ///
+    pub failures: Vec<String>,
+}
+
+/// The report for every case of a connector.
+pub fn render(format: Format, suite: &str, results: &[CaseResult]) -> String {
+    match format {
+        Format::Tap => tap(results),
+        Format::Junit => junit(suite, results),
+    }
+}
+
+/// Failures are YAML diagnostics under the case, quoted as JSON strings which YAML reads too.
+fn tap(results: &[CaseResult]) -> String {
+    let mut report = format!("TAP version 13\n1..{}\n", results.len());
+
+    for (index, result) in results.iter().enumerate() {
+        let number = index.saturating_add(1);
+        if result.failures.is_empty() {
+            let _ok = writeln!(report, "ok {number} - {}", result.name);
+            continue;
+        }
+
+        let _ok = writeln!(report, "not ok {number} - {}", result.name);
+        report.push_str("  ---\n  failures:\n");
+        for failure in &result.failures {
+            let failure = serde_json::Value::from(failure.as_str());
+            let _ok = writeln!(report, "    - {failure}");
+        }
+        report.push_str("  ...\n");
+    }
+
+    report
+}
+
+///
+fn junit(suite: &str, results: &[CaseResult]) -> String {
+    let failed = results
+        .iter()
+        .filter(|result| !result.failures.is_empty())
+        .count();
+    let total: Duration = results.iter().map(|result| result.time).sum();
+
+    let mut report = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ok = writeln!(
+        report,
+        "<testsuites tests=\"{}\" failures=\"{failed}\" time=\"{:.3}\">",
+        results.len(),
+        total.as_secs_f64()
+    );
+    let _ok = writeln!(
+        report,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failed}\" time=\"{:.3}\">",
+        escape(suite),
+        results.len(),
+        total.as_secs_f64()
+    );
+
+    for result in results {
+        let _ok = write!(
+            report,
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape(&result.name),
+            escape(suite),
+            result.time.as_secs_f64()
+        );
+
+        match result.failures.first() {
+            None => report.push_str("/>\n"),
+            Some(first) => {
+                let _ok = writeln!(
+                    report,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    escape(first),
+                    escape(&result.failures.join("\n"))
+                );
+            }
+        }
+    }
+
+    report.push_str("  </testsuite>\n</testsuites>\n");
+    report
+}
+
+///
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|character| match character {
+            '&' => "&amp;".into(),
+            '<' => "&lt;".into(),
+            '>' => "&gt;".into(),
+            '"' => "&quot;".into(),
+            '\'' => "&apos;".into(),
+            _ => character.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn results() -> Vec<CaseResult> {
+        vec![
+            CaseResult {
+                name: "lists items".into(),
+                time: Duration::from_millis(20),
+                failures: vec![],
+            },
+            CaseResult {
+                name: "gets <one>".into(),
+                time: Duration::from_millis(5),
+                failures: vec!["Run failed: \"nope\"".into()],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_reports() {
+        assert_eq!(
+            "TAP version 13\n1..2\nok 1 - lists items\nnot ok 2 - gets <one>\n  ---\n  failures:\n    - \"Run failed: \\\"nope\\\"\"\n  ...\n",
+            render(Format::Tap, "acme", &results())
+        );
+
+        let junit = render(Format::Junit, "acme", &results());
+        assert!(
+            junit.contains("<testsuite name=\"acme\" tests=\"2\" failures=\"1\" time=\"0.025\">")
+        );
+        assert!(
+            junit.contains("<testcase name=\"lists items\" classname=\"acme\" time=\"0.020\"/>")
+        );
+        assert!(junit.contains(
+            "<testcase name=\"gets &lt;one&gt;\" classname=\"acme\" time=\"0.005\">\n      <failure message=\"Run failed: &quot;nope&quot;\">"
+        ));
+    }
+}
@@ -0,0 +1,244 @@
+//! Checks a run's output against a schema from the connector's spec.
+//!
+//! Types, required properties, enums, items and additional properties are checked. Optional
+//! properties can be null, `anyOf` and `oneOf` pass when any of their schemas does.
+
+use std::collections::HashMap;
+
+use core_entities::service::{
+    schema, schema_object::additional_properties, schema_object::SchemaType,
+    service_manifest_latest, Schema, SchemaObject, VersionedServiceTree,
+};
+
+use super::case::SchemaMatch;
+use crate::constants;
+
+/// The schema a case asked for, `None` when it asked for none at all.
+pub fn expected(
+    service: &VersionedServiceTree,
+    operation: &str,
+    expected: &SchemaMatch,
+) -> Result<Option<Schema>, String> {
+    let api = &service.v1().commonApi;
+
+    match *expected {
+        SchemaMatch::Response(false) => Ok(None),
+        // Keyed by their reference, a name on its own is looked for where specs keep them
+        SchemaMatch::Named(ref name) => api
+            .schemas
+            .get(name)
+            .or_else(|| {
+                constants::SCHEMA_PREFIXES
+                    .iter()
+                    .find_map(|prefix| api.schemas.get(&format!("{prefix}{name}")))
+            })
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| format!("The spec has no schema named {name}")),
+        SchemaMatch::Response(true) => {
+            if !matches!(
+                service.v1().manifest.v2().value,
+                Some(service_manifest_latest::Value::Swagger(_))
+            ) {
+                return Err("Only swagger operations have a response schema".into());
+            }
+
+            let responses = &api
+                .operations
+                .get(operation)
+                .ok_or_else(|| format!("The spec has no operation {operation}"))?
+                .apiResponses
+                .apiResponses;
+
+            responses
+                .get(constants::SUCCESS_RESPONSE)
+                .and_then(|response| {
+                    let mut content: Vec<_> = response.content.iter().collect();
+                    content.sort_by(|left, right| left.0.cmp(right.0));
+                    content
+                        .into_iter()
+                        .find(|&(media_type, _)| media_type.contains("json"))
+                })
+                .map(|(_, media_type)| Some(media_type.schema.get_or_default().clone()))
+                .ok_or_else(|| format!("{operation} has no JSON 200 response in the spec"))
+        }
+    }
+}
+
+/// Everything wrong with the value, each prefixed with where it is.
+pub fn validate(
+    value: &serde_json::Value,
+    schema: &Schema,
+    types: &HashMap<String, Schema>,
+) -> Vec<String> {
+    let mut problems = vec![];
+    check(value, schema, types, "$", 0, &mut problems);
+    problems
+}
+
+///
+fn check(
+    value: &serde_json::Value,
+    schema: &Schema,
+    types: &HashMap<String, Schema>,
+    path: &str,
+    depth: usize,
+    problems: &mut Vec<String>,
+) {
+    // References that only lead to each other never get any closer to the value
+    if depth > constants::MAX_SCHEMA_DEPTH {
+        return;
+    }
+    let depth = depth.saturating_add(1);
+
+    match schema.value {
+        Some(schema::Value::Ref(ref name)) => match types.get(name) {
+            Some(schema) => check(value, schema, types, path, depth, problems),
+            None => problems.push(format!("{path}: unknown schema {name}")),
+        },
+        Some(schema::Value::SchemaObject(ref object)) => {
+            check_object(value, object, types, path, depth, problems);
+        }
+        Some(schema::Value::AllOf(ref composed)) => {
+            for schema in &composed.schema {
+                check(value, schema, types, path, depth, problems);
+            }
+        }
+        Some(schema::Value::AnyOf(ref composed) | schema::Value::OneOf(ref composed)) => {
+            let matched = composed.schema.iter().any(|schema| {
+                let mut alternative = vec![];
+                check(value, schema, types, path, depth, &mut alternative);
+                alternative.is_empty()
+            });
+            if !matched && !composed.schema.is_empty() {
+                problems.push(format!("{path}: matches none of the allowed schemas"));
+            }
+        }
+        Some(_) | None => {}
+    }
+}
+
+///
+fn check_object(
+    value: &serde_json::Value,
+    schema: &SchemaObject,
+    types: &HashMap<String, Schema>,
+    path: &str,
+    depth: usize,
+    problems: &mut Vec<String>,
+) {
+    let expected = schema.type_.enum_value_or_default();
+    let matches = match expected {
+        SchemaType::SCHEMA_TYPE_NONE => true,
+        SchemaType::STRING => value.is_string(),
+        SchemaType::NUMBER => value.is_number(),
+        SchemaType::INTEGER => value.is_i64() || value.is_u64(),
+        SchemaType::BOOLEAN => value.is_boolean(),
+        SchemaType::OBJECT => value.is_object(),
+        SchemaType::ARRAY => value.is_array(),
+    };
+    if !matches {
+        problems.push(format!("{path}: expected {expected:?} but got {value}"));
+        return;
+    }
+
+    match *value {
+        serde_json::Value::String(ref text)
+            if !schema.possibleValues.is_empty() && !schema.possibleValues.contains(text) =>
+        {
+            problems.push(format!(
+                "{path}: {text:?} isn't one of {}",
+                schema.possibleValues.join(", ")
+            ));
+        }
+        serde_json::Value::Object(ref fields) => {
+            for required in &schema.required {
+                if !fields.contains_key(required) {
+                    problems.push(format!("{path}: missing required property {required}"));
+                }
+            }
+
+            for (name, field) in fields {
+                let field_path = format!("{path}.{name}");
+                match schema.properties.get(name) {
+                    Some(_) if field.is_null() && !schema.required.contains(name) => {}
+                    Some(property) => check(field, property, types, &field_path, depth, problems),
+                    None => match schema.additionalProperties.type_ {
+                        Some(additional_properties::Type::Schema(ref additional)) => {
+                            check(field, additional, types, &field_path, depth, problems);
+                        }
+                        Some(additional_properties::Type::Boolean(false)) => {
+                            problems.push(format!("{field_path}: isn't an allowed property"));
+                        }
+                        Some(_) | None => {}
+                    },
+                }
+            }
+        }
+        serde_json::Value::Array(ref items) => {
+            if let Some(item_schema) = schema.items.as_ref() {
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = format!("{path}[{index}]");
+                    check(item, item_schema, types, &item_path, depth, problems);
+                }
+            }
+        }
+        serde_json::Value::Null
+        | serde_json::Value::Bool(_)
+        | serde_json::Value::Number(_)
+        | serde_json::Value::String(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn object(type_: SchemaType) -> SchemaObject {
+        let mut object = SchemaObject::new();
+        object.type_ = type_.into();
+        object
+    }
+
+    fn schema(object: SchemaObject) -> Schema {
+        Schema {
+            value: Some(schema::Value::SchemaObject(object)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validates_against_referenced_schemas() {
+        let mut item = object(SchemaType::OBJECT);
+        item.required = vec!["id".into()];
+        item.properties
+            .insert("id".into(), schema(object(SchemaType::INTEGER)));
+        let mut status = object(SchemaType::STRING);
+        status.possibleValues = vec!["open".into(), "closed".into()];
+        item.properties.insert("status".into(), schema(status));
+
+        let types = HashMap::from([("Item".to_owned(), schema(item))]);
+        let mut list = object(SchemaType::ARRAY);
+        list.items = Some(Schema {
+            value: Some(schema::Value::Ref("Item".into())),
+            ..Default::default()
+        })
+        .into();
+        let list = schema(list);
+
+        let valid = serde_json::json!([{ "id": 1, "status": "open" }, { "id": 2, "status": null }]);
+        assert!(validate(&valid, &list, &types).is_empty());
+
+        let invalid = serde_json::json!([{ "id": "1" }, { "status": "gone" }]);
+        assert_eq!(
+            vec![
+                "$[0].id: expected INTEGER but got \"1\"",
+                "$[1]: missing required property id",
+                "$[1].status: \"gone\" isn't one of open, closed",
+            ],
+            validate(&invalid, &list, &types)
+        );
+    }
+}
@@ -15,14 +15,18 @@
     clippy::ref_patterns,
 
     clippy::min_ident_chars,
+    clippy::print_stderr,
 )]
 
 //!
 
+mod batch;
 mod commands;
 mod config;
 mod constants;
 mod engine;
+mod harness;
+mod mock;
 mod path;
 mod stub;
 mod template;
@@ -44,7 +48,7 @@ async fn main() -> anyhow::Result<()> {
 
     dotenv().ok();
     let cli = commands::Cli::parse();
-    let mut engine = engine::Cli::init().await?;
+    let mut engine = engine::Cli::init()?;
     cli.command.execute(&mut engine).await?;
 
     Ok(())
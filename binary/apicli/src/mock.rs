@@ -0,0 +1,749 @@
+//! `apicli mock`, a local server answering a connector's operations with examples from its spec.
+//!
+//! Bodies are the same stubs `apicli output-stub` prints. Lists are split into pages the way the
+//! connector's pagination expects, the last of them short so that callers going by page size stop
+//! too, and requests missing a required parameter or body get a `400` saying what's wrong.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc};
+
+use std::{collections::HashMap, io::Cursor};
+
+use core_entities::service::{
+    operation::HttpMethodType,
+    pagination::{self, extended_path, ExtendedPath},
+    parameter::InType,
+    schema,
+    schema_object::SchemaType,
+    Operation, Parameter, VersionedServiceTree,
+};
+use rocket::{
+    data::ToByteUnit as _,
+    http::{ContentType, Method, Status},
+    route::{Handler, Outcome},
+    Data, Request, Route,
+};
+
+use crate::{constants, stub::get_output};
+
+/// A request as the mock sees it.
+struct MockRequest {
+    ///
+    method: String,
+
+    /// Decoded.
+    path: Vec<String>,
+
+    ///
+    query: HashMap<String, String>,
+
+    /// Keyed by the lowercased header name.
+    headers: HashMap<String, String>,
+
+    ///
+    body: String,
+}
+
+///
+struct MockResponse {
+    ///
+    status: u16,
+
+    ///
+    headers: Vec<(String, String)>,
+
+    ///
+    body: serde_json::Value,
+}
+
+impl MockResponse {
+    ///
+    fn error(status: u16, message: String, problems: Vec<String>) -> Self {
+        let mut body = serde_json::Map::new();
+        body.insert("error".into(), message.into());
+        if !problems.is_empty() {
+            body.insert("problems".into(), problems.into());
+        }
+
+        Self {
+            status,
+            headers: vec![],
+            body: body.into(),
+        }
+    }
+}
+
+/// Starts the server and runs until it's stopped, `log` prints each request to stderr.
+pub async fn serve(
+    service: VersionedServiceTree,
+    host: String,
+    port: u16,
+    pages: u32,
+    log: bool,
+) -> anyhow::Result<()> {
+    let handler = MockHandler {
+        service: Arc::new(service),
+        pages,
+        log,
+    };
+
+    let routes: Vec<Route> = [
+        Method::Get,
+        Method::Post,
+        Method::Put,
+        Method::Patch,
+        Method::Delete,
+        Method::Head,
+        Method::Options,
+    ]
+    .into_iter()
+    .map(|method| Route::new(method, "/<path..>", handler.clone()))
+    .collect();
+
+    let figment = rocket::Config::figment()
+        .merge(("address", host))
+        .merge(("port", port))
+        .merge(("log_level", "off"));
+
+    let _rocket = rocket::custom(figment).mount("/", routes).launch().await?;
+
+    Ok(())
+}
+
+///
+#[derive(Clone)]
+struct MockHandler {
+    ///
+    service: Arc<VersionedServiceTree>,
+
+    ///
+    pages: u32,
+
+    ///
+    log: bool,
+}
+
+#[rocket::async_trait]
+impl Handler for MockHandler {
+    async fn handle<'req>(&self, request: &'req Request<'_>, data: Data<'req>) -> Outcome<'req> {
+        let body = data
+            .open(constants::MOCK_BODY_LIMIT.mebibytes())
+            .into_string()
+            .await
+            .map(rocket::data::Capped::into_inner)
+            .unwrap_or_default();
+
+        let mock_request = MockRequest {
+            method: request.method().as_str().to_owned(),
+            path: request
+                .uri()
+                .path()
+                .segments()
+                .map(ToOwned::to_owned)
+                .collect(),
+            query: request
+                .uri()
+                .query()
+                .map(|query| {
+                    query
+                        .segments()
+                        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            headers: request
+                .headers()
+                .iter()
+                .map(|header| {
+                    (
+                        header.name().as_str().to_lowercase(),
+                        header.value().to_owned(),
+                    )
+                })
+                .collect(),
+            body,
+        };
+
+        let response = respond(&self.service, self.pages, &mock_request);
+        if self.log {
+            eprintln!(
+                "{} {} -> {}",
+                mock_request.method,
+                request.uri(),
+                response.status
+            );
+        }
+
+        let body = response.body.to_string();
+        let mut builder = rocket::Response::build();
+        builder
+            .status(Status::new(response.status))
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body));
+        for (name, value) in response.headers {
+            builder.raw_header(name, value);
+        }
+
+        Outcome::Success(builder.finalize())
+    }
+}
+
+/// Picks the operation, checks the request against it and answers with its example.
+fn respond(service: &VersionedServiceTree, pages: u32, request: &MockRequest) -> MockResponse {
+    let api = &service.v1().commonApi;
+    let operations: BTreeMap<_, _> = api.operations.iter().collect();
+
+    // The most specific template wins, matched against the end of the path so that it doesn't
+    // matter whether the base URL we're given keeps the spec's prefix (i.e. `/v1`)
+    let found = operations
+        .into_iter()
+        .filter(|&(_, operation)| {
+            method_name(operation.method.enum_value_or_default()) == request.method
+                && matches(&operation.path, &request.path)
+        })
+        .max_by_key(|&(_, operation)| specificity(&operation.path));
+
+    let Some((name, operation)) = found else {
+        return MockResponse::error(
+            constants::MOCK_NOT_FOUND,
+            format!(
+                "No operation matches {} /{}",
+                request.method,
+                request.path.join("/")
+            ),
+            vec![],
+        );
+    };
+
+    let problems = validate(operation, request);
+    if !problems.is_empty() {
+        return MockResponse::error(
+            constants::MOCK_BAD_REQUEST,
+            format!("Invalid request for {name}"),
+            problems,
+        );
+    }
+
+    match get_output(service, name) {
+        Ok(example) => paginate(operation, example, pages, request),
+        Err(err) => MockResponse::error(constants::MOCK_SERVER_ERROR, err.to_string(), vec![]),
+    }
+}
+
+///
+const fn method_name(method: HttpMethodType) -> &'static str {
+    match method {
+        HttpMethodType::POST => "POST",
+        HttpMethodType::GET => "GET",
+        HttpMethodType::PUT => "PUT",
+        HttpMethodType::PATCH => "PATCH",
+        HttpMethodType::DELETE => "DELETE",
+        HttpMethodType::HEAD => "HEAD",
+        HttpMethodType::OPTIONS => "OPTIONS",
+        HttpMethodType::TRACE => "TRACE",
+        HttpMethodType::HTTP_METHOD_TYPE_NONE => "",
+    }
+}
+
+///
+fn is_variable(segment: &str) -> bool {
+    segment.starts_with('{') && segment.ends_with('}')
+}
+
+///
+fn template_segments(template: &str) -> Vec<&str> {
+    template
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// Whether the path ends with the template, variables match any segment.
+fn matches(template: &str, path: &[String]) -> bool {
+    let template = template_segments(template);
+    let Some(start) = path.len().checked_sub(template.len()) else {
+        return false;
+    };
+
+    path.get(start..).is_some_and(|tail| {
+        template
+            .iter()
+            .zip(tail)
+            .all(|(expected, actual)| is_variable(expected) || expected == actual)
+    })
+}
+
+/// Longer templates first, then the ones with fewer variables.
+fn specificity(template: &str) -> (usize, usize) {
+    let segments = template_segments(template);
+    let literal = segments
+        .iter()
+        .filter(|segment| !is_variable(segment))
+        .count();
+    (segments.len(), literal)
+}
+
+/// Where a parameter is read from, `None` for ones the mock doesn't check.
+fn location(parameter: &Parameter) -> Option<&'static str> {
+    match parameter.in_.enum_value_or_default() {
+        InType::QUERY => Some("query"),
+        InType::HEADER => Some("header"),
+        InType::PATH | InType::COOKIE | InType::HEADERS | InType::IN_TYPE_NONE => None,
+    }
+}
+
+///
+fn parameter_value<'req>(parameter: &Parameter, request: &'req MockRequest) -> Option<&'req str> {
+    match location(parameter) {
+        Some("query") => request.query.get(&parameter.name),
+        Some(_) => request.headers.get(&parameter.name.to_lowercase()),
+        None => None,
+    }
+    .map(String::as_str)
+}
+
+/// Everything wrong with a request, required parameters and bodies along with values that
+/// aren't the type the spec says.
+fn validate(operation: &Operation, request: &MockRequest) -> Vec<String> {
+    let mut problems = vec![];
+
+    for parameter in &operation.parameter {
+        let Some(location) = location(parameter) else {
+            continue;
+        };
+
+        let Some(value) = parameter_value(parameter, request) else {
+            if parameter.required {
+                problems.push(format!(
+                    "Missing required {location} parameter {}",
+                    parameter.name
+                ));
+            }
+            continue;
+        };
+
+        let expected = match parameter.schema.value {
+            Some(schema::Value::SchemaObject(ref schema)) => schema.type_.enum_value_or_default(),
+            Some(_) | None => continue,
+        };
+        let valid = match expected {
+            SchemaType::INTEGER => value.parse::<i64>().is_ok(),
+            SchemaType::NUMBER => value.parse::<f64>().is_ok(),
+            SchemaType::BOOLEAN => value.parse::<bool>().is_ok(),
+            SchemaType::SCHEMA_TYPE_NONE
+            | SchemaType::STRING
+            | SchemaType::OBJECT
+            | SchemaType::ARRAY => true,
+        };
+        if !valid {
+            problems.push(format!(
+                "{location} parameter {} should be {expected:?} but was {value}",
+                parameter.name
+            ));
+        }
+    }
+
+    if operation.requestBody.is_some() {
+        let body = request.body.trim();
+        let json = operation
+            .requestBody
+            .content
+            .keys()
+            .any(|media_type| media_type.contains("json"));
+
+        if body.is_empty() && operation.requestBody.required {
+            problems.push("Missing required request body".into());
+        }
+
+        let parsed =
+            (json && !body.is_empty()).then(|| serde_json::from_str::<serde_json::Value>(body));
+        if let Some(Err(err)) = parsed {
+            problems.push(format!("Request body isn't valid JSON: {err}"));
+        }
+    }
+
+    problems
+}
+
+/// A value from the request by runtime expression (`$request.query.page`) or parameter name.
+fn request_value<'req>(
+    expression: &str,
+    operation: &Operation,
+    request: &'req MockRequest,
+) -> Option<&'req str> {
+    if let Some(name) = expression.strip_prefix("$request.query.") {
+        return request.query.get(name).map(String::as_str);
+    }
+    if let Some(name) = expression.strip_prefix("$request.header.") {
+        return request
+            .headers
+            .get(&name.to_lowercase())
+            .map(String::as_str);
+    }
+
+    match operation
+        .parameter
+        .iter()
+        .find(|parameter| parameter.name == expression)
+    {
+        Some(parameter) => parameter_value(parameter, request),
+        None => request.query.get(expression).map(String::as_str),
+    }
+}
+
+/// Only paths into the body can be written to, `jmesPath` ones are left alone.
+fn pointer(path: &ExtendedPath) -> Option<&str> {
+    match path.value {
+        Some(extended_path::Value::ColumnPath(ref path)) => {
+            Some(path.strip_prefix("$response.body#").unwrap_or(path))
+        }
+        Some(extended_path::Value::JmesPath(_) | _) | None => None,
+    }
+}
+
+/// Sets a value by JSON pointer, adding any objects along the way.
+fn assign(target: &mut serde_json::Value, pointer: &str, value: serde_json::Value) {
+    let mut current = target;
+    for token in pointer.split('/').filter(|token| !token.is_empty()) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+
+        if !current.is_object() && !current.is_array() {
+            *current = serde_json::Value::Object(serde_json::Map::new());
+        }
+        current = match current {
+            &mut serde_json::Value::Array(ref mut items) => {
+                match token
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| items.get_mut(index))
+                {
+                    Some(item) => item,
+                    None => return,
+                }
+            }
+            &mut serde_json::Value::Object(ref mut fields) => {
+                fields.entry(token).or_insert(serde_json::Value::Null)
+            }
+            &mut (serde_json::Value::Null
+            | serde_json::Value::Bool(_)
+            | serde_json::Value::Number(_)
+            | serde_json::Value::String(_)) => return,
+        };
+    }
+
+    *current = value;
+}
+
+/// Percent-encodes a query key or value.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// The same request, on another page.
+fn page_url(request: &MockRequest, page: u32) -> String {
+    let mut query: BTreeMap<_, _> = request
+        .query
+        .iter()
+        .filter(|&(key, _)| key != constants::MOCK_PAGE_PARAM)
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    query.insert(constants::MOCK_PAGE_PARAM.to_owned(), page.to_string());
+
+    let path: Vec<_> = request.path.iter().map(|segment| encode(segment)).collect();
+    let query: Vec<_> = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect();
+
+    format!("/{}?{}", path.join("/"), query.join("&"))
+}
+
+/// Splits the example's results into pages, pointing at the next one however the connector
+/// expects to find it.
+fn paginate(
+    operation: &Operation,
+    example: serde_json::Value,
+    pages: u32,
+    request: &MockRequest,
+) -> MockResponse {
+    let number = |expression: &str| {
+        request_value(expression, operation, request)?
+            .parse::<u32>()
+            .ok()
+    };
+    let mock_page = || {
+        request
+            .query
+            .get(constants::MOCK_PAGE_PARAM)
+            .and_then(|page| page.parse::<u32>().ok())
+            .unwrap_or_default()
+    };
+
+    let (page, limit_param, max_limit, results_path) = match operation.pagination.value {
+        Some(pagination::Value::PageOffset(ref config)) => (
+            number(&config.pageOffsetParam)
+                .unwrap_or_default()
+                .saturating_sub(u32::try_from(config.startPage.value).unwrap_or_default()),
+            config.limitParam.as_str(),
+            config.maxLimit.value,
+            &config.resultsPath,
+        ),
+        Some(pagination::Value::Offset(ref config)) => (
+            number(&config.offsetParam).unwrap_or_default(),
+            config.limitParam.as_str(),
+            config.maxLimit.value,
+            &config.resultsPath,
+        ),
+        Some(pagination::Value::MultiCursor(ref config)) => (
+            config
+                .cursorsParam
+                .first()
+                .and_then(|param| number(param))
+                .unwrap_or_default(),
+            config.limitParam.as_str(),
+            config.maxLimit.value,
+            &config.resultsPath,
+        ),
+        Some(pagination::Value::NextUrl(ref config)) => (
+            mock_page(),
+            config.limitParam.as_str(),
+            config.maxLimit.value,
+            &config.resultsPath,
+        ),
+        Some(pagination::Value::LinkHeader(ref config)) => (
+            mock_page(),
+            config.limitParam.as_str(),
+            config.maxLimit.value,
+            &config.resultsPath,
+        ),
+        Some(pagination::Value::Unpaginated(_) | _) | None => {
+            return MockResponse {
+                status: constants::MOCK_OK,
+                headers: vec![],
+                body: example,
+            };
+        }
+    };
+
+    let size = number(limit_param)
+        .or_else(|| u32::try_from(max_limit).ok().filter(|limit| *limit > 0))
+        .unwrap_or(constants::MOCK_PAGE_SIZE);
+    let last = pages.saturating_sub(1);
+    let count = match page.cmp(&last) {
+        core::cmp::Ordering::Less => size,
+        core::cmp::Ordering::Equal => 1,
+        core::cmp::Ordering::Greater => 0,
+    };
+    let next = (page < last).then(|| page.saturating_add(1));
+
+    // One item from the example stands in for all of them
+    let mut body = example;
+    let results = pointer(results_path).filter(|path| !path.is_empty() && *path != "/");
+    let item = match results {
+        Some(path) => body.pointer(path),
+        None => Some(&body),
+    }
+    .and_then(serde_json::Value::as_array)
+    .and_then(|items| items.first())
+    .cloned()
+    .unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new()));
+    let items = serde_json::Value::Array(
+        core::iter::repeat_n(item, usize::try_from(count).unwrap_or_default()).collect(),
+    );
+    match results {
+        Some(path) => assign(&mut body, path, items),
+        None if pointer(results_path).is_some() || body.is_array() => body = items,
+        None => {}
+    }
+
+    let headers = point_to_next(operation, &mut body, next, request);
+
+    MockResponse {
+        status: constants::MOCK_OK,
+        headers,
+        body,
+    }
+}
+
+/// Leaves the next page wherever the connector looks for it, nowhere on the last page. Returns
+/// the headers to send.
+fn point_to_next(
+    operation: &Operation,
+    body: &mut serde_json::Value,
+    next: Option<u32>,
+    request: &MockRequest,
+) -> Vec<(String, String)> {
+    let mut headers = vec![];
+    match operation.pagination.value {
+        Some(pagination::Value::MultiCursor(ref config)) => {
+            for path in config.cursorsPath.iter().filter_map(pointer) {
+                let cursor = next.map_or(serde_json::Value::Null, |next| next.to_string().into());
+                assign(body, path, cursor);
+            }
+        }
+        Some(pagination::Value::NextUrl(ref config)) => {
+            if let Some(path) = pointer(&config.nextUrlPath) {
+                let url = next.map_or(serde_json::Value::Null, |next| {
+                    page_url(request, next).into()
+                });
+                assign(body, path, url);
+            }
+        }
+        Some(pagination::Value::LinkHeader(ref config)) => {
+            if let Some(next) = next {
+                headers.push((
+                    constants::LINK_HEADER.to_owned(),
+                    format!("<{}>; rel=\"{}\"", page_url(request, next), config.rel),
+                ));
+            }
+        }
+        Some(_) | None => {}
+    }
+
+    headers
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use core_entities::service::{
+        service_manifest_latest, CommonApi, RequestBody, Schema, SchemaObject,
+        ServiceManifestLatest, SwaggerService,
+    };
+
+    fn service(operation: Operation) -> VersionedServiceTree {
+        let mut api = CommonApi::new();
+        api.operations.insert("list".into(), operation);
+
+        let mut manifest = ServiceManifestLatest::new();
+        manifest.value = Some(service_manifest_latest::Value::Swagger(
+            SwaggerService::new(),
+        ));
+
+        let mut service = VersionedServiceTree::new();
+        let tree = service.mut_v1();
+        tree.commonApi = Some(api).into();
+        tree.manifest.mut_or_insert_default().set_v2(manifest);
+        service
+    }
+
+    fn request(method: &str, path: &str, query: &[(&str, &str)]) -> MockRequest {
+        MockRequest {
+            method: method.into(),
+            path: path
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            query: query
+                .iter()
+                .map(|&(key, value)| (key.into(), value.into()))
+                .collect(),
+            headers: HashMap::new(),
+            body: String::new(),
+        }
+    }
+
+    fn list_operation() -> Operation {
+        let mut operation = Operation::new();
+        operation.path = "/accounts/{id}/items".into();
+        operation.method = HttpMethodType::GET.into();
+
+        let mut limit = Parameter::new();
+        limit.name = "limit".into();
+        limit.in_ = InType::QUERY.into();
+        let mut schema = SchemaObject::new();
+        schema.type_ = SchemaType::INTEGER.into();
+        limit.schema = Some(Schema {
+            value: Some(schema::Value::SchemaObject(schema)),
+            ..Default::default()
+        })
+        .into();
+        operation.parameter.push(limit);
+
+        let mut next_url = pagination::NextUrl::new();
+        next_url
+            .nextUrlPath
+            .mut_or_insert_default()
+            .set_columnPath("$response.body#/links/next".into());
+        next_url
+            .resultsPath
+            .mut_or_insert_default()
+            .set_columnPath("$response.body#/data".into());
+        next_url.limitParam = "limit".into();
+        operation
+            .pagination
+            .mut_or_insert_default()
+            .set_nextUrl(next_url);
+
+        operation
+    }
+
+    #[test]
+    fn test_pages_follow_the_pagination_config() {
+        let service = service(list_operation());
+
+        let first = respond(
+            &service,
+            2,
+            &request("GET", "/v1/accounts/7/items", &[("limit", "3")]),
+        );
+        assert_eq!(200, first.status);
+        assert_eq!(3, first.body["data"].as_array().unwrap().len());
+        assert_eq!(
+            "/v1/accounts/7/items?limit=3&mock_page=1",
+            first.body["links"]["next"]
+        );
+
+        let last = respond(
+            &service,
+            2,
+            &request(
+                "GET",
+                "/v1/accounts/7/items",
+                &[("limit", "3"), ("mock_page", "1")],
+            ),
+        );
+        assert_eq!(1, last.body["data"].as_array().unwrap().len());
+        assert_eq!(serde_json::Value::Null, last.body["links"]["next"]);
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let mut operation = list_operation();
+        operation.requestBody = Some(RequestBody {
+            required: true,
+            ..Default::default()
+        })
+        .into();
+        let service = service(operation);
+
+        let response = respond(&service, 2, &request("POST", "/accounts/7/items", &[]));
+        assert_eq!(404, response.status);
+
+        let response = respond(
+            &service,
+            2,
+            &request("GET", "/accounts/7/items", &[("limit", "lots")]),
+        );
+        assert_eq!(400, response.status);
+        assert_eq!(
+            serde_json::json!([
+                "query parameter limit should be INTEGER but was lots",
+                "Missing required request body"
+            ]),
+            response.body["problems"]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Runs for `RunBatch`, every input through the same operation on a fixed set of workers.
+
+extern crate alloc;
+use alloc::sync::Arc;
+
+use core::{panic::AssertUnwindSafe, time::Duration};
+use std::{
+    panic,
+    sync::{Mutex, PoisonError, RwLock},
+    thread,
+    time::Instant,
+};
+
+use engine_entities::engine::{BatchInput, RunBatchResponse};
+use execution_engine::services::EngineInputContext;
+use tokio::sync::mpsc::{Receiver, Sender};
+use tonic::Status;
+use user_input::Signals;
+
+use crate::util;
+
+/// Spaces out when runs start, shared by every worker of a batch.
+pub struct Throttle {
+    /// `None` lets runs start as soon as a worker is free.
+    interval: Option<Duration>,
+
+    /// The earliest the next run can start.
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    /// At most `rate` runs started per second.
+    pub fn new(rate: Option<f64>) -> Result<Self, String> {
+        let interval = rate
+            .map(|rate| {
+                if !rate.is_finite() || rate <= 0.0_f64 {
+                    return Err(format!("Invalid rate {rate}, expected runs per second"));
+                }
+                Duration::try_from_secs_f64(rate.recip()).map_err(|err| err.to_string())
+            })
+            .transpose()?;
+
+        Ok(Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// When a run asking at `now` can start, pushing the one after it back by the interval.
+    fn reserve(&self, now: Instant) -> Instant {
+        let Some(interval) = self.interval else {
+            return now;
+        };
+
+        let mut next = self.next.lock().unwrap_or_else(PoisonError::into_inner);
+        let slot = (*next).max(now);
+        *next = slot.checked_add(interval).unwrap_or(slot);
+        slot
+    }
+
+    /// Blocks until the next run can start.
+    fn wait(&self) {
+        let now = Instant::now();
+        thread::sleep(self.reserve(now).saturating_duration_since(now));
+    }
+}
+
+/// What every run of a batch shares.
+pub struct Batch {
+    ///
+    pub engine: Arc<RwLock<execution_engine::Engine>>,
+
+    ///
+    pub signals: Signals,
+
+    /// The operation every input is run through.
+    pub id: String,
+
+    ///
+    pub options: serde_json::Value,
+
+    ///
+    pub throttle: Throttle,
+}
+
+impl Batch {
+    /// Starts `parallelism` workers that run inputs until there are none left or the client
+    /// stops listening for results.
+    pub fn start(
+        self,
+        parallelism: usize,
+        inputs: Receiver<BatchInput>,
+        results: &Sender<Result<RunBatchResponse, Status>>,
+    ) {
+        let batch = Arc::new(self);
+        let inputs = Arc::new(Mutex::new(inputs));
+
+        for _ in 0..parallelism {
+            let batch = Arc::clone(&batch);
+            let inputs = Arc::clone(&inputs);
+            let results = results.clone();
+
+            thread::spawn(move || batch.work(&inputs, &results));
+        }
+    }
+
+    ///
+    fn work(
+        &self,
+        inputs: &Mutex<Receiver<BatchInput>>,
+        results: &Sender<Result<RunBatchResponse, Status>>,
+    ) {
+        loop {
+            // Only held while waiting for an input, the run itself happens without it
+            let input = inputs
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .blocking_recv();
+            let Some(input) = input else {
+                break;
+            };
+
+            self.throttle.wait();
+            let result = self.run(&input);
+
+            // Client went away, no point in running the rest
+            if results.blocking_send(Ok(result)).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// A panicking run fails its input rather than taking the worker down with it.
+    fn run(&self, input: &BatchInput) -> RunBatchResponse {
+        let execution_id = uuid::Uuid::new_v4().to_string();
+
+        let result = serde_json::from_str(&input.input)
+            .map_err(|err| format!("Invalid input: {err}"))
+            .and_then(|value| {
+                let ctx = EngineInputContext::new(None, execution_id.clone(), false);
+                let engine = self.engine.read().unwrap_or_else(PoisonError::into_inner);
+
+                let in_flight = util::InFlight::start();
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    engine.run(&self.id, value, self.options.clone(), &ctx)
+                }));
+                drop(in_flight);
+
+                match result {
+                    Ok(Ok(output)) => serde_json::to_string(&output).map_err(|err| err.to_string()),
+                    Ok(Err(err)) => Err(err.to_string()),
+                    Err(_) => Err("Execution panicked".to_owned()),
+                }
+            });
+
+        let mut signals = self.signals.lock().unwrap_or_else(PoisonError::into_inner);
+        signals.remove(&execution_id);
+
+        match result {
+            Ok(output) => RunBatchResponse {
+                index: input.index,
+                execution_id,
+                output: Some(output),
+                error: None,
+            },
+            Err(error) => RunBatchResponse {
+                index: input.index,
+                execution_id,
+                output: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use crate::constants;
+    use core_entities::service::VersionedServiceTree;
+    use execution_engine::services::{DataConnectionRunner, DataConnectorBundle};
+    use in_memory_storage::{repo::InMemoryRepository, OperationRepos};
+    use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+    use service_loader::LoaderOutput as _;
+    use std::sync::{MutexGuard, OnceLock};
+
+    /// The recorder is global, so tests reading it take turns.
+    fn metrics() -> (&'static PrometheusHandle, MutexGuard<'static, ()>) {
+        static RECORDER: OnceLock<PrometheusHandle> = OnceLock::new();
+        static SERIAL: Mutex<()> = Mutex::new(());
+
+        let handle = RECORDER.get_or_init(|| {
+            let recorder = PrometheusBuilder::new()
+                .set_buckets(&constants::DURATION_BUCKETS)
+                .unwrap()
+                .build_recorder();
+            let handle = recorder.handle();
+            metrics::set_boxed_recorder(Box::new(recorder)).unwrap();
+            handle
+        });
+
+        (
+            handle,
+            SERIAL.lock().unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+
+    /// Echoes its input back, or panics when asked to.
+    struct Connector;
+
+    impl DataConnectionRunner for Connector {
+        fn run(
+            &self,
+            _: &str,
+            _: &str,
+            _: &DataConnectorBundle,
+            params: serde_json::Value,
+            _: serde_json::Value,
+            _: &EngineInputContext,
+        ) -> execution_engine::error::Result<serde_json::Value> {
+            assert!(params.get("panic").is_none(), "asked to panic");
+            Ok(params)
+        }
+    }
+
+    /// Runs `id` against a single swagger connector called `api`.
+    fn batch(id: &str) -> Batch {
+        let mut service = VersionedServiceTree::new();
+        service
+            .mut_v1()
+            .manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .mut_swagger();
+
+        let mut repos = OperationRepos::new(
+            Box::new(InMemoryRepository::new()),
+            Box::new(InMemoryRepository::new()),
+        );
+        repos.handle_service("api", service).unwrap();
+        repos.publish();
+
+        let mut engine = execution_engine::Engine::new(Arc::clone(&repos.lookup) as _);
+        engine.register_connector(Box::new(Connector));
+
+        Batch {
+            engine: Arc::new(RwLock::new(engine)),
+            signals: Arc::default(),
+            id: id.into(),
+            options: serde_json::json!({}),
+            throttle: Throttle::new(None).unwrap(),
+        }
+    }
+
+    fn input(input: &str) -> BatchInput {
+        BatchInput {
+            index: 1,
+            input: input.into(),
+        }
+    }
+
+    #[test]
+    fn test_execution_metrics() {
+        let (metrics, _serial) = metrics();
+
+        let result = batch("api.list").run(&input("{\"id\":1}"));
+        assert_eq!(Some("[{\"id\":1}]"), result.output.as_deref());
+        assert!(batch("gone.list").run(&input("{}")).error.is_some());
+
+        let rendered = metrics.render();
+        for series in [
+            "apid_executions_total{service=\"api\",operation=\"list\",status=\"completed\"} 1",
+            "apid_executions_total{service=\"gone\",operation=\"list\",status=\"failed\"} 1",
+            "apid_execution_duration_seconds_count{service=\"api\",operation=\"list\"} 1",
+            "apid_executions_in_flight 0",
+        ] {
+            assert!(
+                rendered.lines().any(|line| line == series),
+                "{series} in\n{rendered}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_flight_balances_after_panic() {
+        let (metrics, _serial) = metrics();
+
+        let result = batch("api.list").run(&input("{\"panic\":true}"));
+        assert_eq!(Some("Execution panicked"), result.error.as_deref());
+
+        // Same for a run on its own thread that nothing catches
+        let run = thread::spawn(|| {
+            let _in_flight = util::InFlight::start();
+            panic!("run failed");
+        });
+        assert!(run.join().is_err());
+
+        let rendered = metrics.render();
+        assert!(
+            rendered
+                .lines()
+                .any(|line| line == "apid_executions_in_flight 0"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn test_throttle_rejects_invalid_rates() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(Throttle::new(Some(rate)).is_err(), "{rate}");
+        }
+    }
+
+    #[test]
+    fn test_throttle_spaces_runs() {
+        let throttle = Throttle::new(Some(4.0)).unwrap();
+        let now = Instant::now();
+        let interval = Duration::from_millis(250);
+
+        // Asked for all at once, each starts a quarter of a second after the last
+        let slots: Vec<_> = (0..3).map(|_| throttle.reserve(now)).collect();
+        assert_eq!(vec![now, now + interval, now + interval * 2], slots);
+
+        // Once it's gone quiet, the next run doesn't wait on the ones before it
+        let later = now + interval * 10;
+        assert_eq!(later, throttle.reserve(later));
+        assert_eq!(later + interval, throttle.reserve(later));
+    }
+
+    #[test]
+    fn test_unthrottled() {
+        let throttle = Throttle::new(None).unwrap();
+        let now = Instant::now();
+
+        assert_eq!(now, throttle.reserve(now));
+        assert_eq!(now, throttle.reserve(now));
+    }
+}
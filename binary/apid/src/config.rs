@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::constants;
+
 ///
 #[derive(Serialize, Deserialize)]
 pub struct Configuration {
@@ -13,6 +15,60 @@ pub struct Configuration {
 
     ///
     pub server: ServerConfiguration,
+
+    ///
+    #[serde(default)]
+    pub tracing: TracingConfiguration,
+
+    /// The Prometheus endpoint is only served when this is set.
+    pub metrics: Option<MetricsConfiguration>,
+
+    ///
+    #[serde(default)]
+    pub http: HttpConfiguration,
+}
+
+/// How connectors make calls, a connector's `config.json` can override any of these.
+#[derive(Serialize, Deserialize, Default)]
+pub struct HttpConfiguration {
+    ///
+    pub connect_timeout_ms: Option<u64>,
+
+    /// Covers the whole request, from connecting until the body is read.
+    pub read_timeout_ms: Option<u64>,
+
+    /// Used for both http and https unless one of them has its own.
+    pub proxy: Option<String>,
+
+    ///
+    pub http_proxy: Option<String>,
+
+    ///
+    pub https_proxy: Option<String>,
+
+    /// Comma separated hosts, domains and CIDR blocks that skip the proxy.
+    pub no_proxy: Option<String>,
+
+    /// PEM files trusted on top of the system's roots.
+    #[serde(default)]
+    pub ca_certificates: Vec<String>,
+
+    /// `0` doesn't follow redirects at all.
+    pub max_redirects: Option<u32>,
+
+    /// `true` speaks HTTP/2 from the start, `false` sticks to HTTP/1.
+    pub http2: Option<bool>,
+
+    /// `APICLI/1.0` by default.
+    pub user_agent: Option<String>,
+
+    /// Binary responses are saved here, one directory per execution, instead of being returned
+    /// base64 encoded.
+    pub download_path: Option<String>,
+
+    /// Where responses are cached for connectors that turn it on, `~/.cache/apid/responses` by
+    /// default.
+    pub cache_path: Option<String>,
 }
 
 ///
@@ -20,6 +76,28 @@ pub struct Configuration {
 pub struct ConnectorConfiguration {
     ///
     pub path: Option<String>,
+
+    /// Where compiled connectors are cached between starts, `~/.cache/apid` by default.
+    pub cache_path: Option<String>,
+
+    /// Set to `false` to parse every connector from scratch on each start.
+    #[serde(default = "default_cache")]
+    pub cache: bool,
+
+    /// Where documents connectors reference over HTTP(S) are kept, `~/.cache/apid/remote` by default.
+    pub remote_cache_path: Option<String>,
+
+    /// Set to `true` to only use remote documents that were fetched before.
+    #[serde(default)]
+    pub offline: bool,
+
+    /// Where `apicli install` puts packaged connectors, `~/.apid/registry` by default.
+    pub registry_path: Option<String>,
+}
+
+///
+const fn default_cache() -> bool {
+    true
 }
 
 ///
@@ -30,6 +108,31 @@ pub struct LogConfiguration {
 
     ///
     pub workflow_path: String,
+
+    /// One of `trace`, `debug`, `info`, `warn`, `error` or `off`.
+    #[serde(default = "default_level")]
+    pub level: String,
+
+    /// In bytes, a log file rolls over once it would grow past this. `0` never rolls over on size.
+    #[serde(default)]
+    pub max_size: u64,
+
+    /// One of `never`, `hourly` or `daily`.
+    #[serde(default)]
+    pub rotation: String,
+
+    /// How many rolled over files to keep around for each log, `0` keeps all of them.
+    #[serde(default)]
+    pub max_files: usize,
+
+    /// JSON body fields masked in the API log.
+    #[serde(default)]
+    pub redact_fields: Vec<String>,
+}
+
+///
+fn default_level() -> String {
+    constants::DEFAULT_LOG_LEVEL.to_owned()
 }
 
 ///
@@ -41,3 +144,26 @@ pub struct ServerConfiguration {
     ///
     pub host: String,
 }
+
+/// Spans are only recorded when at least one exporter is set.
+#[derive(Serialize, Deserialize, Default)]
+pub struct TracingConfiguration {
+    /// Reported as `service.name`, defaults to `apid`.
+    pub service_name: Option<String>,
+
+    /// OTLP over HTTP, i.e. `http://localhost:4318/v1/traces`.
+    pub otlp_endpoint: Option<String>,
+
+    /// A local JSON lines file, rotated like the logs.
+    pub path: Option<String>,
+}
+
+///
+#[derive(Serialize, Deserialize)]
+pub struct MetricsConfiguration {
+    ///
+    pub port: u16,
+
+    ///
+    pub host: String,
+}
@@ -8,3 +8,59 @@ pub const PYTHON_LANG: &str = "python";
 
 ///
 pub const JAVASCRIPT_LANG: &str = "js";
+
+/// Pages of results held in memory per streaming execution before the runner blocks.
+pub const STREAM_BUFFER_SIZE: usize = 4;
+
+/// Results of a batch held in memory before its workers block.
+pub const BATCH_BUFFER_SIZE: usize = 64;
+
+/// Workers a single batch can ask for.
+pub const MAX_BATCH_PARALLELISM: u32 = 64;
+
+///
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+///
+pub const DEFAULT_SERVICE_NAME: &str = "apid";
+
+/// Histogram buckets in seconds, from a quick lookup up to a long running workflow.
+pub const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 60.0,
+];
+
+/// Gauge of executions started over RPC that haven't finished yet.
+pub const METRIC_IN_FLIGHT: &str = "apid_executions_in_flight";
+
+/// Counter, labelled by service and result.
+pub const METRIC_RELOADS: &str = "apid_connector_reloads_total";
+
+///
+pub const RELOAD_SUCCESS: &str = "success";
+
+///
+pub const RELOAD_FAILURE: &str = "failure";
+
+/// How often the fallback watcher checks for changes when native notifications aren't available.
+pub const POLL_INTERVAL: core::time::Duration = core::time::Duration::from_secs(1);
+
+/// A file has to stay quiet this long before it gets reloaded, editors tend to write in bursts.
+pub const DEBOUNCE: core::time::Duration = core::time::Duration::from_millis(250);
+
+/// Relative to `$HOME`.
+pub const DEFAULT_CACHE_PATH: &str = ".cache/apid";
+
+/// Relative to `$HOME`, documents referenced over HTTP(S) by connectors.
+pub const DEFAULT_REMOTE_CACHE_PATH: &str = ".cache/apid/remote";
+
+/// Relative to `$HOME`, responses of connectors that cache them.
+pub const DEFAULT_RESPONSE_CACHE_PATH: &str = ".cache/apid/responses";
+
+/// References starting with these are fetched over the network rather than from the connector.
+pub const REMOTE_PREFIXES: [&str; 2] = ["http://", "https://"];
+
+/// Relative to `$HOME`, connectors installed from packages as `<maintainer>/<name>/<tag>`.
+pub const DEFAULT_REGISTRY_PATH: &str = ".apid/registry";
+
+/// Profile credentials are saved under when a connector doesn't have any yet.
+pub const DEFAULT_PROFILE: &str = "default";
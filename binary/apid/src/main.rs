@@ -15,10 +15,12 @@
     clippy::ref_patterns,
 
     clippy::min_ident_chars,
+    clippy::separated_literal_suffix,
 )]
 
 //!
 
+mod batch;
 mod config;
 mod constants;
 mod util;
@@ -26,19 +28,21 @@ mod workers;
 
 extern crate alloc;
 use alloc::sync::Arc;
-use config::Configuration;
+use config::{Configuration, ConnectorConfiguration, HttpConfiguration};
 
 use std::{
     collections::HashMap,
-    env,
-    fs::{self, File},
-    path::PathBuf,
-    sync::{mpsc::Sender, Mutex, PoisonError, RwLock},
+    env, fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Mutex, PoisonError, RwLock,
+    },
     thread,
 };
 
 use anyhow::{anyhow, Context};
-use core_entities::service::VersionedServiceTree;
+use core_entities::service::{HttpClientConfig, VersionedServiceTree};
 use credential_entities::credentials::Authentication;
 use dotenv::dotenv;
 use engine_entities::engine::{
@@ -46,15 +50,21 @@ use engine_entities::engine::{
     get_run_result_response,
     list_response::ListItem,
     GetRunResultRequest, GetRunResultResponse, GetSerivceRequest, GetServiceResponse, ListRequest,
-    ListResponse, ProvideInputRequest, ProvideInputResponse, RunServiceRequest, RunServiceResponse,
-    SaveServiceRequest, SaveServiceResponse,
+    ListResponse, ProvideInputRequest, ProvideInputResponse, RunBatchRequest, RunBatchResponse,
+    RunServiceRequest, RunServiceResponse, RunServiceStreamResponse, SaveServiceRequest,
+    SaveServiceResponse,
 };
 use execution_engine::services::EngineLookup;
 use in_memory_storage::{repo::InMemoryRepository, OperationRepos};
-use local_file_loader::LocalFileFetcher;
-use protobuf::Message;
+use local_file_loader::{packages::Registry, LocalFileFetcher};
+use protobuf::{
+    well_known_types::wrappers::{BoolValue, UInt32Value, UInt64Value},
+    Message,
+};
+use service_loader::identity::ConnectorId;
 use service_writer::ServiceWriter;
-use tonic::{transport::Server, Request, Response, Status};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
 use user_input::Signals;
 
 #[cfg(feature = "dhat-heap")]
@@ -66,8 +76,11 @@ struct ApiDaemon {
     ///
     repos: Arc<Mutex<OperationRepos>>,
 
-    ///
-    paths: Arc<HashMap<String, PathBuf>>,
+    /// Where new connectors get created.
+    root: PathBuf,
+
+    /// Kept up to date by the loader as connector directories come and go.
+    paths: Arc<RwLock<HashMap<String, PathBuf>>>,
 
     ///
     engine: Arc<RwLock<execution_engine::Engine>>,
@@ -85,25 +98,80 @@ impl ApiDaemon {
     #[inline]
     fn new(
         repos: Arc<Mutex<OperationRepos>>,
-        paths: Arc<HashMap<String, PathBuf>>,
+        root: PathBuf,
+        paths: Arc<RwLock<HashMap<String, PathBuf>>>,
         engine: Arc<RwLock<execution_engine::Engine>>,
         responses: Arc<Mutex<HashMap<String, GetRunResultResponse>>>,
         signals: Signals,
     ) -> Self {
         Self {
             repos,
+            root,
             paths,
             engine,
             responses,
             signals,
         }
     }
+
+    /// Where a connector lives, creating a directory for it under the root when it's new.
+    /// The watcher will notice the new directory and load whatever gets saved into it.
+    fn location(&self, name: &str) -> io::Result<PathBuf> {
+        if ConnectorId::parse(name).is_some_and(|id| id.is_versioned()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name} is installed from a package, use `apicli install` to update it"),
+            ));
+        }
+
+        if let Some(location) = self
+            .paths
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+        {
+            if !location.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{name} is packaged as a bundle and can't be saved to"),
+                ));
+            }
+            return Ok(location.clone());
+        }
+
+        let mut components = Path::new(name).components();
+        let is_single_dir = matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        );
+
+        let location = self.root.join(name);
+        if !is_single_dir || util::is_hidden(&location) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid connector name: {name}"),
+            ));
+        }
+
+        fs::create_dir_all(&location)?;
+
+        self.paths
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.to_owned(), location.clone());
+
+        Ok(location)
+    }
 }
 
 #[tonic::async_trait]
 impl Engine for ApiDaemon {
+    type RunServiceStreamStream = ReceiverStream<Result<RunServiceStreamResponse, Status>>;
+    type RunBatchStream = ReceiverStream<Result<RunBatchResponse, Status>>;
+
     async fn list(&self, _: Request<ListRequest>) -> Result<Response<ListResponse>, Status> {
         let repo = self.repos.lock().unwrap_or_else(PoisonError::into_inner);
+        let dependencies = &repo.dependencies;
         let repo = &repo.services;
 
         let mut items = vec![];
@@ -112,12 +180,18 @@ impl Engine for ApiDaemon {
         for id in repo.list() {
             if let Some(service) = repo.get(&id) {
                 let service = service.v1();
+                let broken_references: Vec<String> = dependencies
+                    .broken(&id)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
 
                 let manifest = service.manifest.v2();
                 if manifest.has_swagger() {
                     for op_name in service.commonApi.operations.keys() {
                         items.push(ListItem {
                             name: format!("(swagger) {id}.{op_name}"),
+                            broken_references: broken_references.clone(),
                         });
                     }
                 }
@@ -128,6 +202,7 @@ impl Engine for ApiDaemon {
                     for op in &manifest.operations {
                         items.push(ListItem {
                             name: format!("(action) {id}.{}", op.id),
+                            broken_references: broken_references.clone(),
                         });
                     }
                 }
@@ -135,12 +210,14 @@ impl Engine for ApiDaemon {
                 if manifest.has_apiWrapped() {
                     items.push(ListItem {
                         name: format!("(wrapped) {id}.execute"),
+                        broken_references: broken_references.clone(),
                     });
                 }
 
                 if manifest.has_simpleCode() {
                     items.push(ListItem {
                         name: format!("(code) {id}.execute"),
+                        broken_references: broken_references.clone(),
                     });
                 }
             }
@@ -158,17 +235,28 @@ impl Engine for ApiDaemon {
     ) -> Result<Response<GetServiceResponse>, Status> {
         let req = req.into_inner();
 
-        let (service, credentials) = {
+        let (service, credentials, profiles, broken_references) = {
             let repo = self.repos.lock().unwrap_or_else(PoisonError::into_inner);
             let services = &repo.services;
             let service = services
                 .get(&req.name)
                 .ok_or_else(|| Status::not_found("Service not found"))?;
 
-            let credentials = &repo.credentials;
-            let creds = credentials.get(&req.name);
+            let creds = repo.get_credentials(&req.name, req.profile.as_deref());
+            let profiles = repo
+                .credentials
+                .get(&req.name)
+                .map(|profiles| profiles.names())
+                .unwrap_or_default();
+
+            let broken = repo
+                .dependencies
+                .broken(&req.name)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
 
-            (service, creds)
+            (service, creds, profiles, broken)
         };
 
         let raw_service = service
@@ -182,6 +270,8 @@ impl Engine for ApiDaemon {
         let response = GetServiceResponse {
             raw_service,
             raw_credentials,
+            broken_references,
+            profiles,
         };
 
         Ok(Response::new(response))
@@ -193,11 +283,14 @@ impl Engine for ApiDaemon {
     ) -> Result<Response<SaveServiceResponse>, Status> {
         let req = req.into_inner();
 
-        let location = self
-            .paths
-            .get(&req.name)
-            .ok_or_else(|| Status::not_found("Service location not found"))?;
-        let storage = LocalFileFetcher::from(location.clone());
+        let location = self.location(&req.name).map_err(|e| {
+            if e.kind() == io::ErrorKind::InvalidInput {
+                Status::invalid_argument(e.to_string())
+            } else {
+                Status::from_error(Box::new(e))
+            }
+        })?;
+        let storage = LocalFileFetcher::from(location);
 
         let writer = ServiceWriter::default();
 
@@ -213,8 +306,22 @@ impl Engine for ApiDaemon {
             let credentials = Authentication::parse_from_bytes(&credentials)
                 .map_err(|e| Status::from_error(Box::new(e)))?;
 
+            // Only the profile being saved changes, the others are written back as they were
+            let mut profiles = {
+                let repo = self.repos.lock().unwrap_or_else(PoisonError::into_inner);
+                repo.credentials
+                    .get(&req.name)
+                    .map(|profiles| (*profiles).clone())
+                    .unwrap_or_default()
+            };
+            let profile = req
+                .profile
+                .or_else(|| (!profiles.default.is_empty()).then(|| profiles.default.clone()))
+                .unwrap_or_else(|| constants::DEFAULT_PROFILE.to_owned());
+            profiles.insert(profile, credentials);
+
             writer
-                .store_credentials(&credentials, &storage)
+                .store_profiles(&profiles, &storage)
                 .map_err(|e| Status::from_error(Box::new(e)))?;
         }
 
@@ -248,11 +355,7 @@ impl Engine for ApiDaemon {
         let input =
             serde_json::from_str(&req.input).map_err(|e| Status::from_error(Box::new(e)))?;
 
-        let options = req.limit.map_or(serde_json::Value::Null, |limit| {
-            let mut map = serde_json::Map::new();
-            map.insert("limit".into(), limit.into());
-            map.into()
-        });
+        let options = run_options(&req);
 
         let engine = Arc::clone(&self.engine);
         let responses = Arc::clone(&self.responses);
@@ -268,7 +371,9 @@ impl Engine for ApiDaemon {
             let engine = engine.read().unwrap_or_else(PoisonError::into_inner);
 
             // TODO: Better error handling, Engine::run should NOT panic!
+            let in_flight = util::InFlight::start();
             let result = engine.run(&req.id, input, options, &ctx);
+            drop(in_flight);
 
             let execution_id = execution_id.to_string();
             match result {
@@ -312,6 +417,107 @@ impl Engine for ApiDaemon {
         Ok(Response::new(response))
     }
 
+    async fn run_service_stream(
+        &self,
+        req: Request<RunServiceRequest>,
+    ) -> Result<Response<Self::RunServiceStreamStream>, Status> {
+        let execution_id = uuid::Uuid::new_v4().to_string();
+
+        {
+            let result = GetRunResultResponse {
+                status: get_run_result_response::Status::Running.into(),
+                output: None,
+            };
+
+            let mut responses = self
+                .responses
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            responses.insert(execution_id.clone(), result);
+        };
+
+        let req = req.into_inner();
+        let input =
+            serde_json::from_str(&req.input).map_err(|e| Status::from_error(Box::new(e)))?;
+
+        let options = run_options(&req);
+
+        let engine = Arc::clone(&self.engine);
+        let responses = Arc::clone(&self.responses);
+        let signals = Arc::clone(&self.signals);
+
+        // Both ends are bounded so that a slow client applies back pressure all the
+        // way to the runner instead of pages piling up in memory
+        let (tx, rx) = tokio::sync::mpsc::channel(constants::STREAM_BUFFER_SIZE);
+        let (page_tx, page_rx) = mpsc::sync_channel(constants::STREAM_BUFFER_SIZE);
+
+        let ctx =
+            execution_engine::services::EngineInputContext::new(None, execution_id.clone(), false)
+                .with_stream(page_tx);
+
+        // TODO: convert to using a ThreadPool
+        let runner = thread::spawn(move || {
+            let engine = engine.read().unwrap_or_else(PoisonError::into_inner);
+
+            let _in_flight = util::InFlight::start();
+            engine.run(&req.id, input, options, &ctx).map(|_| ())
+        });
+
+        thread::spawn(move || {
+            for page in page_rx {
+                let items = match page {
+                    serde_json::Value::Array(items) => items,
+                    item @ (serde_json::Value::Null
+                    | serde_json::Value::Bool(_)
+                    | serde_json::Value::Number(_)
+                    | serde_json::Value::String(_)
+                    | serde_json::Value::Object(_)) => vec![item],
+                };
+
+                let items: Result<Vec<String>, _> =
+                    items.iter().map(serde_json::to_string).collect();
+                let message = items
+                    .map(|items| RunServiceStreamResponse {
+                        execution_id: execution_id.clone(),
+                        items,
+                    })
+                    .map_err(|e| Status::internal(e.to_string()));
+
+                // Client went away, dropping the receiver will stop the runner
+                if tx.blocking_send(message).is_err() {
+                    break;
+                }
+            }
+
+            let output = match runner.join() {
+                Ok(Ok(())) => None,
+                Ok(Err(err)) => {
+                    tx.blocking_send(Err(Status::internal(err.to_string())))
+                        .unwrap_or_default();
+                    Some(format!("{{ \"error\": \"{err}\" }}"))
+                }
+                Err(_) => {
+                    tx.blocking_send(Err(Status::internal("Execution panicked")))
+                        .unwrap_or_default();
+                    Some("{ \"error\": \"Execution panicked\" }".to_owned())
+                }
+            };
+
+            let result = GetRunResultResponse {
+                status: get_run_result_response::Status::Completed.into(),
+                output,
+            };
+
+            let mut responses = responses.lock().unwrap_or_else(PoisonError::into_inner);
+            responses.insert(execution_id.clone(), result);
+
+            let mut signals = signals.lock().unwrap_or_else(PoisonError::into_inner);
+            signals.remove(&execution_id);
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
     async fn get_run_result(
         &self,
         req: Request<GetRunResultRequest>,
@@ -372,38 +578,223 @@ impl Engine for ApiDaemon {
 
         Ok(Response::new(ProvideInputResponse {}))
     }
+
+    async fn run_batch(
+        &self,
+        req: Request<Streaming<RunBatchRequest>>,
+    ) -> Result<Response<Self::RunBatchStream>, Status> {
+        let mut requests = req.into_inner();
+        let first = requests
+            .message()
+            .await?
+            .ok_or_else(|| Status::invalid_argument("The batch is empty"))?;
+
+        let run = first.run.as_ref().ok_or_else(|| {
+            Status::invalid_argument("The first message of a batch has to say what to run")
+        })?;
+        let parallelism = first.parallelism.unwrap_or(1);
+        if !(1..=constants::MAX_BATCH_PARALLELISM).contains(&parallelism) {
+            return Err(Status::invalid_argument(format!(
+                "Parallelism has to be between 1 and {}",
+                constants::MAX_BATCH_PARALLELISM
+            )));
+        }
+        let parallelism =
+            usize::try_from(parallelism).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let batch = batch::Batch {
+            engine: Arc::clone(&self.engine),
+            signals: Arc::clone(&self.signals),
+            id: run.id.clone(),
+            options: run_options(run),
+            throttle: batch::Throttle::new(first.rate).map_err(Status::invalid_argument)?,
+        };
+
+        let (tx, rx) = tokio::sync::mpsc::channel(constants::BATCH_BUFFER_SIZE);
+        // Only as many inputs are read ahead as there are workers to take them
+        let (input_tx, input_rx) = tokio::sync::mpsc::channel(parallelism);
+        batch.start(parallelism, input_rx, &tx);
+
+        tokio::spawn(async move {
+            let mut message = Some(first);
+            while let Some(request) = message {
+                for input in request.inputs {
+                    // Every worker stopped, the client went away
+                    if input_tx.send(input).await.is_err() {
+                        return;
+                    }
+                }
+
+                message = match requests.message().await {
+                    Ok(message) => message,
+                    Err(status) => {
+                        tx.send(Err(status)).await.unwrap_or_default();
+                        return;
+                    }
+                };
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Where compiled connectors and remote documents are cached, compiled ones aren't when
+/// caching is turned off.
+fn loader_options(config: &Configuration) -> anyhow::Result<workers::LoaderOptions> {
+    let home = || anyhow::Ok(PathBuf::from(env::var("HOME")?));
+
+    let cache = match config.connector.as_ref() {
+        Some(connector) if !connector.cache => None,
+        Some(&ConnectorConfiguration {
+            cache_path: Some(ref cache_path),
+            ..
+        }) => Some(PathBuf::from(cache_path)),
+        Some(_) | None => Some(home()?.join(constants::DEFAULT_CACHE_PATH)),
+    };
+
+    let remote_cache = match config.connector.as_ref() {
+        Some(&ConnectorConfiguration {
+            remote_cache_path: Some(ref remote_cache_path),
+            ..
+        }) => PathBuf::from(remote_cache_path),
+        Some(_) | None => home()?.join(constants::DEFAULT_REMOTE_CACHE_PATH),
+    };
+
+    let registry = match config.connector.as_ref() {
+        Some(&ConnectorConfiguration {
+            registry_path: Some(ref registry_path),
+            ..
+        }) => PathBuf::from(registry_path),
+        Some(_) | None => home()?.join(constants::DEFAULT_REGISTRY_PATH),
+    };
+    // Watched along with the connector root, so it has to be there from the start
+    fs::create_dir_all(&registry)?;
+
+    Ok(workers::LoaderOptions {
+        registry: Registry::from(registry),
+        cache,
+        remote_cache,
+        offline: config
+            .connector
+            .as_ref()
+            .is_some_and(|connector| connector.offline),
+    })
+}
+
+/// Engine options for a run, only what was set on the request.
+fn run_options(req: &RunServiceRequest) -> serde_json::Value {
+    let mut options = serde_json::Map::new();
+    if let Some(limit) = req.limit {
+        options.insert("limit".into(), limit.into());
+    }
+    if let Some(ref profile) = req.profile {
+        options.insert("profile".into(), profile.clone().into());
+    }
+    if let Some(ref environment) = req.environment {
+        options.insert("environment".into(), environment.clone().into());
+    }
+    if let Some(envelope) = req.envelope {
+        options.insert("envelope".into(), envelope.into());
+    }
+    if let Some(ref error_statuses) = req.error_statuses {
+        options.insert("errorStatuses".into(), error_statuses.clone().into());
+    }
+    if let Some(no_cache) = req.no_cache {
+        options.insert("noCache".into(), no_cache.into());
+    }
+    if let Some(ref record) = req.record {
+        options.insert("record".into(), record.clone().into());
+    }
+    if let Some(ref replay) = req.replay {
+        options.insert("replay".into(), replay.clone().into());
+    }
+
+    if options.is_empty() {
+        serde_json::Value::Null
+    } else {
+        options.into()
+    }
+}
+
+/// `[http]` as the settings every connector's client starts from.
+fn client_defaults(http: &HttpConfiguration) -> HttpClientConfig {
+    HttpClientConfig {
+        connectTimeoutMs: http
+            .connect_timeout_ms
+            .map(|value| UInt64Value {
+                value,
+                ..Default::default()
+            })
+            .into(),
+        readTimeoutMs: http
+            .read_timeout_ms
+            .map(|value| UInt64Value {
+                value,
+                ..Default::default()
+            })
+            .into(),
+        proxy: http.proxy.clone().unwrap_or_default(),
+        httpProxy: http.http_proxy.clone().unwrap_or_default(),
+        httpsProxy: http.https_proxy.clone().unwrap_or_default(),
+        noProxy: http.no_proxy.clone().unwrap_or_default(),
+        caCertificates: http.ca_certificates.clone(),
+        maxRedirects: http
+            .max_redirects
+            .map(|value| UInt32Value {
+                value,
+                ..Default::default()
+            })
+            .into(),
+        http2: http
+            .http2
+            .map(|value| BoolValue {
+                value,
+                ..Default::default()
+            })
+            .into(),
+        userAgent: http.user_agent.clone().unwrap_or_default(),
+        ..Default::default()
+    }
 }
 
 ///
 fn construct_execution_engine(
-    lookup: Arc<Mutex<dyn EngineLookup + Sync + Send>>,
+    lookup: Arc<dyn EngineLookup + Sync + Send>,
     signals: Signals,
     config: &Configuration,
 ) -> anyhow::Result<Arc<RwLock<execution_engine::Engine>>> {
-    let workflow_logger = Arc::new(RwLock::new(File::create(config.log.workflow_path.clone())?));
-
-    let api_logger = Arc::new(RwLock::new(File::create(config.log.api_path.clone())?));
-
-    let engine = Arc::new(RwLock::new(execution_engine::Engine::new(
-        lookup,
-        Arc::clone(&workflow_logger),
-    )));
+    let engine = Arc::new(RwLock::new(execution_engine::Engine::new(lookup)));
 
-    let connector = Box::new(api_caller::APICaller::new(api_logger));
+    let mut api_caller = api_caller::APICaller::new()
+        .with_redacted_fields(config.log.redact_fields.iter().cloned())
+        .with_client_defaults(client_defaults(&config.http));
+    if let Some(ref download_path) = config.http.download_path {
+        api_caller = api_caller.with_download_dir(PathBuf::from(download_path));
+    }
+    match config.http.cache_path {
+        Some(ref cache_path) => api_caller = api_caller.with_cache_dir(PathBuf::from(cache_path)),
+        None => {
+            if let Ok(home) = env::var("HOME") {
+                api_caller = api_caller.with_cache_dir(
+                    PathBuf::from(home).join(constants::DEFAULT_RESPONSE_CACHE_PATH),
+                );
+            }
+        }
+    }
+    let connector = Box::new(api_caller);
 
     #[cfg(feature = "python")]
-    let py_runner =
-        python_runner::PyActionRunner::new(Arc::clone(&workflow_logger), Arc::clone(&engine));
+    let py_runner = python_runner::PyActionRunner::new(Arc::clone(&engine));
 
     #[cfg(feature = "javascript")]
-    let js_runner =
-        javascript_runner::JsActionRunner::new(Arc::clone(&engine), Arc::clone(&workflow_logger));
+    let js_runner = javascript_runner::JsActionRunner::new(Arc::clone(&engine));
 
     #[cfg(feature = "input")]
     let input_handler = Box::new(user_input::UserInput::new(signals));
 
     #[cfg(feature = "wrapper")]
-    let api_wrapper = filtered_runner::APIWrapper::new(workflow_logger, Arc::clone(&engine));
+    let api_wrapper = filtered_runner::APIWrapper::new(Arc::clone(&engine));
 
     {
         let mut engine = engine
@@ -442,6 +833,7 @@ async fn main() -> anyhow::Result<()> {
         Box::new(InMemoryRepository::new()),
         Box::new(InMemoryRepository::new()),
     );
+    let lookup = Arc::clone(&repos.lookup);
     let repos = Arc::new(Mutex::new(repos));
 
     let config_home = env::var(constants::CONFIG_PATH).with_context(|| {
@@ -454,6 +846,39 @@ async fn main() -> anyhow::Result<()> {
         .with_context(|| format!("Unable to read config file at {config_home}"))?;
     let config: Configuration = toml::from_str(&config)?;
 
+    let rotation = common_logging::rotation::Rotation::new(
+        config.log.max_size,
+        config.log.rotation.parse()?,
+        config.log.max_files,
+    );
+    let spans = common_logging::spans::SpanOptions::new(
+        config
+            .tracing
+            .service_name
+            .clone()
+            .unwrap_or_else(|| constants::DEFAULT_SERVICE_NAME.to_owned()),
+        config.tracing.otlp_endpoint.clone(),
+        config.tracing.path.as_ref().map(PathBuf::from),
+    );
+    common_logging::init(
+        &common_logging::Options::new(
+            config.log.level.clone(),
+            PathBuf::from(&config.log.api_path),
+            PathBuf::from(&config.log.workflow_path),
+            rotation,
+        )
+        .with_spans(spans),
+    )?;
+
+    if let Some(metrics) = config.metrics.as_ref() {
+        let addr: core::net::SocketAddr = format!("{}:{}", metrics.host, metrics.port).parse()?;
+        metrics_exporter_prometheus::PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .set_buckets(&constants::DURATION_BUCKETS)?
+            .install()?;
+        tracing::info!(%addr, "serving metrics");
+    }
+
     let default_path = PathBuf::from(env::var("HOME")?);
     let default_path = default_path.join("./connectors");
 
@@ -469,36 +894,35 @@ async fn main() -> anyhow::Result<()> {
 
     let paths: anyhow::Result<HashMap<String, PathBuf>> = util::get_paths(&path)?
         .map(|dir| {
-            let name = dir
-                .file_name()
-                .and_then(std::ffi::OsStr::to_str)
+            let name = util::service_name(&dir)
                 .ok_or_else(|| anyhow!("Unable to get filename from path"))?;
             Ok((name.to_owned(), dir))
         })
         .collect();
-    let paths = paths?;
-    let paths = Arc::new(paths);
+    let mut paths = paths?;
+
+    let options = loader_options(&config)?;
+    for (id, location) in options.registry.installed()? {
+        paths.insert(id.to_string(), location);
+    }
+    let paths = Arc::new(RwLock::new(paths));
 
     // Spawn off our background loader
     let (watcher_handler, loader_handler) =
-        workers::start_background_watcher(Arc::clone(&repos), &paths)?;
+        workers::start_background_watcher(Arc::clone(&repos), &path, &paths, options)?;
 
     // TODO: Shard this to reduce lock contention for concurrent requests
     let response_store = Arc::new(Mutex::new(HashMap::<String, GetRunResultResponse>::new()));
     let signals = HashMap::<String, (serde_json::Value, Sender<serde_json::Value>)>::new();
     let signals = Arc::new(Mutex::new(signals));
 
-    let engine = construct_execution_engine(
-        Arc::<Mutex<in_memory_storage::OperationRepos>>::clone(&repos),
-        Arc::clone(&signals),
-        &config,
-    )?;
+    let engine = construct_execution_engine(lookup, Arc::clone(&signals), &config)?;
 
     // Start Server
-    // println!("Starting server...");
 
-    let engine = ApiDaemon::new(repos, paths, engine, response_store, signals);
+    let engine = ApiDaemon::new(repos, path, paths, engine, response_store, signals);
     let addr = format!("{}:{}", config.server.host, config.server.port).parse()?;
+    tracing::info!(%addr, "starting server");
     Server::builder()
         .add_service(EngineServer::new(engine))
         .serve(addr)
@@ -513,3 +937,63 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn daemon(root: &Path) -> ApiDaemon {
+        let repos = OperationRepos::new(
+            Box::new(InMemoryRepository::new()),
+            Box::new(InMemoryRepository::new()),
+        );
+        let lookup = Arc::clone(&repos.lookup);
+
+        ApiDaemon::new(
+            Arc::new(Mutex::new(repos)),
+            root.to_path_buf(),
+            Arc::default(),
+            Arc::new(RwLock::new(execution_engine::Engine::new(lookup))),
+            Arc::default(),
+            Arc::default(),
+        )
+    }
+
+    #[test]
+    fn test_location_creates_new_connectors() {
+        let root = std::env::temp_dir().join(format!("apid_location_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("bundled.zip"), b"").unwrap();
+
+        let daemon = daemon(&root);
+        daemon
+            .paths
+            .write()
+            .unwrap()
+            .insert("bundled".into(), root.join("bundled.zip"));
+
+        let location = daemon.location("fresh").unwrap();
+        assert_eq!(root.join("fresh"), location);
+        assert!(location.is_dir());
+        assert_eq!(Some(&location), daemon.paths.read().unwrap().get("fresh"));
+
+        // Asked again, it's the same directory
+        assert_eq!(location, daemon.location("fresh").unwrap());
+
+        for name in [
+            "acme/github:1.0.0",
+            "../escape",
+            "nested/name",
+            ".hidden",
+            "bundled",
+        ] {
+            let err = daemon.location(name).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.kind(), "{name}");
+        }
+        assert!(!root.join(".hidden").exists());
+        assert!(!root.parent().unwrap().join("escape").exists());
+    }
+}
@@ -6,6 +6,28 @@ use std::{
 };
 
 use anyhow::anyhow;
+use local_file_loader::{bundle::ArchiveFetcher, packages::Registry};
+use service_loader::identity::ConnectorId;
+
+use crate::constants;
+
+/// Counts a run in [`constants::METRIC_IN_FLIGHT`] for as long as it's held, so one that
+/// returns early or panics doesn't leave the gauge raised.
+pub struct InFlight;
+
+impl InFlight {
+    ///
+    pub fn start() -> Self {
+        metrics::increment_gauge!(constants::METRIC_IN_FLIGHT, 1.0_f64);
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        metrics::decrement_gauge!(constants::METRIC_IN_FLIGHT, 1.0_f64);
+    }
+}
 
 ///
 pub fn is_hidden(entry: &Path) -> bool {
@@ -15,16 +37,43 @@ pub fn is_hidden(entry: &Path) -> bool {
         .map_or(true, |name| name.starts_with('.'))
 }
 
-///
+/// Connectors under the root, either directories or `.zip`/`.tar.gz` bundles.
 pub fn get_paths(path: &Path) -> anyhow::Result<impl Iterator<Item = PathBuf>> {
     if path.is_dir() {
         let iter = fs::read_dir(path)?
             .filter_map(core::result::Result::ok)
             .map(|dir| dir.path())
-            .filter(|dir| dir.is_dir() && !is_hidden(dir));
+            .filter(|dir| {
+                (dir.is_dir() || (dir.is_file() && ArchiveFetcher::is_bundle(dir)))
+                    && !is_hidden(dir)
+            });
 
         Ok(iter)
     } else {
         Err(anyhow!("Not a directory"))
     }
 }
+
+/// The name a connector is known by, `github` for both `github/` and `github.zip`.
+pub fn service_name(path: &Path) -> Option<&str> {
+    if path.is_dir() {
+        path.file_name().and_then(std::ffi::OsStr::to_str)
+    } else {
+        ArchiveFetcher::service_name(path)
+    }
+}
+
+/// Where a connector currently lives. Versioned ones are only ever found in the registry,
+/// in the root a directory wins over a bundle.
+pub fn locate(root: &Path, registry: &Registry, service: &str) -> Option<PathBuf> {
+    if let Some(id) = ConnectorId::parse(service).filter(ConnectorId::is_versioned) {
+        return registry.locate(&id);
+    }
+
+    let dir = root.join(service);
+    if dir.is_dir() && !is_hidden(&dir) {
+        return Some(dir);
+    }
+
+    ArchiveFetcher::locate(root, service)
+}
@@ -1,62 +1,527 @@
-#![allow(clippy::print_stdout, clippy::use_debug)]
-
 //!
 
 extern crate alloc;
-use alloc::sync::Arc;
+use alloc::{collections::BTreeMap, sync::Arc};
 
+use core::{iter, num::NonZeroUsize};
 use std::{
-    collections::HashMap,
-    path::PathBuf,
-    sync::{
-        mpsc::{Receiver, Sender},
-        Mutex, PoisonError,
-    },
+    collections::{HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+    sync::{mpsc::Receiver, Mutex, PoisonError, RwLock},
     thread::{self, JoinHandle},
 };
 
+use core_entities::service::VersionedServiceTree;
 use in_memory_storage::OperationRepos;
-use local_file_loader::LocalFileFetcher;
-use service_loader::ServiceLoader;
+use local_file_loader::{bundle::ArchiveFetcher, packages::Registry, LocalCache, LocalFileFetcher};
+use remote_file_loader::HttpFetcher;
+use service_loader::{routing::CompositeFetcher, Change, ServiceLoader};
+
+use super::{FileChange, LoaderOptions};
+use crate::{constants, util};
 
 ///
 pub fn start(
     repos: Arc<Mutex<OperationRepos>>,
-    paths: Arc<HashMap<String, PathBuf>>,
-    tx: Sender<bool>,
-    rx: Receiver<Vec<String>>,
+    root: PathBuf,
+    paths: Arc<RwLock<HashMap<String, PathBuf>>>,
+    options: LoaderOptions,
+    rx: Receiver<Vec<FileChange>>,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
-        let loader = ServiceLoader::default();
+        let loader = match options.cache {
+            Some(cache) => ServiceLoader::new().with_cache(Box::new(LocalCache::from(cache))),
+            None => ServiceLoader::new(),
+        };
 
-        if let Err(err) = tx.send(true) {
-            println!("Unable to signal to watcher thread ready: {err}");
-            return;
-        }
+        // Built on this thread, the blocking client can't be created inside the async runtime
+        let remote = match HttpFetcher::new() {
+            Ok(remote) => remote
+                .with_cache(options.remote_cache)
+                .with_offline(options.offline),
+            Err(err) => {
+                tracing::error!(error = %err, "unable to create HTTP client");
+                return;
+            }
+        };
+
+        // Services as parsed before `config.json` is merged in, so that only the
+        // configuration needs to be re-read when that's all that changed
+        let mut bases = HashMap::<String, VersionedServiceTree>::new();
+
+        while let Ok(first) = rx.recv() {
+            // Anything that queued up while the last batch was loading gets folded in
+            let changes = group(first.into_iter().chain(rx.try_iter().flatten()));
+
+            // Parsing is the slow part, it happens across connectors at once and before
+            // taking the lock so that lookups for anything else aren't held up
+            let needs_compiling = needs_compiling(&changes, &bases, &root, &options.registry);
+            let mut compiled = compile(&loader, &root, &options.registry, &remote, needs_compiling);
 
-        for event in rx {
             let mut repos = repos.lock().unwrap_or_else(PoisonError::into_inner);
             let repos = &mut *repos;
-            for service in event {
-                if let Some(path) = paths.get(&service) {
-                    let fetcher = LocalFileFetcher::from(path.clone());
-                    if let Err(err) = loader
-                        .load(&service, &fetcher, repos, true, false)
-                        .map_err(anyhow::Error::from)
-                    {
-                        println!("Error loading {service}:\n{err:?}");
+            for (service, kinds) in changes {
+                let Some(path) = util::locate(&root, &options.registry, &service) else {
+                    // The directory or bundle is gone, so is the connector
+                    if let Err(err) = remove(&service, &paths, &mut bases, repos) {
+                        tracing::error!(service, error = %err, "unable to remove");
                     } else {
-                        println!("Reloading {service}");
+                        tracing::info!(service, "removed");
                     }
+                    log_broken(repos, &repos.dependencies.dependents(&service));
+                    continue;
+                };
+
+                paths
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(service.clone(), path.clone());
+
+                let result = Source::open(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|source| {
+                        let fetcher = source.with_remote(&remote);
+                        match compiled.remove(&service) {
+                            Some(base) => {
+                                apply(&loader, &service, base?, &mut bases, &fetcher, repos)
+                            }
+                            None => reload(&loader, &service, &kinds, &bases, &fetcher, repos),
+                        }
+                    });
+
+                if let Err(err) = result {
+                    tracing::error!(service, changed = ?kinds, error = %err, "reload failed");
+                    metrics::increment_counter!(
+                        constants::METRIC_RELOADS,
+                        "service" => service.clone(),
+                        "result" => constants::RELOAD_FAILURE,
+                    );
                 } else {
-                    println!("Service not found?: {service}");
+                    tracing::info!(service, changed = ?kinds, "reloaded");
+                    metrics::increment_counter!(
+                        constants::METRIC_RELOADS,
+                        "service" => service.clone(),
+                        "result" => constants::RELOAD_SUCCESS,
+                    );
+
+                    let mut affected = repos.dependencies.dependents(&service);
+                    affected.push(service);
+                    log_broken(repos, &affected);
                 }
             }
 
-            if let Err(err) = tx.send(true) {
-                println!("Unable to signal to watcher thread ready: {err}");
-                return;
-            }
+            // The whole batch becomes visible to runs at once
+            repos.publish();
         }
     })
 }
+
+/// What has to be redone for a connector.
+#[derive(Debug, PartialEq, Eq)]
+enum Plan {
+    /// Parse it from scratch.
+    Compile,
+
+    /// Re-read `credentials.json` and/or `config.json` on top of what's already compiled.
+    Reload {
+        ///
+        credentials: bool,
+
+        ///
+        configuration: bool,
+    },
+}
+
+/// Only a connector that's already compiled and where nothing but its credentials or
+/// configuration changed gets away without being parsed again.
+fn plan(kinds: &HashSet<Change>, compiled: bool) -> Plan {
+    if !compiled || kinds.contains(&Change::Service) {
+        return Plan::Compile;
+    }
+
+    Plan::Reload {
+        credentials: kinds.contains(&Change::Credentials),
+        configuration: kinds.contains(&Change::Configuration),
+    }
+}
+
+/// Every connector that changed along with what changed in it, a change without a file is
+/// the connector itself.
+fn group(batch: impl IntoIterator<Item = FileChange>) -> BTreeMap<String, HashSet<Change>> {
+    let mut changes = BTreeMap::<String, HashSet<Change>>::new();
+    for change in batch {
+        let kind = change
+            .file
+            .as_deref()
+            .map_or(Change::Service, Change::from_file);
+        tracing::debug!(
+            service = change.service,
+            file = ?change.file,
+            "file changed"
+        );
+        changes.entry(change.service).or_default().insert(kind);
+    }
+
+    changes
+}
+
+/// Connectors that have to be parsed from scratch, brand new ones included. Ones that are
+/// gone are left for the removal.
+fn needs_compiling(
+    changes: &BTreeMap<String, HashSet<Change>>,
+    bases: &HashMap<String, VersionedServiceTree>,
+    root: &Path,
+    registry: &Registry,
+) -> Vec<String> {
+    changes
+        .iter()
+        .filter(|&(service, kinds)| plan(kinds, bases.contains_key(service)) == Plan::Compile)
+        .map(|(service, _)| service.clone())
+        .filter(|service| util::locate(root, registry, service).is_some())
+        .collect()
+}
+
+/// Forgets a connector whose directory or bundle is gone, anything depending on it is
+/// checked again without it.
+fn remove(
+    service: &str,
+    paths: &RwLock<HashMap<String, PathBuf>>,
+    bases: &mut HashMap<String, VersionedServiceTree>,
+    repos: &mut OperationRepos,
+) -> in_memory_storage::error::Result<()> {
+    paths
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(service);
+    bases.remove(service);
+
+    repos.remove(service)
+}
+
+/// Where a connector is read from, references to remote documents are fetched alongside.
+enum Source {
+    ///
+    Directory(LocalFileFetcher),
+
+    ///
+    Bundle(ArchiveFetcher),
+}
+
+impl Source {
+    ///
+    fn open(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            Ok(Self::Directory(LocalFileFetcher::from(path.to_path_buf())))
+        } else {
+            ArchiveFetcher::open(path).map(Self::Bundle)
+        }
+    }
+
+    ///
+    fn with_remote<'fetcher>(
+        &'fetcher self,
+        remote: &'fetcher HttpFetcher,
+    ) -> CompositeFetcher<'fetcher> {
+        let fetcher = match *self {
+            Self::Directory(ref directory) => CompositeFetcher::new(directory),
+            Self::Bundle(ref bundle) => CompositeFetcher::new(bundle),
+        };
+
+        constants::REMOTE_PREFIXES
+            .iter()
+            .fold(fetcher, |fetcher, prefix| fetcher.route(prefix, remote))
+    }
+}
+
+/// Loads services in parallel, as many at a time as there are cores.
+fn compile(
+    loader: &ServiceLoader,
+    root: &Path,
+    registry: &Registry,
+    remote: &HttpFetcher,
+    services: Vec<String>,
+) -> HashMap<String, anyhow::Result<VersionedServiceTree>> {
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(services.len());
+    let queue = Mutex::new(services.into_iter());
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = iter::repeat_with(|| {
+            scope.spawn(|| {
+                let mut compiled = vec![];
+                loop {
+                    let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let Some(service) = next else {
+                        break;
+                    };
+
+                    let base = util::locate(root, registry, &service)
+                        .ok_or_else(|| anyhow::anyhow!("{service} is gone"))
+                        .and_then(|path| Ok(Source::open(&path)?))
+                        .and_then(|source| {
+                            Ok(loader.load_base(&source.with_remote(remote), false)?)
+                        });
+                    compiled.push((service, base));
+                }
+                compiled
+            })
+        })
+        .take(workers)
+        .collect();
+
+        handles
+            .into_iter()
+            .filter_map(|handle| match handle.join() {
+                Ok(compiled) => Some(compiled),
+                Err(_err) => {
+                    tracing::error!("a loader thread panicked");
+                    None
+                }
+            })
+            .flatten()
+            .collect()
+    })
+}
+
+/// Stores a freshly compiled service along with its credentials and configuration.
+fn apply(
+    loader: &ServiceLoader,
+    service: &str,
+    base: VersionedServiceTree,
+    bases: &mut HashMap<String, VersionedServiceTree>,
+    fetcher: &CompositeFetcher,
+    repos: &mut OperationRepos,
+) -> anyhow::Result<()> {
+    if base.v1().manifest.v2().has_swagger() {
+        // Not every connector needs credentials
+        if let Err(err) = loader.reload_credentials(service, fetcher, repos) {
+            tracing::debug!(service, error = %err, "no credentials loaded");
+        }
+    }
+
+    loader.reload_configuration(service, &base, fetcher, repos, true)?;
+    bases.insert(service.to_owned(), base);
+
+    Ok(())
+}
+
+/// Re-parses only `credentials.json` and/or `config.json` for a service that's already compiled.
+fn reload(
+    loader: &ServiceLoader,
+    service: &str,
+    kinds: &HashSet<Change>,
+    bases: &HashMap<String, VersionedServiceTree>,
+    fetcher: &CompositeFetcher,
+    repos: &mut OperationRepos,
+) -> anyhow::Result<()> {
+    let base = bases
+        .get(service)
+        .ok_or_else(|| anyhow::anyhow!("{service} hasn't been compiled"))?;
+
+    let Plan::Reload {
+        credentials,
+        configuration,
+    } = plan(kinds, true)
+    else {
+        return Err(anyhow::anyhow!("{service} has to be compiled again"));
+    };
+
+    if credentials {
+        loader.reload_credentials(service, fetcher, repos)?;
+    }
+
+    if configuration {
+        loader.reload_configuration(service, base, fetcher, repos, true)?;
+    }
+
+    Ok(())
+}
+
+/// Warns about references that no longer resolve, they're revalidated as their dependencies load.
+fn log_broken(repos: &OperationRepos, services: &[String]) {
+    for service in services {
+        for broken in repos.dependencies.broken(service) {
+            tracing::warn!(service, reference = %broken, "broken reference");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use in_memory_storage::repo::InMemoryRepository;
+    use service_loader::{dependencies::BrokenReference, LoaderOutput as _};
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("apid_loader_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// With a `listRepos` operation.
+    fn swagger() -> VersionedServiceTree {
+        let mut service = VersionedServiceTree::new();
+        let v1 = service.mut_v1();
+        v1.manifest.mut_or_insert_default().mut_v2().mut_swagger();
+        v1.commonApi
+            .mut_or_insert_default()
+            .operations
+            .insert("listRepos".into(), Default::default());
+        service
+    }
+
+    /// Wraps `github.listRepos`.
+    fn wrapped() -> VersionedServiceTree {
+        let mut service = VersionedServiceTree::new();
+        let wrapped = service
+            .mut_v1()
+            .manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .mut_apiWrapped();
+        wrapped.connectorId = "github".into();
+        wrapped.connectorOperation = "listRepos".into();
+        service
+    }
+
+    fn change(service: &str, file: Option<&str>) -> FileChange {
+        FileChange {
+            service: service.into(),
+            file: file.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn test_changes_from_files() {
+        let kind = |file: &str| Change::from_file(Path::new(file));
+
+        assert_eq!(Change::Credentials, kind("credentials.json"));
+        assert_eq!(Change::Configuration, kind("config.json"));
+        assert_eq!(Change::Service, kind("manifest.json"));
+        assert_eq!(Change::Service, kind("scripts/list.py"));
+
+        // Only the connector's own files, not ones that happen to share their name
+        assert_eq!(Change::Service, kind("scripts/credentials.json"));
+        assert_eq!(Change::Service, kind("fixtures/config.json"));
+    }
+
+    #[test]
+    fn test_groups_changes_by_connector() {
+        let changes = group([
+            change("github", Some("credentials.json")),
+            change("github", Some("config.json")),
+            change("github", Some("credentials.json")),
+            change("gitlab", Some("scripts/list.py")),
+            change("jira", None),
+        ]);
+
+        assert_eq!(
+            BTreeMap::from([
+                (
+                    "github".to_owned(),
+                    HashSet::from([Change::Credentials, Change::Configuration])
+                ),
+                ("gitlab".to_owned(), HashSet::from([Change::Service])),
+                ("jira".to_owned(), HashSet::from([Change::Service])),
+            ]),
+            changes
+        );
+    }
+
+    #[test]
+    fn test_plans_only_what_changed() {
+        let kinds = |kinds: &[Change]| kinds.iter().copied().collect::<HashSet<_>>();
+
+        assert_eq!(
+            Plan::Reload {
+                credentials: true,
+                configuration: false
+            },
+            plan(&kinds(&[Change::Credentials]), true)
+        );
+        assert_eq!(
+            Plan::Reload {
+                credentials: false,
+                configuration: true
+            },
+            plan(&kinds(&[Change::Configuration]), true)
+        );
+        assert_eq!(
+            Plan::Reload {
+                credentials: true,
+                configuration: true
+            },
+            plan(&kinds(&[Change::Credentials, Change::Configuration]), true)
+        );
+
+        // Anything else in the connector, or one never compiled, starts over
+        assert_eq!(
+            Plan::Compile,
+            plan(&kinds(&[Change::Credentials, Change::Service]), true)
+        );
+        assert_eq!(Plan::Compile, plan(&kinds(&[Change::Configuration]), false));
+    }
+
+    #[test]
+    fn test_compiles_new_connectors() {
+        let root = temp_dir("new");
+        let registry = Registry::from(root.join(".registry"));
+        fs::create_dir_all(root.join("fresh")).unwrap();
+        fs::create_dir_all(root.join("github")).unwrap();
+
+        let changes = group([
+            change("fresh", None),
+            change("github", Some("config.json")),
+            change("gone", None),
+        ]);
+        let bases = HashMap::from([("github".to_owned(), swagger())]);
+
+        // Only configuration changed for `github`, and `gone` is left to be removed
+        assert_eq!(
+            vec!["fresh".to_owned()],
+            needs_compiling(&changes, &bases, &root, &registry)
+        );
+    }
+
+    #[test]
+    fn test_removes_connectors() {
+        let mut repos = OperationRepos::new(
+            Box::new(InMemoryRepository::new()),
+            Box::new(InMemoryRepository::new()),
+        );
+        repos.handle_service("github", swagger()).unwrap();
+        repos.handle_service("repos", wrapped()).unwrap();
+        assert!(repos.dependencies.broken("repos").is_empty());
+
+        let paths = RwLock::new(HashMap::from([
+            ("github".to_owned(), PathBuf::from("/connectors/github")),
+            ("repos".to_owned(), PathBuf::from("/connectors/repos")),
+        ]));
+        let mut bases = HashMap::from([
+            ("github".to_owned(), swagger()),
+            ("repos".to_owned(), wrapped()),
+        ]);
+
+        remove("github", &paths, &mut bases, &mut repos).unwrap();
+
+        assert!(!paths.read().unwrap().contains_key("github"));
+        assert!(!bases.contains_key("github"));
+        assert!(repos.services.get("github").is_none());
+        assert!(repos.services.get("repos").is_some());
+
+        // What wrapped it is broken until it comes back
+        assert!(matches!(
+            repos.dependencies.broken("repos"),
+            [BrokenReference::MissingService(ref reference)] if reference.service == "github"
+        ));
+
+        repos.handle_service("github", swagger()).unwrap();
+        assert!(repos.dependencies.broken("repos").is_empty());
+    }
+}
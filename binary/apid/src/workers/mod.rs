@@ -8,25 +8,70 @@ use alloc::sync::Arc;
 
 use std::{
     collections::HashMap,
-    path::PathBuf,
-    sync::{mpsc, Mutex},
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex, RwLock},
     thread::JoinHandle,
 };
 
 use in_memory_storage::OperationRepos;
+use local_file_loader::packages::Registry;
+
+/// A debounced change somewhere under a connector's directory, or to its bundle.
+#[derive(Debug)]
+pub struct FileChange {
+    ///
+    pub service: String,
+
+    /// Relative to the connector's directory, `None` when it's the directory itself.
+    pub file: Option<PathBuf>,
+}
+
+/// Where connectors are installed, and where what's fetched while loading them is kept.
+pub struct LoaderOptions {
+    /// Versions installed from packages, alongside the connector root.
+    pub registry: Registry,
+
+    /// Compiled connectors, `None` parses every connector from scratch.
+    pub cache: Option<PathBuf>,
+
+    /// Documents referenced over HTTP(S), used when the server can't be reached.
+    pub remote_cache: PathBuf,
+
+    /// Only ever read remote documents from `remote_cache`.
+    pub offline: bool,
+}
 
 ///
 pub fn start_background_watcher(
     repos: Arc<Mutex<OperationRepos>>,
-    paths: &Arc<HashMap<String, PathBuf>>,
+    root: &Path,
+    paths: &Arc<RwLock<HashMap<String, PathBuf>>>,
+    options: LoaderOptions,
 ) -> anyhow::Result<(JoinHandle<()>, JoinHandle<()>)> {
-    let (file_tx, file_rx) = mpsc::channel::<Vec<String>>();
-    let (load_tx, load_rx) = mpsc::channel::<bool>();
+    let (file_tx, file_rx) = mpsc::channel::<Vec<FileChange>>();
 
-    let watcher_handler = watcher::start(Arc::clone(paths), file_tx.clone(), load_rx);
-    let loading_handler = loader::start(repos, Arc::clone(paths), load_tx, file_rx);
+    let watcher_handler = watcher::start(
+        root.to_path_buf(),
+        options.registry.clone(),
+        file_tx.clone(),
+    );
+    let loading_handler = loader::start(
+        repos,
+        root.to_path_buf(),
+        Arc::clone(paths),
+        options,
+        file_rx,
+    );
 
-    let all_services: Vec<_> = paths.keys().cloned().collect();
+    let all_services: Vec<_> = paths
+        .read()
+        .map_err(|err| anyhow::anyhow!("Unable to read connector paths: {err}"))?
+        .keys()
+        .map(|service| FileChange {
+            service: service.clone(),
+            file: None,
+        })
+        .collect();
     file_tx.send(all_services)?;
 
     Ok((watcher_handler, loading_handler))
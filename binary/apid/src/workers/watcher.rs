@@ -1,87 +1,315 @@
-#![allow(clippy::print_stdout)]
-
 //!
 
-extern crate alloc;
-use alloc::sync::Arc;
-
-use core::time::Duration;
 use std::{
-    collections::{HashMap, HashSet},
-    path::PathBuf,
-    sync::{
-        mpsc::{self, Receiver, Sender},
-        Mutex, PoisonError,
-    },
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
     thread::{self, JoinHandle},
+    time::Instant,
 };
 
-use notify::Watcher;
+use local_file_loader::{bundle::ArchiveFetcher, packages::Registry};
+use notify::{EventKind, Watcher};
 
-///
-pub fn start(
-    paths: Arc<HashMap<String, PathBuf>>,
-    tx: Sender<Vec<String>>,
-    rx: Receiver<bool>,
-) -> JoinHandle<()> {
+use super::FileChange;
+use crate::{constants, util};
+
+/// Watches the whole connector root and registry so that new and deleted connectors are noticed
+/// too, each file is debounced on its own and reported along with the connector it belongs to.
+pub fn start(root: PathBuf, registry: Registry, tx: Sender<Vec<FileChange>>) -> JoinHandle<()> {
     thread::spawn(move || {
-        let mut watchers = vec![];
-        let cache = Arc::new(Mutex::new(HashSet::<String>::new()));
-        let (inner_tx, inner_rx) = mpsc::channel::<bool>();
-
-        for (name, path) in &*paths {
-            let config = notify::Config::default()
-                .with_poll_interval(Duration::from_secs(1))
-                .with_compare_contents(false);
-
-            let cache = Arc::clone(&cache);
-            let name = name.clone();
-            let inner_tx = inner_tx.clone();
-            let watcher = notify::PollWatcher::new(
-                move |_| {
-                    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
-                    cache.insert(name.clone());
-
-                    match inner_tx.send(true) {
-                        Ok(()) => {}
-                        Err(err) => println!("Unable to signal read for loading: {err}"),
-                    }
-                },
-                config,
-            );
-
-            match watcher {
-                Ok(mut watcher) => match watcher.watch(path, notify::RecursiveMode::Recursive) {
-                    Ok(()) => {
-                        println!("Started watcher for: {}", path.to_string_lossy());
-                        watchers.push(watcher);
-                    }
-                    Err(err) => {
-                        println!("Unable start PollWatcher: {err}");
-                    }
-                },
-                Err(err) => {
-                    println!("Unable create PollWatcher: {err}");
-                }
+        let (event_tx, event_rx) = mpsc::channel::<notify::Result<notify::Event>>();
+
+        // Has to outlive the loop below, dropping it stops the watching
+        let watched = [root.as_path(), registry.root()];
+        let Some(_watcher) = watch(&watched, event_tx) else {
+            return;
+        };
+
+        let mut pending = HashMap::<PathBuf, Instant>::new();
+        loop {
+            let event = if pending.is_empty() {
+                event_rx
+                    .recv()
+                    .map_err(|_err| RecvTimeoutError::Disconnected)
+            } else {
+                event_rx.recv_timeout(constants::DEBOUNCE)
+            };
+
+            match event {
+                Ok(Ok(event)) => record(&mut pending, event, Instant::now()),
+                Ok(Err(err)) => tracing::error!(error = %err, "watch error"),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
             }
-        }
 
-        for is_ready in rx {
-            if is_ready {
-                for has_updates in &inner_rx {
-                    if has_updates {
-                        // Sleep to catch any potential double updates
-                        // At least one polling iteration
-                        thread::sleep(Duration::from_secs(2));
-
-                        let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
-                        if let Err(err) = tx.send(cache.drain().collect()) {
-                            println!("Unable to signal to loader thread what to load: {err}");
-                            return;
-                        }
-                    }
+            let changes: Vec<_> = settled(&mut pending, Instant::now())
+                .iter()
+                .filter_map(|path| classify(&root, &registry, path))
+                .collect();
+
+            if !changes.is_empty() {
+                if let Err(err) = tx.send(changes) {
+                    tracing::error!(
+                        error = %err,
+                        "unable to signal to loader thread what to load"
+                    );
+                    return;
                 }
             }
         }
     })
 }
+
+/// Restarts the quiet period of every path the event touched, reads don't count.
+fn record(pending: &mut HashMap<PathBuf, Instant>, event: notify::Event, now: Instant) {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+
+    for path in event.paths {
+        pending.insert(path, now);
+    }
+}
+
+/// Takes the paths that have been quiet for long enough as of `now`, the rest keep waiting.
+fn settled(pending: &mut HashMap<PathBuf, Instant>, now: Instant) -> Vec<PathBuf> {
+    let mut settled = vec![];
+    pending.retain(|path, &mut last_seen| {
+        if now.saturating_duration_since(last_seen) < constants::DEBOUNCE {
+            return true;
+        }
+
+        settled.push(path.clone());
+        false
+    });
+
+    settled.sort();
+    settled
+}
+
+/// The connector a changed path belongs to, whether it's installed or in the root.
+fn classify(root: &Path, registry: &Registry, path: &Path) -> Option<FileChange> {
+    installed_change(registry, path).or_else(|| file_change(root, path))
+}
+
+/// Prefers the platform's notifications (inotify, `FSEvents`, ...) and falls back to polling.
+fn watch(
+    paths: &[&Path],
+    tx: Sender<notify::Result<notify::Event>>,
+) -> Option<Box<dyn Watcher + Send>> {
+    let native = notify::recommended_watcher(tx.clone()).and_then(|mut watcher| {
+        for path in paths {
+            watcher.watch(path, notify::RecursiveMode::Recursive)?;
+        }
+        Ok(watcher)
+    });
+
+    match native {
+        Ok(watcher) => {
+            tracing::info!(paths = ?paths, "started watcher");
+            return Some(Box::new(watcher));
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "native file notifications unavailable, polling instead");
+        }
+    }
+
+    let config = notify::Config::default()
+        .with_poll_interval(constants::POLL_INTERVAL)
+        .with_compare_contents(false);
+    let polling = notify::PollWatcher::new(tx, config).and_then(|mut watcher| {
+        for path in paths {
+            watcher.watch(path, notify::RecursiveMode::Recursive)?;
+        }
+        Ok(watcher)
+    });
+
+    match polling {
+        Ok(watcher) => {
+            tracing::info!(paths = ?paths, "started polling watcher");
+            Some(Box::new(watcher))
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "unable to start PollWatcher");
+            None
+        }
+    }
+}
+
+/// An installed connector a changed path belongs to, keyed as `maintainer/name:tag`.
+fn installed_change(registry: &Registry, path: &Path) -> Option<FileChange> {
+    let (id, file) = registry.connector_id(path)?;
+
+    let hidden = file
+        .components()
+        .any(|component| util::is_hidden(Path::new(component.as_os_str())));
+    if hidden {
+        return None;
+    }
+
+    Some(FileChange {
+        service: id.to_string(),
+        file: (!file.as_os_str().is_empty()).then_some(file),
+    })
+}
+
+/// The connector a changed path belongs to, i.e. the first directory under the root, and the
+/// file within it. Anything hidden along the way (`.git`, editor swap files, ...) is ignored.
+/// A bundle changing is the same as its whole directory changing.
+fn file_change(root: &Path, path: &Path) -> Option<FileChange> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut components = relative.components();
+
+    let service = match components.next() {
+        Some(Component::Normal(name)) => name.to_str()?.to_owned(),
+        _ => return None,
+    };
+
+    let hidden = relative
+        .components()
+        .any(|component| util::is_hidden(Path::new(component.as_os_str())));
+    if hidden {
+        return None;
+    }
+
+    let file = components.as_path();
+
+    if file.as_os_str().is_empty() {
+        if let Some(bundle) = ArchiveFetcher::service_name(Path::new(&service)) {
+            return Some(FileChange {
+                service: bundle.to_owned(),
+                file: None,
+            });
+        }
+    }
+
+    let file = if file.as_os_str().is_empty() {
+        None
+    } else {
+        Some(file.to_path_buf())
+    };
+
+    Some(FileChange { service, file })
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+
+    fn event(kind: EventKind, paths: &[&str]) -> notify::Event {
+        paths.iter().fold(notify::Event::new(kind), |event, path| {
+            event.add_path(path.into())
+        })
+    }
+
+    #[test]
+    fn test_debounces_each_path() {
+        let start = Instant::now();
+        let mut pending = HashMap::new();
+
+        let modify = EventKind::Modify(ModifyKind::Any);
+        record(
+            &mut pending,
+            event(modify, &["/c/a.json", "/c/b.json"]),
+            start,
+        );
+        record(
+            &mut pending,
+            event(EventKind::Access(AccessKind::Any), &["/c/read.json"]),
+            start,
+        );
+
+        // Still settling, and a second write to `b` starts its wait over
+        let later = start + constants::DEBOUNCE / 2;
+        assert!(settled(&mut pending, later).is_empty());
+        record(&mut pending, event(modify, &["/c/b.json"]), later);
+
+        let quiet = start + constants::DEBOUNCE;
+        assert_eq!(
+            vec![PathBuf::from("/c/a.json")],
+            settled(&mut pending, quiet)
+        );
+        assert_eq!(
+            vec![PathBuf::from("/c/b.json")],
+            settled(&mut pending, later + constants::DEBOUNCE)
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_settles_in_path_order() {
+        let start = Instant::now();
+        let mut pending = HashMap::new();
+
+        let create = EventKind::Create(CreateKind::File);
+        record(
+            &mut pending,
+            event(create, &["/c/z", "/c/a", "/c/m"]),
+            start,
+        );
+
+        let settled = settled(&mut pending, start + constants::DEBOUNCE);
+        assert_eq!(
+            vec![
+                PathBuf::from("/c/a"),
+                PathBuf::from("/c/m"),
+                PathBuf::from("/c/z")
+            ],
+            settled
+        );
+    }
+
+    #[test]
+    fn test_classifies_changes() {
+        let root = Path::new("/connectors");
+        let registry = Registry::from(PathBuf::from("/registry"));
+        let classify = |path: &str| {
+            classify(root, &registry, Path::new(path)).map(|change| (change.service, change.file))
+        };
+
+        assert_eq!(
+            Some(("github".into(), Some(PathBuf::from("config.json")))),
+            classify("/connectors/github/config.json")
+        );
+        assert_eq!(
+            Some(("github".into(), Some(PathBuf::from("scripts/list.py")))),
+            classify("/connectors/github/scripts/list.py")
+        );
+        assert_eq!(
+            Some(("github".into(), None)),
+            classify("/connectors/github")
+        );
+
+        // A bundle is its whole connector
+        assert_eq!(
+            Some(("github".into(), None)),
+            classify("/connectors/github.tar.gz")
+        );
+
+        assert_eq!(
+            Some((
+                "acme/github:1.0.0".into(),
+                Some(PathBuf::from("manifest.json"))
+            )),
+            classify("/registry/acme/github/1.0.0/manifest.json")
+        );
+        assert_eq!(
+            Some(("acme/github:1.0.0".into(), None)),
+            classify("/registry/acme/github/1.0.0")
+        );
+
+        // Hidden files, anything outside and half installed packages are ignored
+        assert_eq!(None, classify("/connectors/github/.git/HEAD"));
+        assert_eq!(None, classify("/connectors/.github.swp"));
+        assert_eq!(
+            None,
+            classify("/registry/acme/github/.1.0.0.123/manifest.json")
+        );
+        assert_eq!(None, classify("/elsewhere/github/config.json"));
+        assert_eq!(None, classify("/connectors"));
+    }
+}
@@ -0,0 +1,19 @@
+//!
+
+/// Events from this target (and its modules) are written to the API log.
+pub const API_TARGET: &str = "api_caller";
+
+/// Appended to a log file's name when it's rotated out, sorts oldest first.
+pub const ROTATED_SUFFIX_FORMAT: &str = "%Y%m%dT%H%M%S%.3f";
+
+///
+pub const HOURLY_PERIOD_FORMAT: &str = "%Y%m%d%H";
+
+///
+pub const DAILY_PERIOD_FORMAT: &str = "%Y%m%d";
+
+/// Resource attribute collectors use to tell services apart.
+pub const SERVICE_NAME_KEY: &str = "service.name";
+
+///
+pub const TRACER_NAME: &str = "apid";
@@ -0,0 +1,47 @@
+#![allow(clippy::std_instead_of_core)]
+
+//!
+
+use std::io;
+
+use thiserror::Error;
+
+///
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Logging {
+    ///
+    #[error("Unknown log level: {0}")]
+    InvalidLevel(String),
+
+    ///
+    #[error("Unknown rotation interval: {0}")]
+    InvalidInterval(String),
+
+    ///
+    #[error(transparent)]
+    Io {
+        ///
+        #[from]
+        source: io::Error,
+    },
+
+    ///
+    #[error(transparent)]
+    Trace {
+        ///
+        #[from]
+        source: opentelemetry::trace::TraceError,
+    },
+
+    ///
+    #[error(transparent)]
+    AlreadyInitialized {
+        ///
+        #[from]
+        source: tracing_subscriber::util::TryInitError,
+    },
+}
+
+///
+pub type Result<T> = core::result::Result<T, Logging>;
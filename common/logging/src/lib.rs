@@ -0,0 +1,173 @@
+#![warn(clippy::restriction, clippy::pedantic)]
+#![allow(
+    clippy::blanket_clippy_restriction_lints,
+    clippy::mod_module_files,
+    clippy::self_named_module_files,
+
+    clippy::implicit_return,
+    clippy::shadow_reuse,
+    clippy::shadow_unrelated,
+    clippy::match_ref_pats,
+    clippy::question_mark_used,
+    clippy::absolute_paths,
+    clippy::single_call_fn,
+    clippy::separated_literal_suffix,
+    clippy::missing_trait_methods,
+    clippy::needless_borrowed_reference,
+    clippy::ref_patterns,
+
+    // Would like to turn on (Configured to 50?)
+    clippy::too_many_lines
+)]
+
+//! JSON line logging for apid. API traffic (anything from `api_caller`) goes to its
+//! own file, everything else lands in the workflow log. Spans are exported separately,
+//! see [`spans`].
+
+mod constants;
+pub mod error;
+pub mod rotation;
+pub mod spans;
+
+use std::{path::PathBuf, sync::Mutex};
+
+use tracing_subscriber::{
+    filter::{filter_fn, LevelFilter},
+    fmt,
+    layer::SubscriberExt as _,
+    util::SubscriberInitExt as _,
+    Layer as _,
+};
+
+use opentelemetry::trace::TracerProvider as _;
+use rotation::{RotatingFile, Rotation};
+use spans::SpanOptions;
+
+///
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Options {
+    /// One of `trace`, `debug`, `info`, `warn`, `error` or `off`.
+    pub level: String,
+
+    ///
+    pub api_path: PathBuf,
+
+    ///
+    pub workflow_path: PathBuf,
+
+    ///
+    pub rotation: Rotation,
+
+    ///
+    pub spans: SpanOptions,
+}
+
+impl Options {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new(
+        level: String,
+        api_path: PathBuf,
+        workflow_path: PathBuf,
+        rotation: Rotation,
+    ) -> Self {
+        Self {
+            level,
+            api_path,
+            workflow_path,
+            rotation,
+            spans: SpanOptions::default(),
+        }
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_spans(mut self, spans: SpanOptions) -> Self {
+        self.spans = spans;
+        self
+    }
+}
+
+/// Installs the global subscriber, can only be called once per process.
+///
+/// # Errors
+/// Fails on an unknown level, when either log file can't be opened or when a
+/// subscriber has already been installed.
+#[inline]
+pub fn init(options: &Options) -> error::Result<()> {
+    let level = options
+        .level
+        .parse::<LevelFilter>()
+        .map_err(|_err| error::Logging::InvalidLevel(options.level.clone()))?;
+
+    let api = RotatingFile::open(&options.api_path, options.rotation.clone())?;
+    let workflow = RotatingFile::open(&options.workflow_path, options.rotation.clone())?;
+
+    let api_layer = fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_writer(Mutex::new(api))
+        .with_filter(filter_fn(|metadata| {
+            metadata.target().starts_with(constants::API_TARGET)
+        }));
+
+    let workflow_layer = fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_writer(Mutex::new(workflow))
+        .with_filter(filter_fn(|metadata| {
+            !metadata.target().starts_with(constants::API_TARGET)
+        }));
+
+    // The global provider keeps the exporters alive for as long as the process runs
+    let span_layer = spans::provider(&options.spans, &options.rotation)?.map(|provider| {
+        let tracer = provider.tracer(constants::TRACER_NAME);
+        opentelemetry::global::set_tracer_provider(provider);
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    });
+
+    tracing_subscriber::registry()
+        .with(level)
+        .with(api_layer)
+        .with(workflow_layer)
+        .with(span_layer)
+        .try_init()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use std::fs;
+
+    // apid calls this from `main`, which already runs on the runtime
+    #[tokio::test]
+    async fn test_init_inside_runtime() {
+        let dir = std::env::temp_dir().join(format!("common_logging_init_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let options = Options::new(
+            "info".into(),
+            dir.join("api.log"),
+            dir.join("workflow.log"),
+            Rotation::default(),
+        )
+        .with_spans(SpanOptions::new(
+            "apid".into(),
+            Some("http://127.0.0.1:9/v1/traces".into()),
+            None,
+        ));
+
+        init(&options).unwrap();
+    }
+}
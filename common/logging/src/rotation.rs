@@ -0,0 +1,327 @@
+//! A log file that rolls over once it grows too large or a new period starts.
+
+use core::str::FromStr;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::{constants, error};
+
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Interval {
+    ///
+    #[default]
+    Never,
+
+    ///
+    Hourly,
+
+    ///
+    Daily,
+}
+
+impl Interval {
+    /// Identifies the period a point in time falls in, `None` when we never rotate on time.
+    fn period(self, time: &DateTime<Local>) -> Option<String> {
+        match self {
+            Self::Never => None,
+            Self::Hourly => Some(time.format(constants::HOURLY_PERIOD_FORMAT).to_string()),
+            Self::Daily => Some(time.format(constants::DAILY_PERIOD_FORMAT).to_string()),
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = error::Logging;
+
+    #[inline]
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "never" | "" => Ok(Self::Never),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            _ => Err(error::Logging::InvalidInterval(value.to_owned())),
+        }
+    }
+}
+
+///
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Rotation {
+    /// In bytes, `0` turns off size based rotation.
+    pub max_size: u64,
+
+    ///
+    pub interval: Interval,
+
+    /// How many rotated files to hold onto, `0` keeps all of them.
+    pub max_files: usize,
+}
+
+impl Rotation {
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn new(max_size: u64, interval: Interval, max_files: usize) -> Self {
+        Self {
+            max_size,
+            interval,
+            max_files,
+        }
+    }
+}
+
+///
+#[derive(Debug)]
+pub struct RotatingFile {
+    ///
+    path: PathBuf,
+
+    ///
+    rotation: Rotation,
+
+    ///
+    file: File,
+
+    ///
+    size: u64,
+
+    ///
+    period: Option<String>,
+}
+
+impl RotatingFile {
+    /// Appends to whatever is already at `path`.
+    ///
+    /// # Errors
+    /// Fails when the file (or its parent directory) can't be created.
+    #[inline]
+    pub fn open<P: AsRef<Path>>(path: P, rotation: Rotation) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = Self::append(&path)?;
+        let metadata = file.metadata()?;
+
+        // A file left over from a previous period should roll over on the first write
+        let modified = metadata
+            .modified()
+            .map_or_else(|_| Local::now(), DateTime::<Local>::from);
+
+        Ok(Self {
+            period: rotation.interval.period(&modified),
+            size: metadata.len(),
+            path,
+            rotation,
+            file,
+        })
+    }
+
+    ///
+    fn append(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    ///
+    fn write_at(&mut self, buf: &[u8], now: &DateTime<Local>) -> io::Result<usize> {
+        let period = self.rotation.interval.period(now);
+        let incoming = u64::try_from(buf.len()).unwrap_or(u64::MAX);
+
+        let too_large = self.rotation.max_size > 0
+            && self.size > 0
+            && self.size.saturating_add(incoming) > self.rotation.max_size;
+
+        if too_large || period != self.period {
+            self.rotate(now)?;
+            self.period = period;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size = self
+            .size
+            .saturating_add(u64::try_from(written).unwrap_or(u64::MAX));
+
+        Ok(written)
+    }
+
+    /// Moves the current file aside and starts a new one in its place.
+    fn rotate(&mut self, now: &DateTime<Local>) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.size > 0 {
+            let suffix = now.format(constants::ROTATED_SUFFIX_FORMAT).to_string();
+
+            let mut rotated = self.rotated_path(&suffix);
+            let mut attempt = 0_u32;
+            while rotated.exists() {
+                attempt = attempt.saturating_add(1);
+                rotated = self.rotated_path(&format!("{suffix}-{attempt}"));
+            }
+
+            fs::rename(&self.path, rotated)?;
+            self.file = Self::append(&self.path)?;
+            self.size = 0;
+        }
+
+        self.prune()
+    }
+
+    ///
+    fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    ///
+    fn rotated_path(&self, suffix: &str) -> PathBuf {
+        self.path
+            .with_file_name(format!("{}.{suffix}", self.file_name()))
+    }
+
+    /// Every file we've rotated out, oldest first.
+    fn rotated_files(&self) -> io::Result<Vec<PathBuf>> {
+        let prefix = format!("{}.", self.file_name());
+        let parent = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+
+        let mut rotated = vec![];
+        for entry in fs::read_dir(parent)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                rotated.push(entry.path());
+            }
+        }
+        rotated.sort();
+
+        Ok(rotated)
+    }
+
+    ///
+    fn prune(&self) -> io::Result<()> {
+        if self.rotation.max_files == 0 {
+            return Ok(());
+        }
+
+        let rotated = self.rotated_files()?;
+        let excess = rotated.len().saturating_sub(self.rotation.max_files);
+
+        for path in rotated.iter().take(excess) {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, &Local::now())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use chrono::TimeZone;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("common_logging_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_appends_to_existing_file() -> io::Result<()> {
+        let dir = scratch_dir("append");
+        let path = dir.join("apid.log");
+
+        fs::write(&path, "first\n")?;
+
+        let mut file = RotatingFile::open(&path, Rotation::default())?;
+        file.write_all(b"second\n")?;
+        file.flush()?;
+
+        assert_eq!("first\nsecond\n", fs::read_to_string(&path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotates_on_size_and_prunes() -> io::Result<()> {
+        let dir = scratch_dir("size");
+        let path = dir.join("api.log");
+
+        let mut file = RotatingFile::open(&path, Rotation::new(10, Interval::Never, 2))?;
+        for _ in 0..5 {
+            file.write_all(b"0123456789")?;
+        }
+        file.flush()?;
+
+        assert_eq!("0123456789", fs::read_to_string(&path)?);
+        assert_eq!(2, file.rotated_files()?.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotates_on_new_period() -> io::Result<()> {
+        let dir = scratch_dir("period");
+        let path = dir.join("workflow.log");
+
+        let mut file = RotatingFile::open(&path, Rotation::new(0, Interval::Daily, 0))?;
+
+        let today = Local::now();
+        let tomorrow = today + chrono::Duration::days(1);
+        file.period = Interval::Daily.period(&today);
+
+        file.write_at(b"today\n", &today)?;
+        file.write_at(b"more today\n", &today)?;
+        assert!(file.rotated_files()?.is_empty());
+
+        file.write_at(b"tomorrow\n", &tomorrow)?;
+
+        let rotated = file.rotated_files()?;
+        assert_eq!(1, rotated.len());
+        assert_eq!("today\nmore today\n", fs::read_to_string(&rotated[0])?);
+        assert_eq!("tomorrow\n", fs::read_to_string(&path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_periods() {
+        let time = Local.with_ymd_and_hms(2023, 4, 5, 13, 0, 0).unwrap();
+
+        assert_eq!(None, Interval::Never.period(&time));
+        assert_eq!(
+            Some("2023040513".to_owned()),
+            Interval::Hourly.period(&time)
+        );
+        assert_eq!(Some("20230405".to_owned()), Interval::Daily.period(&time));
+        assert!("weekly".parse::<Interval>().is_err());
+    }
+}
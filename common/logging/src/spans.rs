@@ -0,0 +1,318 @@
+//! Spans for every execution and HTTP call, shipped to an OTLP collector and/or a local file.
+
+use std::{
+    io::Write as _,
+    panic,
+    path::PathBuf,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures_util::future::{self, BoxFuture};
+use opentelemetry::{
+    trace::{SpanId, Status, TraceError},
+    KeyValue,
+};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig as _};
+use opentelemetry_sdk::{
+    export::trace::{ExportResult, SpanData, SpanExporter},
+    trace::{Config, TracerProvider},
+    Resource,
+};
+use serde_json::json;
+
+use crate::{
+    constants, error,
+    rotation::{RotatingFile, Rotation},
+};
+
+///
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct SpanOptions {
+    /// Reported to the collector as `service.name`.
+    pub service_name: String,
+
+    /// Where to POST spans using OTLP over HTTP, i.e. `http://localhost:4318/v1/traces`.
+    pub otlp_endpoint: Option<String>,
+
+    /// A JSON lines file to write spans to, rotated like the other logs.
+    pub path: Option<PathBuf>,
+}
+
+impl SpanOptions {
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn new(
+        service_name: String,
+        otlp_endpoint: Option<String>,
+        path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            service_name,
+            otlp_endpoint,
+            path,
+        }
+    }
+}
+
+/// Builds a provider with an exporter for each destination, `None` when there's nowhere to send spans.
+///
+/// # Errors
+/// Fails when the span file can't be opened or the OTLP exporter can't be built.
+#[inline]
+pub fn provider(
+    options: &SpanOptions,
+    rotation: &Rotation,
+) -> error::Result<Option<TracerProvider>> {
+    if options.otlp_endpoint.is_none() && options.path.is_none() {
+        return Ok(None);
+    }
+
+    let resource = Resource::new(vec![KeyValue::new(
+        constants::SERVICE_NAME_KEY,
+        options.service_name.clone(),
+    )]);
+    let mut builder =
+        TracerProvider::builder().with_config(Config::default().with_resource(resource));
+
+    if let &Some(ref endpoint) = &options.otlp_endpoint {
+        // Built on its own thread, the blocking client can't be created inside the async runtime
+        let exporter = thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    SpanExporterBuilder::from(
+                        opentelemetry_otlp::new_exporter()
+                            .http()
+                            .with_endpoint(endpoint.clone()),
+                    )
+                    .build_span_exporter()
+                })
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err))
+        })?;
+        builder = builder.with_simple_exporter(exporter);
+    }
+
+    if let &Some(ref path) = &options.path {
+        let exporter = FileExporter::new(RotatingFile::open(path, rotation.clone())?);
+        builder = builder.with_simple_exporter(exporter);
+    }
+
+    Ok(Some(builder.build()))
+}
+
+/// Writes each finished span as a line of JSON.
+#[derive(Debug)]
+pub struct FileExporter {
+    ///
+    file: RotatingFile,
+}
+
+impl FileExporter {
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn new(file: RotatingFile) -> Self {
+        Self { file }
+    }
+
+    ///
+    fn write_span(&mut self, span: &SpanData) -> std::io::Result<()> {
+        let parent = if span.parent_span_id == SpanId::INVALID {
+            serde_json::Value::Null
+        } else {
+            span.parent_span_id.to_string().into()
+        };
+
+        let status = match &span.status {
+            &Status::Unset => json!({ "code": "unset" }),
+            &Status::Ok => json!({ "code": "ok" }),
+            &Status::Error { ref description } => {
+                json!({ "code": "error", "description": description })
+            }
+        };
+
+        let attributes: serde_json::Map<String, serde_json::Value> = span
+            .attributes
+            .iter()
+            .map(|(key, value)| (key.to_string(), attribute_value(value)))
+            .collect();
+
+        let duration_ms = span
+            .end_time
+            .duration_since(span.start_time)
+            .map(|duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+            .unwrap_or_default();
+
+        let line = json!({
+            "trace_id": span.span_context.trace_id().to_string(),
+            "span_id": span.span_context.span_id().to_string(),
+            "parent_span_id": parent,
+            "name": span.name,
+            "kind": format!("{:?}", span.span_kind).to_lowercase(),
+            "start_unix_nano": unix_nanos(span.start_time),
+            "end_unix_nano": unix_nanos(span.end_time),
+            "duration_ms": duration_ms,
+            "status": status,
+            "attributes": attributes,
+        });
+
+        writeln!(self.file, "{line}")?;
+        self.file.flush()
+    }
+}
+
+impl SpanExporter for FileExporter {
+    #[inline]
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let result = batch
+            .iter()
+            .try_for_each(|span| self.write_span(span))
+            .map_err(|err| TraceError::Other(Box::new(err)));
+
+        Box::pin(future::ready(result))
+    }
+}
+
+///
+fn attribute_value(value: &opentelemetry::Value) -> serde_json::Value {
+    match value {
+        &opentelemetry::Value::Bool(value) => value.into(),
+        &opentelemetry::Value::I64(value) => value.into(),
+        &opentelemetry::Value::F64(value) => value.into(),
+        &opentelemetry::Value::String(ref value) => value.as_str().into(),
+        &opentelemetry::Value::Array(ref value) => value.to_string().into(),
+    }
+}
+
+///
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use opentelemetry::trace::TracerProvider as _;
+    use std::{
+        fs,
+        io::{BufRead, BufReader, Read},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn run_nested(provider: &TracerProvider) {
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("test"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let root = tracing::info_span!("engine.run", service = "workflow");
+            let child = tracing::info_span!(parent: &root, "engine.run", service = "connector");
+            let call = tracing::info_span!(parent: &child, "http.request", otel.kind = "client");
+
+            drop(call);
+            drop(child);
+            drop(root);
+        });
+
+        provider.force_flush();
+    }
+
+    #[test]
+    fn test_file_exporter_links_parents() {
+        let dir = std::env::temp_dir().join(format!("common_logging_spans_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("spans.log");
+
+        let options = SpanOptions::new("apid".into(), None, Some(path.clone()));
+        let provider = provider(&options, &Rotation::default()).unwrap().unwrap();
+        run_nested(&provider);
+
+        let spans: Vec<serde_json::Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(3, spans.len());
+
+        // Children finish first
+        let (call, child, root) = (&spans[0], &spans[1], &spans[2]);
+        assert_eq!("http.request", call["name"]);
+        assert_eq!("client", call["kind"]);
+        assert_eq!(child["span_id"], call["parent_span_id"]);
+        assert_eq!(root["span_id"], child["parent_span_id"]);
+        assert_eq!(serde_json::Value::Null, root["parent_span_id"]);
+        assert_eq!(root["trace_id"], call["trace_id"]);
+        assert_eq!("connector", child["attributes"]["service"]);
+    }
+
+    #[test]
+    fn test_otlp_exporter_posts_to_collector() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+
+        // Stands in for a collector, takes each export and hands us the request line and body
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .unwrap();
+                tx.send((request_line, body)).unwrap();
+            }
+        });
+
+        let options = SpanOptions::new("apid".into(), Some(endpoint), None);
+        let provider = provider(&options, &Rotation::default()).unwrap().unwrap();
+        run_nested(&provider);
+
+        let mut names = vec![];
+        for _ in 0..3 {
+            let (request_line, body) = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert!(request_line.starts_with("POST /v1/traces"));
+
+            let body = String::from_utf8_lossy(&body);
+            assert!(body.contains("apid"));
+            names.push(
+                ["http.request", "engine.run"]
+                    .into_iter()
+                    .find(|name| body.contains(name))
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!(vec!["http.request", "engine.run", "engine.run"], names);
+    }
+}
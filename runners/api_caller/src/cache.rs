@@ -0,0 +1,344 @@
+//! GET responses kept on disk, for connectors that turn it on in `config.json`.
+//!
+//! Entries are keyed by a hash of the connector, operation, URL, request headers and the run's
+//! credentials, so a response is never shared between credentials. A fresh entry
+//! is used without calling the API, a stale one is revalidated with `If-None-Match` or
+//! `If-Modified-Since` when the response had an `ETag` or `Last-Modified`.
+
+use core::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::Engine as _;
+use core_entities::service::ResponseCacheConfig;
+use credential_entities::credentials::Authentication;
+use protobuf::Message as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+/// A response as it came off the wire, decoded again each time it's used.
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the epoch.
+    stored_at: u64,
+
+    ///
+    status: u16,
+
+    /// Keyed by the lowercased header name.
+    headers: HashMap<String, serde_json::Value>,
+
+    /// Base64 encoded.
+    body: String,
+}
+
+impl Entry {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new(status: u16, headers: HashMap<String, serde_json::Value>, body: &[u8]) -> Self {
+        Self {
+            stored_at: now(),
+            status,
+            headers,
+            body: base64::engine::general_purpose::STANDARD.encode(body),
+        }
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn body(&self) -> Vec<u8> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.body)
+            .unwrap_or_default()
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn status(&self) -> u16 {
+        self.status
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn headers(&self) -> &HashMap<String, serde_json::Value> {
+        &self.headers
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(serde_json::Value::as_str)
+    }
+
+    /// Used as it is, without asking the API.
+    #[must_use]
+    #[inline]
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        now().saturating_sub(self.stored_at) < ttl.as_secs()
+    }
+
+    /// The API said it's still good, start its TTL over.
+    #[inline]
+    pub fn touch(&mut self) {
+        self.stored_at = now();
+    }
+}
+
+///
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// A directory per connector with a file per response.
+pub struct ResponseCache {
+    ///
+    root: PathBuf,
+
+    /// Keeps temporary files apart when the same key is written from a few threads at once.
+    writes: AtomicUsize,
+}
+
+impl From<PathBuf> for ResponseCache {
+    ///
+    #[inline]
+    fn from(value: PathBuf) -> Self {
+        Self {
+            root: value,
+            writes: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl ResponseCache {
+    /// Entries that can't be read are as good as missing.
+    #[must_use]
+    #[inline]
+    pub fn get(&self, key: &Key) -> Option<Entry> {
+        let entry = fs::read(self.root.join(&key.connector).join(&key.hash)).ok()?;
+        serde_json::from_slice(&entry).ok()
+    }
+
+    /// Written to the side and then renamed so a reader never sees half an entry.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn put(&self, key: &Key, entry: &Entry) -> io::Result<()> {
+        let dir = self.root.join(&key.connector);
+        fs::create_dir_all(&dir)?;
+
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let temp = dir.join(format!(".{}.{}.{write}", key.hash, std::process::id()));
+        fs::write(&temp, serde_json::to_vec(entry)?)?;
+        fs::rename(temp, dir.join(&key.hash))
+    }
+}
+
+/// Where a request's response is kept.
+pub struct Key {
+    /// Only ever a directory name, never part of a path.
+    connector: String,
+
+    ///
+    hash: String,
+}
+
+impl Key {
+    /// Only the `vary` headers count when there are any, otherwise all of them do. The
+    /// credentials always count, whatever `vary` says, so that different credentials never share
+    /// a response.
+    #[must_use]
+    #[inline]
+    pub fn new<'header, I>(
+        connector: &str,
+        operation: &str,
+        url: &str,
+        headers: I,
+        vary: &[String],
+        credentials: &[u8],
+    ) -> Self
+    where
+        I: IntoIterator<Item = (&'header str, &'header [u8])>,
+    {
+        let mut headers: Vec<_> = headers
+            .into_iter()
+            .map(|(name, value)| (name.to_lowercase(), value))
+            .filter(|&(ref name, _)| {
+                vary.is_empty() || vary.iter().any(|vary| vary.eq_ignore_ascii_case(name))
+            })
+            .collect();
+        headers.sort();
+
+        let mut hasher = Sha256::new();
+        for part in [operation.as_bytes(), url.as_bytes(), credentials] {
+            hasher.update(part);
+            hasher.update([0]);
+        }
+        for (name, value) in headers {
+            hasher.update(name.as_bytes());
+            hasher.update([b':']);
+            hasher.update(value);
+            hasher.update([0]);
+        }
+
+        Self {
+            connector: format!("{:x}", Sha256::digest(connector.as_bytes())),
+            hash: format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+/// The cache as a run sees it, `None` when it's off for the connector or the run.
+pub struct Caching<'cache> {
+    ///
+    pub store: &'cache ResponseCache,
+
+    ///
+    pub ttl: Duration,
+
+    ///
+    pub vary: &'cache [String],
+
+    /// The run's credentials serialized, part of every key.
+    pub credentials: Vec<u8>,
+}
+
+impl<'cache> Caching<'cache> {
+    /// Off for a run whose credentials can't be told apart from anyone else's.
+    #[must_use]
+    #[inline]
+    pub fn new(
+        store: Option<&'cache ResponseCache>,
+        config: &'cache ResponseCacheConfig,
+        creds: Option<&Authentication>,
+    ) -> Option<Self> {
+        if !config.enabled.as_ref().is_some_and(|enabled| enabled.value) {
+            return None;
+        }
+
+        let credentials = match creds {
+            Some(creds) => creds.write_to_bytes().ok()?,
+            None => vec![],
+        };
+
+        Some(Self {
+            store: store?,
+            ttl: Duration::from_secs(config.ttlSeconds.as_ref().map_or(0, |ttl| ttl.value)),
+            vary: &config.varyHeaders,
+            credentials,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_keys_only_vary_on_the_listed_headers() {
+        let key = |headers: &[(&'static str, &'static str)], vary: &[String], creds: &[u8]| {
+            Key::new(
+                "acme",
+                "list",
+                "https://example.com/items",
+                headers
+                    .iter()
+                    .map(|&(name, value)| (name, value.as_bytes())),
+                vary,
+                creds,
+            )
+            .hash
+        };
+
+        let first = [
+            ("Authorization", "Bearer a"),
+            ("Accept", "application/json"),
+            ("User-Agent", "one"),
+        ];
+        let second = [
+            ("Accept", "application/json"),
+            ("authorization", "Bearer b"),
+            ("User-Agent", "two"),
+        ];
+
+        assert_ne!(key(&first, &[], b"a"), key(&second, &[], b"b"));
+        assert_eq!(
+            key(&first, &["accept".into()], b"a"),
+            key(&first, &["Accept".into()], b"a")
+        );
+        // Credentials count even when vary leaves out the headers they're sent in
+        assert_ne!(
+            key(&first, &["accept".into()], b"a"),
+            key(&second, &["Accept".into()], b"b")
+        );
+    }
+
+    #[test]
+    fn test_profiles_never_share_entries() {
+        let mut config = ResponseCacheConfig::new();
+        config.enabled = Some(protobuf::well_known_types::wrappers::BoolValue {
+            value: true,
+            ..Default::default()
+        })
+        .into();
+        config.varyHeaders = vec!["Accept".into()];
+        let store = ResponseCache::from(std::env::temp_dir());
+
+        let mut personal = Authentication::new();
+        personal.mut_header().value = "Bearer a".into();
+        let mut work = Authentication::new();
+        work.mut_header().value = "Bearer b".into();
+
+        let key = |creds: &Authentication| {
+            let caching = Caching::new(Some(&store), &config, Some(creds)).unwrap();
+            Key::new(
+                "acme",
+                "list",
+                "https://example.com/items",
+                [("accept", b"application/json".as_slice())],
+                caching.vary,
+                &caching.credentials,
+            )
+            .hash
+        };
+
+        assert_ne!(key(&personal), key(&work));
+        assert_eq!(key(&work), key(&work.clone()));
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let root = std::env::temp_dir().join(format!("api-caller-cache-{}", std::process::id()));
+        let cache = ResponseCache::from(root.clone());
+        let key = Key::new("acme", "list", "https://example.com", [], &[], &[]);
+        assert!(cache.get(&key).is_none());
+
+        let headers = HashMap::from([("etag".to_owned(), "\"v1\"".into())]);
+        cache
+            .put(&key, &Entry::new(200, headers, b"[1, 2]"))
+            .unwrap();
+
+        let entry = cache.get(&key).unwrap();
+        assert_eq!(Some("\"v1\""), entry.header("etag"));
+        assert_eq!(b"[1, 2]".to_vec(), entry.body());
+        assert!(entry.is_fresh(Duration::from_secs(60)));
+        assert!(!entry.is_fresh(Duration::ZERO));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Recorded HTTP traffic, one JSON line per exchange in a file per execution.
+//!
+//! Everything is redacted the same as the API log before it's written, so a replayed request is
+//! matched on its redacted method, URL and body. Requests that were made more than once are
+//! answered in the order they were recorded, the last answer is reused after that.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, OpenOptions},
+    io::Write as _,
+    path::Path,
+    sync::Mutex,
+};
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+
+/// A request and what came back.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Interaction {
+    ///
+    pub method: String,
+
+    ///
+    pub url: String,
+
+    /// Only for the record, they aren't matched on.
+    #[serde(default)]
+    pub request_headers: HashMap<String, String>,
+
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+
+    ///
+    pub response: Recorded,
+}
+
+///
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recorded {
+    ///
+    pub status: u16,
+
+    /// Keyed by the lowercased header name.
+    #[serde(default)]
+    pub headers: HashMap<String, serde_json::Value>,
+
+    /// When it's UTF-8, so that cassettes can be read and edited by hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+impl Recorded {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new(status: u16, headers: HashMap<String, serde_json::Value>, bytes: &[u8]) -> Self {
+        let (body, body_base64) = match core::str::from_utf8(bytes) {
+            Ok(text) => (Some(text.to_owned()), None),
+            Err(_) => (
+                None,
+                Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+            ),
+        };
+
+        Self {
+            status,
+            headers,
+            body,
+            body_base64,
+        }
+    }
+
+    ///
+    /// # Errors
+    /// When `bodyBase64` isn't base64.
+    #[inline]
+    pub fn bytes(&self) -> error::Result<Vec<u8>> {
+        match (&self.body, &self.body_base64) {
+            (&Some(ref body), _) => Ok(body.as_bytes().to_vec()),
+            (&None, &Some(ref encoded)) => {
+                Ok(base64::engine::general_purpose::STANDARD.decode(encoded)?)
+            }
+            (&None, &None) => Ok(vec![]),
+        }
+    }
+}
+
+/// Keeps writers from interleaving and tracks which answers each replayed execution has used.
+#[derive(Default)]
+pub struct Cassettes {
+    ///
+    writing: Mutex<()>,
+
+    /// The recorded exchanges each execution has used.
+    replayed: Mutex<HashMap<String, HashSet<usize>>>,
+}
+
+impl Cassettes {
+    ///
+    /// # Errors
+    #[inline]
+    pub fn record(&self, path: &Path, interaction: &Interaction) -> error::Result<()> {
+        let mut line = serde_json::to_vec(interaction)?;
+        line.push(b'\n');
+
+        let _writing = self
+            .writing
+            .lock()
+            .map_err(|err| error::APICaller::PoisonedLock(err.to_string()))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(&line)?;
+
+        Ok(())
+    }
+
+    /// The recorded answer to a request, `session` keeps executions replaying the same
+    /// cassette from using up each other's answers.
+    ///
+    /// # Errors
+    /// When the cassette can't be read or nothing in it matches.
+    #[inline]
+    pub fn replay(
+        &self,
+        path: &Path,
+        session: &str,
+        method: &str,
+        url: &str,
+        body: Option<&serde_json::Value>,
+    ) -> error::Result<Recorded> {
+        let interactions = read(path)?;
+        let matching: Vec<usize> = interactions
+            .iter()
+            .enumerate()
+            .filter(|&(_, interaction)| {
+                interaction.method.eq_ignore_ascii_case(method)
+                    && interaction.url == url
+                    && interaction.body.as_ref() == body
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        let mut replayed = self
+            .replayed
+            .lock()
+            .map_err(|err| error::APICaller::PoisonedLock(err.to_string()))?;
+        let used = replayed.entry(session.to_owned()).or_default();
+
+        let next = matching
+            .iter()
+            .find(|&index| !used.contains(index))
+            .or_else(|| matching.last())
+            .copied()
+            .ok_or_else(|| error::APICaller::NotRecorded(format!("{method} {url}")))?;
+        used.insert(next);
+
+        interactions
+            .into_iter()
+            .nth(next)
+            .map(|interaction| interaction.response)
+            .ok_or_else(|| error::APICaller::NotRecorded(format!("{method} {url}")))
+    }
+}
+
+/// Blank lines are skipped so cassettes can be split up by hand.
+fn read(path: &Path) -> error::Result<Vec<Interaction>> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn interaction(url: &str, body: &str) -> Interaction {
+        Interaction {
+            method: "GET".into(),
+            url: url.into(),
+            request_headers: HashMap::new(),
+            body: None,
+            response: Recorded::new(200, HashMap::new(), body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_replays_in_recorded_order() {
+        let path = std::env::temp_dir()
+            .join(format!("api-caller-cassette-{}", std::process::id()))
+            .join("run.jsonl");
+        let cassettes = Cassettes::default();
+        cassettes
+            .record(&path, &interaction("https://example.com/jobs/1", "running"))
+            .unwrap();
+        cassettes
+            .record(&path, &interaction("https://example.com/jobs/1", "done"))
+            .unwrap();
+
+        let replay = |session| {
+            let recorded = cassettes
+                .replay(&path, session, "get", "https://example.com/jobs/1", None)
+                .unwrap();
+            String::from_utf8(recorded.bytes().unwrap()).unwrap()
+        };
+        assert_eq!("running", replay("first"));
+        assert_eq!("done", replay("first"));
+        assert_eq!("done", replay("first"));
+        assert_eq!("running", replay("second"));
+
+        assert!(matches!(
+            cassettes.replay(&path, "first", "POST", "https://example.com/jobs/1", None),
+            Err(error::APICaller::NotRecorded(_))
+        ));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_binary_bodies_are_base64() {
+        let recorded = Recorded::new(200, HashMap::new(), &[0xff, 0x00]);
+        assert_eq!(None, recorded.body);
+        assert_eq!(vec![0xff, 0x00], recorded.bytes().unwrap());
+    }
+}
@@ -0,0 +1,211 @@
+//! HTTP clients, one per connector and kept around so that pages and runs share its connections.
+
+use core::time::Duration;
+use std::{collections::HashMap, fs, sync::Mutex};
+
+use core_entities::service::HttpClientConfig;
+use protobuf::MessageField;
+use reqwest::{
+    blocking::{Client, ClientBuilder},
+    redirect, Certificate, NoProxy, Proxy,
+};
+
+use crate::{constants, error};
+
+/// A connector's settings on top of the defaults, field by field.
+#[must_use]
+#[inline]
+pub fn layered(defaults: &HttpClientConfig, connector: &HttpClientConfig) -> HttpClientConfig {
+    let pick = |over: &String, base: &String| {
+        if over.is_empty() {
+            base.clone()
+        } else {
+            over.clone()
+        }
+    };
+
+    HttpClientConfig {
+        connectTimeoutMs: either(&connector.connectTimeoutMs, &defaults.connectTimeoutMs),
+        readTimeoutMs: either(&connector.readTimeoutMs, &defaults.readTimeoutMs),
+        proxy: pick(&connector.proxy, &defaults.proxy),
+        httpProxy: pick(&connector.httpProxy, &defaults.httpProxy),
+        httpsProxy: pick(&connector.httpsProxy, &defaults.httpsProxy),
+        noProxy: pick(&connector.noProxy, &defaults.noProxy),
+        caCertificates: defaults
+            .caCertificates
+            .iter()
+            .chain(&connector.caCertificates)
+            .cloned()
+            .collect(),
+        maxRedirects: either(&connector.maxRedirects, &defaults.maxRedirects),
+        http2: either(&connector.http2, &defaults.http2),
+        userAgent: pick(&connector.userAgent, &defaults.userAgent),
+        ..Default::default()
+    }
+}
+
+///
+fn either<T: Clone>(over: &MessageField<T>, base: &MessageField<T>) -> MessageField<T> {
+    if over.is_some() {
+        over.clone()
+    } else {
+        base.clone()
+    }
+}
+
+/// Builds a client for a connector the first time it's called, and again whenever its
+/// settings change, i.e. after `config.json` is edited.
+#[derive(Default)]
+pub struct Clients {
+    ///
+    defaults: HttpClientConfig,
+
+    ///
+    clients: Mutex<HashMap<String, (HttpClientConfig, Client)>>,
+}
+
+impl Clients {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new(defaults: HttpClientConfig) -> Self {
+        Self {
+            defaults,
+            clients: Mutex::default(),
+        }
+    }
+
+    /// Clients are cheap to clone, they share the same pool.
+    ///
+    /// # Errors
+    /// When the settings are invalid, i.e. a proxy that isn't a URL or a CA file that can't be read.
+    #[inline]
+    pub fn get(&self, connector: &str, config: &HttpClientConfig) -> error::Result<Client> {
+        let config = layered(&self.defaults, config);
+
+        let mut clients = self
+            .clients
+            .lock()
+            .map_err(|err| error::APICaller::PoisonedLock(err.to_string()))?;
+
+        if let Some(&(ref built_with, ref client)) = clients.get(connector) {
+            if *built_with == config {
+                return Ok(client.clone());
+            }
+        }
+
+        let client = build(&config)?;
+        clients.insert(connector.to_owned(), (config, client.clone()));
+        Ok(client)
+    }
+}
+
+///
+fn build(config: &HttpClientConfig) -> error::Result<Client> {
+    let user_agent = if config.userAgent.is_empty() {
+        constants::DEFAULT_USER_AGENT
+    } else {
+        &config.userAgent
+    };
+    let mut builder = ClientBuilder::new().user_agent(user_agent);
+
+    if let Some(timeout) = config.connectTimeoutMs.as_ref() {
+        builder = builder.connect_timeout(Duration::from_millis(timeout.value));
+    }
+    if let Some(timeout) = config.readTimeoutMs.as_ref() {
+        builder = builder.timeout(Duration::from_millis(timeout.value));
+    }
+
+    let no_proxy = || NoProxy::from_string(&config.noProxy);
+    if !config.httpProxy.is_empty() {
+        builder = builder.proxy(Proxy::http(&config.httpProxy)?.no_proxy(no_proxy()));
+    }
+    if !config.httpsProxy.is_empty() {
+        builder = builder.proxy(Proxy::https(&config.httpsProxy)?.no_proxy(no_proxy()));
+    }
+    if !config.proxy.is_empty() {
+        builder = builder.proxy(Proxy::all(&config.proxy)?.no_proxy(no_proxy()));
+    }
+
+    for path in &config.caCertificates {
+        for certificate in certificates(&fs::read_to_string(path)?)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if let Some(max) = config.maxRedirects.as_ref() {
+        builder = builder.redirect(if max.value == 0 {
+            redirect::Policy::none()
+        } else {
+            redirect::Policy::limited(usize::try_from(max.value)?)
+        });
+    }
+
+    builder = match config.http2.as_ref() {
+        Some(http2) if http2.value => builder.http2_prior_knowledge(),
+        Some(_) => builder.http1_only(),
+        None => builder,
+    };
+
+    Ok(builder.build()?)
+}
+
+/// Every certificate in a PEM bundle, corporate CA files usually have a few.
+fn certificates(pem: &str) -> error::Result<Vec<Certificate>> {
+    pem.split_inclusive(constants::PEM_CERTIFICATE_END)
+        .filter(|block| block.contains(constants::PEM_CERTIFICATE_BEGIN))
+        .map(|block| Ok(Certificate::from_pem(block.trim().as_bytes())?))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use protobuf::well_known_types::wrappers::{UInt32Value, UInt64Value};
+
+    #[test]
+    fn test_connector_settings_override_the_defaults() {
+        let defaults = HttpClientConfig {
+            connectTimeoutMs: Some(UInt64Value {
+                value: 1000,
+                ..Default::default()
+            })
+            .into(),
+            proxy: "http://proxy.internal:3128".into(),
+            userAgent: "apid".into(),
+            ..Default::default()
+        };
+        let connector = HttpClientConfig {
+            maxRedirects: Some(UInt32Value {
+                value: 0,
+                ..Default::default()
+            })
+            .into(),
+            userAgent: "acme-connector".into(),
+            ..Default::default()
+        };
+
+        let config = layered(&defaults, &connector);
+        assert_eq!(1000, config.connectTimeoutMs.value);
+        assert_eq!(0, config.maxRedirects.value);
+        assert_eq!("http://proxy.internal:3128", config.proxy);
+        assert_eq!("acme-connector", config.userAgent);
+    }
+
+    #[test]
+    fn test_clients_are_reused_until_settings_change() {
+        let clients = Clients::default();
+        let config = HttpClientConfig::new();
+        clients.get("acme", &config).unwrap();
+        clients.get("acme", &config).unwrap();
+        assert_eq!(1, clients.clients.lock().unwrap().len());
+
+        let invalid = HttpClientConfig {
+            proxy: "not a url".into(),
+            ..Default::default()
+        };
+        assert!(clients.get("acme", &invalid).is_err());
+    }
+}
@@ -1,10 +1,91 @@
 //!
 
 ///
-pub const DATETIME_FORMAT: &str = "%a %b %e %Y %I:%M:%S %p";
+pub const OUTCOME_COMPLETED: &str = "completed";
+
+///
+pub const OUTCOME_FAILED: &str = "failed";
+
+/// Marks a span as failed for OpenTelemetry.
+pub const SPAN_STATUS_ERROR: &str = "ERROR";
 
 ///
 pub const RESPONSE_BODY_PREFIX: &str = "$response.body#";
 
+///
+pub const RESPONSE_HEADER_PREFIX: &str = "$response.header.";
+
+///
+pub const LINK_HEADER: &str = "link";
+
 ///
 pub const DEFAULT_LIMIT: i32 = 0;
+
+///
+pub const AUTHORIZATION_HEADER: &str = "authorization";
+
+/// Stands in for secrets in the API log.
+pub const REDACTED: &str = "********";
+
+/// Stands in for a status code when the request never got a response.
+pub const STATUS_ERROR: &str = "error";
+
+/// Counter, labelled by connector and status code.
+pub const METRIC_HTTP_CALLS: &str = "apid_http_calls_total";
+
+/// Histogram in seconds, labelled by connector.
+pub const METRIC_HTTP_DURATION: &str = "apid_http_call_duration_seconds";
+
+/// Counter, labelled by connector.
+pub const METRIC_PAGES: &str = "apid_pages_fetched_total";
+
+/// Sent unless the connector or apid's configuration sets another.
+pub const DEFAULT_USER_AGENT: &str = "APICLI/1.0";
+
+///
+pub const PEM_CERTIFICATE_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+
+///
+pub const PEM_CERTIFICATE_END: &str = "-----END CERTIFICATE-----";
+
+/// Run option naming a directory to save binary responses to.
+pub const DOWNLOAD_OPTION: &str = "download";
+
+/// When a binary response doesn't say what it is.
+pub const BINARY_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Newline delimited JSON goes by a few names.
+pub const LINES_SUBTYPES: [&str; 5] = ["x-ndjson", "ndjson", "jsonl", "x-jsonlines", "jsonlines"];
+
+///
+pub const TSV_SUBTYPE: &str = "tab-separated-values";
+
+/// Application types that are still text.
+pub const TEXT_APPLICATION_SUBTYPES: [&str; 3] = ["javascript", "x-www-form-urlencoded", "graphql"];
+
+/// Run option that returns `{ status, headers, body }` instead of just the body.
+pub const ENVELOPE_OPTION: &str = "envelope";
+
+/// Run option overriding which statuses fail the run.
+pub const ERROR_STATUSES_OPTION: &str = "errorStatuses";
+
+/// Unless the connector or the run says otherwise.
+pub const DEFAULT_ERROR_STATUSES: &str = "4xx,5xx";
+
+/// Every status is handed back, the default with an envelope since the caller can see the status.
+pub const NO_ERROR_STATUSES: &str = "none";
+
+/// Run option that skips the response cache, neither reading nor writing it.
+pub const NO_CACHE_OPTION: &str = "noCache";
+
+///
+pub const STATUS_OK: u16 = 200;
+
+/// Our cached response is still good.
+pub const STATUS_NOT_MODIFIED: u16 = 304;
+
+/// A `Cache-Control` directive, responses with it aren't cached.
+pub const NO_STORE: &str = "no-store";
+
+/// Recorded traffic is saved as `<execution id>.jsonl`.
+pub const CASSETTE_EXTENSION: &str = ".jsonl";
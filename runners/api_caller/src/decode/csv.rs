@@ -0,0 +1,102 @@
+//! Delimited text (RFC 4180), the first row names the columns.
+
+/// Every row as an object keyed by the header, `None` when a quote is never closed.
+#[must_use]
+#[inline]
+pub fn to_objects(text: &str, delimiter: char) -> Option<Vec<serde_json::Value>> {
+    let mut rows = records(text, delimiter)?.into_iter();
+    let Some(header) = rows.next() else {
+        return Some(vec![]);
+    };
+
+    Some(
+        rows.map(|row| {
+            let mut object = serde_json::Map::new();
+            for (index, value) in row.into_iter().enumerate() {
+                // Columns past the header are kept under their position
+                let key = header
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_else(|| index.to_string());
+                object.insert(key, value.into());
+            }
+            object.into()
+        })
+        .collect(),
+    )
+}
+
+/// Splits rows and fields, handling quoted fields with delimiters, doubled quotes and line breaks.
+fn records(text: &str, delimiter: char) -> Option<Vec<Vec<String>>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(current) = chars.next() {
+        match current {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            _ if quoted => field.push(current),
+            '\r' => {}
+            '\n' => {
+                row.push(core::mem::take(&mut field));
+                if !(row.len() == 1 && row.first().is_some_and(String::is_empty)) {
+                    rows.push(core::mem::take(&mut row));
+                }
+                row.clear();
+            }
+            _ if current == delimiter => row.push(core::mem::take(&mut field)),
+            _ => field.push(current),
+        }
+    }
+
+    if quoted {
+        return None;
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    Some(rows)
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_rows_become_objects() {
+        let text =
+            "id,name,note\r\n1,Ada,\"likes \"\"math\"\", and tea\"\n2,Grace,\"multi\nline\"\n\n";
+
+        assert_eq!(
+            Some(vec![
+                serde_json::json!({ "id": "1", "name": "Ada", "note": "likes \"math\", and tea" }),
+                serde_json::json!({ "id": "2", "name": "Grace", "note": "multi\nline" }),
+            ]),
+            to_objects(text, ',')
+        );
+    }
+
+    #[test]
+    fn test_tabs_and_unclosed_quotes() {
+        assert_eq!(
+            Some(vec![serde_json::json!({ "a": "1", "b": "2" })]),
+            to_objects("a\tb\n1\t2", '\t')
+        );
+        assert_eq!(None, to_objects("a,b\n\"1,2", ','));
+    }
+}
@@ -0,0 +1,297 @@
+//! Response bodies as JSON, decoded according to their `Content-Type`.
+//!
+//! JSON is parsed, NDJSON becomes an array of items, CSV an array of objects keyed by the
+//! header and XML an object (see [`xml`]). Anything that isn't text is binary, saved to a file
+//! when there's somewhere to put it and returned base64 otherwise, along with what it is.
+
+mod csv;
+mod xml;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::Engine as _;
+
+use crate::{constants, error};
+
+/// How a body is decoded, picked from its media type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    ///
+    Json,
+
+    /// One JSON value per line.
+    Lines,
+
+    ///
+    Xml,
+
+    /// With the delimiter.
+    Delimited(char),
+
+    ///
+    Text,
+
+    ///
+    Binary,
+
+    /// No `Content-Type`, JSON if it parses, text if it's UTF-8 and binary otherwise.
+    Unknown,
+}
+
+impl Format {
+    ///
+    fn of(content_type: Option<&str>) -> Self {
+        let Some(media) = content_type.and_then(|value| value.parse::<mime::Mime>().ok()) else {
+            return Self::Unknown;
+        };
+
+        let subtype = media.subtype().as_str();
+        let suffix = media.suffix().map(|suffix| suffix.as_str());
+
+        if constants::LINES_SUBTYPES.contains(&subtype) {
+            Self::Lines
+        } else if subtype == mime::JSON || suffix == Some(mime::JSON.as_str()) {
+            Self::Json
+        } else if subtype == mime::XML || suffix == Some(mime::XML.as_str()) {
+            Self::Xml
+        } else if subtype == mime::CSV {
+            Self::Delimited(',')
+        } else if subtype == constants::TSV_SUBTYPE {
+            Self::Delimited('\t')
+        } else if media.type_() == mime::TEXT
+            || constants::TEXT_APPLICATION_SUBTYPES.contains(&subtype)
+        {
+            Self::Text
+        } else {
+            Self::Binary
+        }
+    }
+}
+
+/// Where binary bodies are saved, they're returned base64 when there's no directory.
+#[derive(Clone, Copy, Debug)]
+pub struct Downloads<'dir> {
+    ///
+    pub dir: Option<&'dir Path>,
+
+    /// Files go into a directory named after the execution.
+    pub execution_id: &'dir str,
+
+    /// Names the file when the response doesn't.
+    pub page: i32,
+}
+
+/// A response body as it came in.
+#[derive(Debug)]
+pub struct RawBody<'raw> {
+    ///
+    pub content_type: Option<&'raw str>,
+
+    /// `Content-Disposition`, only used for the name of a binary body.
+    pub disposition: Option<&'raw str>,
+
+    ///
+    pub bytes: Vec<u8>,
+}
+
+/// Bodies that claim to be something they aren't come back as text, same as an empty body is null.
+///
+/// # Errors
+/// Only when a binary body can't be saved.
+#[inline]
+pub fn decode(body: &RawBody, downloads: Downloads) -> error::Result<serde_json::Value> {
+    if body.bytes.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+
+    let format = Format::of(body.content_type);
+    if format == Format::Binary {
+        return binary(body, downloads);
+    }
+
+    let text = match core::str::from_utf8(&body.bytes) {
+        Ok(text) => text,
+        Err(_) if format == Format::Unknown => return binary(body, downloads),
+        Err(_) => return Ok(String::from_utf8_lossy(&body.bytes).into_owned().into()),
+    };
+
+    let decoded = match format {
+        Format::Json | Format::Unknown => serde_json::from_str(text).ok(),
+        Format::Lines => Some(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    serde_json::from_str::<serde_json::Value>(line).unwrap_or_else(|_| line.into())
+                })
+                .collect(),
+        ),
+        Format::Xml => xml::to_json(text),
+        Format::Delimited(delimiter) => csv::to_objects(text, delimiter).map(Into::into),
+        Format::Text | Format::Binary => None,
+    };
+
+    Ok(decoded.unwrap_or_else(|| text.into()))
+}
+
+/// What the body was along with either where it was saved or its content base64 encoded.
+fn binary(body: &RawBody, downloads: Downloads) -> error::Result<serde_json::Value> {
+    let content_type = body.content_type.unwrap_or(constants::BINARY_CONTENT_TYPE);
+    let file_name = body.disposition.and_then(file_name);
+
+    let mut metadata = serde_json::Map::new();
+    metadata.insert("contentType".into(), content_type.into());
+    metadata.insert("size".into(), body.bytes.len().into());
+    metadata.insert("fileName".into(), file_name.clone().into());
+
+    if let Some(dir) = downloads.dir {
+        let dir = dir.join(downloads.execution_id);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(
+            file_name
+                .unwrap_or_else(|| format!("page-{}.{}", downloads.page, extension(content_type))),
+        );
+        fs::write(&path, &body.bytes)?;
+
+        metadata.insert("path".into(), path.to_string_lossy().into_owned().into());
+    } else {
+        metadata.insert(
+            "base64".into(),
+            base64::engine::general_purpose::STANDARD
+                .encode(&body.bytes)
+                .into(),
+        );
+    }
+
+    Ok(metadata.into())
+}
+
+/// The name a `Content-Disposition` suggests, without any directories.
+fn file_name(disposition: &str) -> Option<String> {
+    let value = disposition
+        .split(';')
+        .map(str::trim)
+        .find_map(|part| part.strip_prefix("filename="))?
+        .trim_matches('"');
+
+    PathBuf::from(value)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+}
+
+/// A short extension from the media type, `bin` when there isn't an obvious one.
+fn extension(content_type: &str) -> String {
+    let subtype = content_type
+        .parse::<mime::Mime>()
+        .map(|media| media.subtype().as_str().to_owned())
+        .unwrap_or_default();
+
+    match subtype.as_str() {
+        "jpeg" => "jpg".into(),
+        "" | "octet-stream" => "bin".into(),
+        _ if subtype.chars().all(char::is_alphanumeric) => subtype,
+        _ => "bin".into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn raw<'raw>(content_type: Option<&'raw str>, bytes: &[u8]) -> RawBody<'raw> {
+        RawBody {
+            content_type,
+            disposition: None,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    const NOWHERE: Downloads<'static> = Downloads {
+        dir: None,
+        execution_id: "run",
+        page: 0,
+    };
+
+    #[test]
+    fn test_decodes_by_content_type() {
+        let decoded = |content_type, body: &str| {
+            decode(&raw(content_type, body.as_bytes()), NOWHERE).unwrap()
+        };
+
+        assert_eq!(
+            serde_json::json!({ "a": 1 }),
+            decoded(
+                Some("application/vnd.api+json; charset=utf-8"),
+                r#"{ "a": 1 }"#
+            )
+        );
+        assert_eq!(
+            serde_json::json!([{ "a": 1 }, { "a": 2 }]),
+            decoded(
+                Some("application/x-ndjson"),
+                "{ \"a\": 1 }\n\n{ \"a\": 2 }\n"
+            )
+        );
+        assert_eq!(
+            serde_json::json!([{ "id": "1", "name": "Ada" }]),
+            decoded(Some("text/csv"), "id,name\n1,Ada\n")
+        );
+        assert_eq!(
+            serde_json::json!({ "feed": { "entry": "hi" } }),
+            decoded(
+                Some("application/atom+xml"),
+                "<feed><entry>hi</entry></feed>"
+            )
+        );
+        assert_eq!(
+            serde_json::json!("<html></html>"),
+            decoded(Some("text/html"), "<html></html>")
+        );
+        assert_eq!(serde_json::json!("not json"), decoded(None, "not json"));
+        assert_eq!(
+            serde_json::Value::Null,
+            decoded(Some("application/json"), "")
+        );
+    }
+
+    #[test]
+    fn test_binary_bodies() {
+        let bytes = [0x25, 0x50, 0x44, 0x46, 0xff];
+        let decoded = decode(&raw(Some("application/pdf"), &bytes), NOWHERE).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "contentType": "application/pdf",
+                "size": 5,
+                "fileName": null,
+                "base64": "JVBERv8="
+            }),
+            decoded
+        );
+
+        let dir = std::env::temp_dir().join(format!("api-caller-decode-{}", std::process::id()));
+        let body = RawBody {
+            content_type: None,
+            disposition: Some("attachment; filename=\"../report.pdf\""),
+            bytes: bytes.to_vec(),
+        };
+        let downloads = Downloads {
+            dir: Some(&dir),
+            ..NOWHERE
+        };
+        let decoded = decode(&body, downloads).unwrap();
+
+        let path = dir.join("run").join("report.pdf");
+        assert_eq!("report.pdf", decoded["fileName"]);
+        assert_eq!(path.to_str().unwrap(), decoded["path"]);
+        assert_eq!(bytes.to_vec(), fs::read(&path).unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! XML as JSON: `{ "root": { "@attribute": "..", "child": "text", "repeated": [..] } }`.
+//!
+//! An element with only text becomes that text, otherwise its text is kept under `#text`.
+//! Children sharing a name become an array. Comments, processing instructions and the
+//! doctype are dropped.
+
+/// `None` when the document isn't well formed.
+#[must_use]
+#[inline]
+pub fn to_json(xml: &str) -> Option<serde_json::Value> {
+    let mut parser = Parser { rest: xml };
+    parser.skip_misc()?;
+
+    let (name, value) = parser.element()?;
+    parser.skip_misc()?;
+    if !parser.rest.is_empty() {
+        return None;
+    }
+
+    let mut root = serde_json::Map::new();
+    root.insert(name, value);
+    Some(root.into())
+}
+
+///
+struct Parser<'xml> {
+    ///
+    rest: &'xml str,
+}
+
+impl<'xml> Parser<'xml> {
+    /// Whitespace, comments, processing instructions and the doctype around the root element.
+    fn skip_misc(&mut self) -> Option<()> {
+        loop {
+            self.rest = self.rest.trim_start_matches('\u{feff}').trim_start();
+
+            if self.rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest.starts_with("<!DOCTYPE") {
+                // An internal subset has its own `>`s
+                let end = self.rest.find('>')?;
+                if self.rest.get(..end)?.contains('[') {
+                    self.skip_past("]>")?;
+                } else {
+                    self.skip_past(">")?;
+                }
+            } else {
+                return Some(());
+            }
+        }
+    }
+
+    ///
+    fn skip_past(&mut self, marker: &str) -> Option<&'xml str> {
+        let (skipped, rest) = self.rest.split_once(marker)?;
+        self.rest = rest;
+        Some(skipped)
+    }
+
+    ///
+    fn name(&mut self) -> Option<String> {
+        let end = self
+            .rest
+            .find(|character: char| character.is_whitespace() || "/>=".contains(character))?;
+        let (name, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        (!name.is_empty()).then(|| name.to_owned())
+    }
+
+    /// An element starting at `<`, along with its name.
+    fn element(&mut self) -> Option<(String, serde_json::Value)> {
+        self.rest = self.rest.strip_prefix('<')?;
+        let name = self.name()?;
+        let mut object = serde_json::Map::new();
+
+        // Attributes
+        loop {
+            self.rest = self.rest.trim_start();
+            if let Some(rest) = self.rest.strip_prefix("/>") {
+                self.rest = rest;
+                return Some((name, element_value(object, &[])));
+            }
+            if let Some(rest) = self.rest.strip_prefix('>') {
+                self.rest = rest;
+                break;
+            }
+
+            let attribute = self.name()?;
+            self.rest = self.rest.trim_start().strip_prefix('=')?.trim_start();
+            let quote = self
+                .rest
+                .chars()
+                .next()
+                .filter(|quote| "\"'".contains(*quote))?;
+            self.rest = self.rest.get(1..)?;
+            let (value, rest) = self.rest.split_once(quote)?;
+            self.rest = rest;
+
+            object.insert(format!("@{attribute}"), unescape(value).into());
+        }
+
+        // Content
+        let mut text = vec![];
+        loop {
+            if let Some(rest) = self.rest.strip_prefix("</") {
+                self.rest = rest;
+                let closing = self.skip_past(">")?;
+                if closing.trim() != name {
+                    return None;
+                }
+                return Some((name, element_value(object, &text)));
+            } else if self.rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if let Some(rest) = self.rest.strip_prefix("<![CDATA[") {
+                self.rest = rest;
+                text.push(self.skip_past("]]>")?.to_owned());
+            } else if self.rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest.starts_with('<') {
+                let (child, value) = self.element()?;
+                insert_child(&mut object, child, value);
+            } else {
+                let end = self.rest.find('<')?;
+                let (content, rest) = self.rest.split_at(end);
+                self.rest = rest;
+                text.push(unescape(content));
+            }
+        }
+    }
+}
+
+/// Just the text when there aren't any attributes or children.
+fn element_value(
+    mut object: serde_json::Map<String, serde_json::Value>,
+    text: &[String],
+) -> serde_json::Value {
+    let text = text.concat();
+    let text = text.trim();
+
+    if object.is_empty() {
+        return if text.is_empty() {
+            serde_json::Value::Null
+        } else {
+            text.into()
+        };
+    }
+
+    if !text.is_empty() {
+        object.insert("#text".into(), text.into());
+    }
+    object.into()
+}
+
+/// A second child with the same name turns it into an array.
+fn insert_child(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    name: String,
+    value: serde_json::Value,
+) {
+    match object.get_mut(&name) {
+        Some(&mut serde_json::Value::Array(ref mut existing)) => existing.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = serde_json::Value::Array(vec![first, value]);
+        }
+        None => {
+            object.insert(name, value);
+        }
+    }
+}
+
+/// Resolves the predefined and numeric entities, anything else is left as it is.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(rest.get(..start).unwrap_or_default());
+        rest = rest.get(start..).unwrap_or_default();
+
+        let entity = rest.find(';').and_then(|end| {
+            let name = rest.get(1..end)?;
+            let character = match name {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = match name.strip_prefix("#x") {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => name.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((character, end))
+        });
+
+        if let Some((character, end)) = entity {
+            result.push(character);
+            rest = rest.get(end.saturating_add(1)..).unwrap_or_default();
+        } else {
+            result.push('&');
+            rest = rest.get(1..).unwrap_or_default();
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_elements_attributes_and_repeats() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- exported -->
+            <orders count="2">
+                <order id="1"><total currency="USD">9.50</total><note>Fish &amp; chips</note></order>
+                <order id="2"><total>3</total><note><![CDATA[<fragile>]]></note><gift/></order>
+            </orders>"#;
+
+        assert_eq!(
+            Some(serde_json::json!({
+                "orders": {
+                    "@count": "2",
+                    "order": [
+                        {
+                            "@id": "1",
+                            "total": { "@currency": "USD", "#text": "9.50" },
+                            "note": "Fish & chips"
+                        },
+                        { "@id": "2", "total": "3", "note": "<fragile>", "gift": null }
+                    ]
+                }
+            })),
+            to_json(xml)
+        );
+    }
+
+    #[test]
+    fn test_malformed_documents() {
+        assert_eq!(None, to_json("<a><b></a>"));
+        assert_eq!(None, to_json("<a>"));
+        assert_eq!(None, to_json("just text"));
+        assert_eq!("A ☃ &bogus;", unescape("&#65; &#x2603; &bogus;"));
+    }
+}
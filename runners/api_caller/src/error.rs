@@ -43,6 +43,18 @@ pub enum APICaller {
     #[error("Invalid Runtime Expression: {0}")]
     InvalidRuntimeExpression(String),
 
+    ///
+    #[error("Expected a URL to the next page at: {0}")]
+    InvalidNextUrl(String),
+
+    /// The next page is on another origin, it would get the connector's credentials.
+    #[error("Refusing to follow a next page on another origin: {0}")]
+    CrossOriginNextUrl(String),
+
+    ///
+    #[error("Each cursor path needs a cursor param ({0} paths, {1} params)")]
+    MismatchedCursors(usize, usize),
+
     ///
     #[error("Json Pointer Parser Error")]
     JsonPointerParseError {
@@ -57,6 +69,14 @@ pub enum APICaller {
         source: jsonptr::Error,
     },
 
+    ///
+    #[error(transparent)]
+    JmesPath {
+        ///
+        #[from]
+        source: jmespath::JmespathError,
+    },
+
     ///
     #[error("Unable to simplify value")]
     SimpleValueAssertion,
@@ -73,6 +93,24 @@ pub enum APICaller {
     #[error("Paging strategy encountered an integer overflow")]
     PagingOverflow,
 
+    /// The body is redacted the same as in the API log.
+    #[error("Request failed with status {status}: {body}")]
+    HttpStatus {
+        ///
+        status: u16,
+
+        ///
+        body: String,
+    },
+
+    ///
+    #[error("Invalid error statuses (expected i.e. 4xx,500-599,429 or none): {0}")]
+    InvalidStatusRule(String),
+
+    /// Replaying and the cassette doesn't have an answer for the request.
+    #[error("No recorded response for {0}")]
+    NotRecorded(String),
+
     ///
     #[error(transparent)]
     HttpMethodParsingError {
@@ -97,6 +135,14 @@ pub enum APICaller {
         source: serde_json::Error,
     },
 
+    ///
+    #[error(transparent)]
+    Base64 {
+        ///
+        #[from]
+        source: base64::DecodeError,
+    },
+
     ///
     #[error(transparent)]
     Io {
@@ -137,6 +183,14 @@ pub enum APICaller {
         source: reqwest::header::ToStrError,
     },
 
+    ///
+    #[error(transparent)]
+    Engine {
+        ///
+        #[from]
+        source: ExecutionEngine,
+    },
+
     ///
     #[error(transparent)]
     IntegerConversion {
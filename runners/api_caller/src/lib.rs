@@ -20,23 +20,42 @@
     clippy::question_mark_used,
     clippy::absolute_paths,
     clippy::ref_patterns,
+    clippy::single_call_fn,
 )]
 
 //!
 
+mod cache;
+mod cassette;
+pub mod client;
 mod constants;
+mod decode;
 pub mod error;
+mod redact;
+mod status;
 
-extern crate alloc;
-use alloc::sync::Arc;
-
-use std::{collections::HashMap, fs::File, io::Write, sync::RwLock};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
 use base64::Engine as _;
-use core_entities::service::{pagination, Operation, Parameter, SwaggerService};
+use cache::{Caching, ResponseCache};
+use cassette::{Cassettes, Interaction, Recorded};
+use client::Clients;
+use core_entities::service::{
+    pagination::{self, extended_path},
+    HttpClientConfig, Operation, Parameter, SwaggerService,
+};
 use credential_entities::credentials::Authentication;
-use execution_engine::services::{DataConnectionRunner, DataConnectorBundle, EngineInputContext};
+use decode::{Downloads, RawBody};
+use execution_engine::services::{
+    Cassette, DataConnectionRunner, DataConnectorBundle, EngineInputContext,
+};
 use http::{HeaderMap, HeaderName, HeaderValue};
+use redact::Redactor;
+use status::ErrorStatuses;
 
 ///
 fn simplify_value(value: &serde_json::Value) -> error::Result<String> {
@@ -61,95 +80,172 @@ where
         .collect()
 }
 
-///
-fn find_results<'item>(
-    result: &'item serde_json::Value,
+/// Pulls the items out of a page. A results path that can't be found is an empty
+/// page unless the connector asked us to treat that as an error.
+fn find_results(
+    result: &PageResponse,
     pagination_config: &Option<pagination::Value>,
-) -> error::Result<&'item serde_json::Value> {
-    let result = if let &Some(ref pagination) = pagination_config {
-        match pagination {
-            &core_entities::service::pagination::Value::PageOffset(ref page_offset) => {
-                let path = page_offset.resultsPath.jmesPath();
-                let path = path
-                    .strip_prefix(constants::RESPONSE_BODY_PREFIX)
-                    .unwrap_or(path);
-
-                let path = if path.starts_with('/') {
-                    path.to_owned()
-                } else {
-                    format!("/{path}")
-                };
-
-                if path == "/" {
-                    result
-                } else {
-                    let path = path.parse::<jsonptr::Pointer>()?;
-                    path.resolve(result)?
-                }
-            }
-            &core_entities::service::pagination::Value::MultiCursor(ref cursor) => {
-                let path = cursor.resultsPath.jmesPath();
-                let path = path
-                    .strip_prefix(constants::RESPONSE_BODY_PREFIX)
-                    .unwrap_or(path);
+) -> error::Result<serde_json::Value> {
+    let (results_path, error_on_path_not_found) = match pagination_config {
+        &Some(pagination::Value::PageOffset(ref page_offset)) => {
+            (&page_offset.resultsPath, &page_offset.errorOnPathNotFound)
+        }
+        &Some(pagination::Value::MultiCursor(ref cursor)) => {
+            (&cursor.resultsPath, &cursor.errorOnPathNotFound)
+        }
+        &Some(pagination::Value::Offset(ref offset)) => {
+            (&offset.resultsPath, &offset.errorOnPathNotFound)
+        }
+        &Some(pagination::Value::Unpaginated(ref unpaginated)) => {
+            (&unpaginated.resultsPath, &unpaginated.errorOnPathNotFound)
+        }
+        &Some(pagination::Value::LinkHeader(ref link_header)) => {
+            (&link_header.resultsPath, &link_header.errorOnPathNotFound)
+        }
+        &Some(pagination::Value::NextUrl(ref next_url)) => {
+            (&next_url.resultsPath, &next_url.errorOnPathNotFound)
+        }
+        &Some(_) | &None => return Ok(result.body.clone()),
+    };
 
-                let path = if path.starts_with('/') {
-                    path.to_owned()
-                } else {
-                    format!("/{path}")
-                };
+    match lookup_path(results_path, result)? {
+        Some(value) => Ok(value),
+        // `JMESPath` can't tell a missing path from a null one, so an expression that comes up
+        // null is no results unless the connector says otherwise. A missing pointer always fails.
+        None if results_path.has_jmesPath() && !error_on_path_not_found.value => {
+            Ok(serde_json::Value::Array(vec![]))
+        }
+        None => Err(error::APICaller::NotFound(
+            path_expression(results_path).into(),
+        )),
+    }
+}
 
-                if path == "/" {
-                    result
-                } else {
-                    let path = path.parse::<jsonptr::Pointer>()?;
-                    path.resolve(result)?
-                }
+/// Evaluates an `ExtendedPath` against a response. A `columnPath` is a runtime expression
+/// (see [`lookup_response_expression`]) while a `jmesPath` is a `JMESPath` expression evaluated
+/// against the body. Since `JMESPath` doesn't tell missing and null apart, a null result from
+/// one is treated as not found.
+fn lookup_path(
+    path: &pagination::ExtendedPath,
+    response: &PageResponse,
+) -> error::Result<Option<serde_json::Value>> {
+    match path.value {
+        Some(extended_path::Value::JmesPath(ref expression)) => {
+            let expression = jmespath::compile(expression)?;
+            let value = expression.search(&response.body)?;
+
+            let value = serde_json::to_value(&*value)?;
+            if value.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some(value))
             }
-            &core_entities::service::pagination::Value::Offset(ref offset) => {
-                let path = offset.resultsPath.jmesPath();
-                let path = path
-                    .strip_prefix(constants::RESPONSE_BODY_PREFIX)
-                    .unwrap_or(path);
+        }
+        Some(extended_path::Value::ColumnPath(ref expression)) => {
+            Ok(lookup_response_expression(expression, response).cloned())
+        }
+        Some(_) | None => Ok(Some(response.body.clone())),
+    }
+}
 
-                let path = if path.starts_with('/') {
-                    path.to_owned()
-                } else {
-                    format!("/{path}")
-                };
+/// The raw expression behind an `ExtendedPath`, mostly useful for errors.
+fn path_expression(path: &pagination::ExtendedPath) -> &str {
+    match path.value {
+        Some(
+            extended_path::Value::JmesPath(ref expression)
+            | extended_path::Value::ColumnPath(ref expression),
+        ) => expression,
+        Some(_) | None => "",
+    }
+}
 
-                if path == "/" {
-                    result
-                } else {
-                    let path = path.parse::<jsonptr::Pointer>()?;
-                    path.resolve(result)?
-                }
-            }
-            &core_entities::service::pagination::Value::Unpaginated(ref unpaginated) => {
-                let path = unpaginated.resultsPath.jmesPath();
-                let path = path
-                    .strip_prefix(constants::RESPONSE_BODY_PREFIX)
-                    .unwrap_or(path);
+/// Resolves a runtime expression against a response. Anything that isn't a
+/// `$response.header.` expression is treated as a JSON pointer into the body.
+fn lookup_response_expression<'item>(
+    expression: &str,
+    response: &'item PageResponse,
+) -> Option<&'item serde_json::Value> {
+    if let Some(header) = expression.strip_prefix(constants::RESPONSE_HEADER_PREFIX) {
+        return response.headers.get(&header.to_lowercase());
+    }
 
-                let path = if path.starts_with('/') {
-                    path.to_owned()
-                } else {
-                    format!("/{path}")
-                };
+    let path = expression
+        .strip_prefix(constants::RESPONSE_BODY_PREFIX)
+        .unwrap_or(expression);
 
-                if path == "/" {
-                    result
-                } else {
-                    let path = path.parse::<jsonptr::Pointer>()?;
-                    path.resolve(result)?
-                }
-            }
-            &core_entities::service::pagination::Value::NextUrl(_) | &_ => result,
-        }
+    let path = if path.starts_with('/') {
+        path.to_owned()
     } else {
-        result
+        format!("/{path}")
     };
-    Ok(result)
+
+    if path == "/" {
+        Some(&response.body)
+    } else {
+        response.body.pointer(&path)
+    }
+}
+
+/// Parses a `Link` header (RFC 8288) into a map of relation to target URL.
+fn parse_link_header(header: &str) -> HashMap<String, String> {
+    header
+        .split(',')
+        .filter_map(|link| {
+            let mut parts = link.split(';');
+            let target = parts
+                .next()?
+                .trim()
+                .strip_prefix('<')?
+                .strip_suffix('>')?
+                .to_owned();
+
+            let rel = parts
+                .find_map(|param| param.trim().strip_prefix("rel="))?
+                .trim_matches('"')
+                .to_owned();
+
+            Some((rel, target))
+        })
+        .flat_map(|(rel, target)| {
+            rel.split_whitespace()
+                .map(|rel| (rel.to_lowercase(), target.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+///
+struct PageResponse {
+    /// Relative URLs handed back to us are resolved against this.
+    url: reqwest::Url,
+
+    ///
+    status: u16,
+
+    /// Keyed by the lowercased header name.
+    headers: HashMap<String, serde_json::Value>,
+
+    ///
+    body: serde_json::Value,
+}
+
+/// What a run hands back with the `envelope` option, the status and headers are the last page's.
+fn envelope(response: Option<&PageResponse>, body: serde_json::Value) -> serde_json::Value {
+    let mut envelope = serde_json::Map::new();
+    envelope.insert("status".into(), response.map(|page| page.status).into());
+    envelope.insert(
+        "headers".into(),
+        response
+            .map(|page| {
+                page.headers
+                    .clone()
+                    .into_iter()
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            .into(),
+    );
+    envelope.insert("body".into(), body);
+    envelope.into()
 }
 
 ///
@@ -174,30 +270,176 @@ struct APICallState {
     body: Option<serde_json::Value>,
 }
 
-impl APICallState {
+/// Identifies a call in the log.
+struct CallInfo<'info> {
+    ///
+    execution_id: &'info str,
+
+    ///
+    service: &'info str,
+
+    ///
+    operation: &'info str,
+
+    /// The execution this call is made for.
+    span: &'info tracing::Span,
+
+    /// Where binary responses are saved.
+    downloads: Option<&'info Path>,
+
+    ///
+    cache: Option<Caching<'info>>,
+
+    /// Set when the execution's traffic is recorded or replayed.
+    cassette: Option<&'info Cassette>,
+
     ///
+    cassettes: &'info Cassettes,
+}
+
+/// The API asked for a response not to be kept.
+fn no_store(headers: &HashMap<String, serde_json::Value>) -> bool {
+    headers
+        .get(http::header::CACHE_CONTROL.as_str())
+        .and_then(serde_json::Value::as_str)
+        .is_some_and(|directives| {
+            directives
+                .split(',')
+                .any(|directive| directive.trim().eq_ignore_ascii_case(constants::NO_STORE))
+        })
+}
+
+/// Decodes a body, whether it just came in or was cached.
+fn page_response(
+    url: reqwest::Url,
+    status: u16,
+    headers: HashMap<String, serde_json::Value>,
+    bytes: &[u8],
+    downloads: Downloads,
+    redactor: &Redactor,
+) -> error::Result<PageResponse> {
+    let header = |name: &str| headers.get(name).and_then(serde_json::Value::as_str);
+    let raw = RawBody {
+        content_type: header(http::header::CONTENT_TYPE.as_str()),
+        disposition: header(http::header::CONTENT_DISPOSITION.as_str()),
+        bytes: bytes.to_vec(),
+    };
+    let body = decode::decode(&raw, downloads)?;
+
+    tracing::debug!(
+        status,
+        headers = ?redactor.headers(&headers),
+        body = %redactor.body(&body),
+        "http response"
+    );
+
+    Ok(PageResponse {
+        url,
+        status,
+        headers,
+        body,
+    })
+}
+
+impl APICallState {
+    /// Sends the request in its own span, logging one event per call and the redacted exchange at debug.
     fn send(
         &self,
-        id: &str,
+        call: &CallInfo,
+        page: i32,
         client: &reqwest::blocking::Client,
-        log: &Arc<RwLock<File>>,
-    ) -> error::Result<serde_json::Value> {
-        let now = chrono::offset::Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
+        redactor: &Redactor,
+    ) -> error::Result<PageResponse> {
+        let url = self
+            .redacted_endpoint(redactor)
+            .map(|url| url.to_string())
+            .unwrap_or_default();
+
+        let span = tracing::info_span!(
+            parent: call.span,
+            "http.request",
+            otel.kind = "client",
+            http.method = self.method.as_str(),
+            http.url = url.as_str(),
+            http.status_code = tracing::field::Empty,
+            otel.status_code = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+
+        let started = Instant::now();
+        let result = self.send_internal(call, page, client, redactor);
+        let elapsed = started.elapsed();
+        let duration_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+
+        let status_label = match &result {
+            &Ok(ref response) => response.status.to_string(),
+            &Err(_) => constants::STATUS_ERROR.to_owned(),
+        };
+        metrics::increment_counter!(
+            constants::METRIC_HTTP_CALLS,
+            "connector" => call.service.to_owned(),
+            "status" => status_label,
+        );
+        metrics::histogram!(
+            constants::METRIC_HTTP_DURATION,
+            elapsed,
+            "connector" => call.service.to_owned(),
+        );
+
+        match &result {
+            &Ok(ref response) => {
+                let status = response.status;
+                span.record("http.status_code", status);
+                tracing::info!(
+                    execution_id = call.execution_id,
+                    service = call.service,
+                    operation = call.operation,
+                    method = self.method.as_str(),
+                    url,
+                    status,
+                    duration_ms,
+                    outcome = constants::OUTCOME_COMPLETED,
+                    "http call"
+                );
+            }
+            &Err(ref err) => {
+                span.record("otel.status_code", constants::SPAN_STATUS_ERROR);
+                tracing::error!(
+                    execution_id = call.execution_id,
+                    service = call.service,
+                    operation = call.operation,
+                    method = self.method.as_str(),
+                    url,
+                    duration_ms,
+                    outcome = constants::OUTCOME_FAILED,
+                    error = %err,
+                    "http call"
+                );
+            }
+        }
 
-        let mut log = log
-            .write()
-            .map_err(|err| error::APICaller::PoisonedLock(err.to_string()))?;
+        result
+    }
 
+    ///
+    fn send_internal(
+        &self,
+        call: &CallInfo,
+        page: i32,
+        client: &reqwest::blocking::Client,
+        redactor: &Redactor,
+    ) -> error::Result<PageResponse> {
         let method = self.method.parse::<reqwest::Method>()?;
+        let method_name = method.as_str().to_owned();
+        let cacheable = method == reqwest::Method::GET;
         let endpoint = self.resolve_endpoint()?;
-        log.write_all(b"==============================\n")?;
-        log.write_all(format!("ID = {id}\n").as_bytes())?;
-        log.write_all(format!("Time = {now}\n").as_bytes())?;
-        log.write_all(b"[REQUEST]\n")?;
-        log.write_all(format!("{} {}\n", &self.method, &endpoint).as_bytes())?;
+        let downloads = Downloads {
+            dir: call.downloads,
+            execution_id: call.execution_id,
+            page,
+        };
 
-        let mut builder = client.request(method, endpoint);
+        let mut builder = client.request(method, endpoint.clone());
 
         let headers: error::Result<HeaderMap> = self
             .header_params
@@ -213,52 +455,186 @@ impl APICallState {
             .collect();
         let headers = headers?;
 
-        log.write_all(b"Headers = \n")?;
-        for (key, value) in &headers {
-            log.write_all(format!("  {}: {}\n", key.as_str(), value.to_str()?).as_bytes())?;
+        let recording = matches!(call.cassette, Some(&Cassette::Record(_)));
+        let request_headers = if recording || tracing::enabled!(tracing::Level::DEBUG) {
+            let mut request_headers = HashMap::new();
+            for (key, value) in &headers {
+                request_headers.insert(
+                    key.as_str().to_owned(),
+                    redactor.header(key.as_str(), value.to_str()?).to_owned(),
+                );
+            }
+            request_headers
+        } else {
+            HashMap::new()
+        };
+
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            tracing::debug!(
+                headers = ?request_headers,
+                body = %self
+                    .body
+                    .as_ref()
+                    .map(|body| redactor.body(body))
+                    .unwrap_or_default(),
+                "http request"
+            );
+        }
+
+        // Replayed calls never touch the network
+        if let Some(&Cassette::Replay(ref path)) = call.cassette {
+            let recorded = call.cassettes.replay(
+                path,
+                call.execution_id,
+                &method_name,
+                self.redacted_endpoint(redactor)?.as_str(),
+                self.body.as_ref().map(|body| redactor.body(body)).as_ref(),
+            )?;
+            return page_response(
+                endpoint,
+                recorded.status,
+                recorded.headers.clone(),
+                &recorded.bytes()?,
+                downloads,
+                redactor,
+            );
         }
 
+        // Only reads are cached, under the request as it's about to go out
+        let cached = call.cache.as_ref().filter(|_| cacheable).map(|caching| {
+            let key = cache::Key::new(
+                call.service,
+                call.operation,
+                endpoint.as_str(),
+                headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_bytes())),
+                caching.vary,
+                &caching.credentials,
+            );
+            let entry = caching.store.get(&key);
+            (caching, key, entry)
+        });
+
         builder = builder.headers(headers);
 
+        if let Some(&(caching, _, Some(ref entry))) = cached.as_ref() {
+            if entry.is_fresh(caching.ttl) {
+                tracing::debug!(status = entry.status(), "http response from cache");
+                return page_response(
+                    endpoint,
+                    entry.status(),
+                    entry.headers().clone(),
+                    &entry.body(),
+                    downloads,
+                    redactor,
+                );
+            }
+
+            if let Some(etag) = entry.header(http::header::ETAG.as_str()) {
+                builder = builder.header(http::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(modified) = entry.header(http::header::LAST_MODIFIED.as_str()) {
+                builder = builder.header(http::header::IF_MODIFIED_SINCE, modified);
+            }
+        }
+
         if let &Some(ref body) = &self.body {
-            log.write_all(format!("\n{}\n", serde_json::to_string_pretty(body)?).as_bytes())?;
             builder = builder.json(body);
-        } else {
-            log.write_all(b"\nNo Body\n")?;
         }
 
-        log.write_all(b"\n")?;
-
-        log.write_all(b"[RESPONSE]\n")?;
         let response = builder.send()?;
+        let status = response.status().as_u16();
 
-        log.write_all(format!("Status = {}\n", response.status()).as_bytes())?;
-
-        log.write_all(b"Headers = \n")?;
+        let mut headers: HashMap<String, serde_json::Value> = HashMap::new();
         for (key, value) in response.headers() {
-            log.write_all(format!("  {}: {}\n", key.as_str(), value.to_str()?).as_bytes())?;
+            let value = value.to_str()?;
+
+            headers
+                .entry(key.as_str().to_lowercase())
+                .and_modify(|existing| {
+                    if let &mut serde_json::Value::String(ref mut existing) = existing {
+                        existing.push_str(", ");
+                        existing.push_str(value);
+                    }
+                })
+                .or_insert_with(|| serde_json::Value::String(value.to_owned()));
         }
+        let bytes = response.bytes()?.to_vec();
 
-        let response_body: String = response.text()?;
-        if response_body.is_empty() {
-            log.write_all(b"\nNo Content\n")?;
-            Ok(serde_json::Value::Null)
-        } else {
-            let response = match serde_json::from_str(&response_body) {
-                Ok(value) => value,
-                Err(_) => serde_json::Value::String(response_body),
+        if let Some(&Cassette::Record(ref dir)) = call.cassette {
+            // JSON bodies are redacted like the log, anything else is kept as it is
+            let body = serde_json::from_slice::<serde_json::Value>(&bytes).map_or_else(
+                |_| bytes.clone(),
+                |body| redactor.body(&body).to_string().into_bytes(),
+            );
+            let interaction = Interaction {
+                method: method_name,
+                url: self.redacted_endpoint(redactor)?.to_string(),
+                request_headers,
+                body: self.body.as_ref().map(|body| redactor.body(body)),
+                response: Recorded::new(status, redactor.headers(&headers), &body),
             };
-            log.write_all(format!("\n{}\n", serde_json::to_string_pretty(&response)?).as_bytes())?;
+            call.cassettes.record(
+                &dir.join(format!(
+                    "{}{}",
+                    call.execution_id,
+                    constants::CASSETTE_EXTENSION
+                )),
+                &interaction,
+            )?;
+        }
 
-            Ok(response)
+        if let Some((caching, key, entry)) = cached {
+            let store = |entry: &cache::Entry| {
+                if let Err(err) = caching.store.put(&key, entry) {
+                    // Worst case the next run calls the API again
+                    tracing::warn!(error = %err, "unable to cache response");
+                }
+            };
+
+            match entry {
+                Some(mut entry) if status == constants::STATUS_NOT_MODIFIED => {
+                    entry.touch();
+                    store(&entry);
+                    return page_response(
+                        endpoint,
+                        entry.status(),
+                        entry.headers().clone(),
+                        &entry.body(),
+                        downloads,
+                        redactor,
+                    );
+                }
+                Some(_) | None if status == constants::STATUS_OK && !no_store(&headers) => {
+                    store(&cache::Entry::new(status, headers.clone(), &bytes));
+                }
+                Some(_) | None => {}
+            }
         }
+
+        page_response(endpoint, status, headers, &bytes, downloads, redactor)
     }
 
     ///
     fn resolve_endpoint(&self) -> error::Result<reqwest::Url> {
+        self.resolve_endpoint_with(&self.path_params)
+    }
+
+    /// The endpoint as it should appear in the log.
+    fn redacted_endpoint(&self, redactor: &Redactor) -> error::Result<reqwest::Url> {
+        let url = self.resolve_endpoint_with(&redactor.path_params(&self.path_params))?;
+        Ok(redactor.url(&url))
+    }
+
+    ///
+    fn resolve_endpoint_with(
+        &self,
+        path_params: &HashMap<String, serde_json::Value>,
+    ) -> error::Result<reqwest::Url> {
         let mut endpoint = self.endpoint.clone();
 
-        let params = simplify_value_map(self.path_params.iter())?;
+        let params = simplify_value_map(path_params.iter())?;
 
         for (key, value) in params {
             let key = ["{", &key, "}"].join("");
@@ -268,10 +644,21 @@ impl APICallState {
 
         let query = simplify_value_map(self.query_params.iter())?;
 
-        let url = match query.len() {
-            0 => reqwest::Url::parse(&endpoint),
-            _ => reqwest::Url::parse_with_params(&endpoint, query),
-        }?;
+        let mut url = reqwest::Url::parse(&endpoint)?;
+        if !query.is_empty() {
+            // Parameters we've collected take precedence over any already in the endpoint
+            // (i.e. when following a link to the next page)
+            let existing: Vec<(String, String)> = url
+                .query_pairs()
+                .filter(|&(ref key, _)| !query.contains_key(key.as_ref()))
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+
+            url.query_pairs_mut()
+                .clear()
+                .extend_pairs(existing)
+                .extend_pairs(query);
+        }
 
         Ok(url)
     }
@@ -370,9 +757,6 @@ impl APICallState {
             }
         }
 
-        self.header_params
-            .insert("User-Agent".to_owned(), "APICLI/1.0".into());
-
         Ok(())
     }
 
@@ -486,97 +870,198 @@ impl APICallState {
         Ok(())
     }
 
-    ///
+    /// Applies the pagination strategy to the current request. Returns the number of
+    /// items we expect in a full page or `None` if there aren't any pages left to request.
     fn handle_pagination(
         &mut self,
         pagination_config: &Option<pagination::Value>,
-        previous_response: Option<&serde_json::Value>,
+        previous_response: Option<&PageResponse>,
         current_page: i32,
         parameters: &[Parameter],
-    ) -> error::Result<i32> {
-        let requested = if let &Some(ref pagination) = pagination_config {
-            match pagination {
-                &core_entities::service::pagination::Value::PageOffset(ref page_offset) => {
-                    let current_page = page_offset
-                        .startPage
-                        .value
-                        .checked_add(current_page)
-                        .ok_or(error::APICaller::PagingOverflow)?;
-                    let max_limit = page_offset.maxLimit.value;
-
-                    self.apply_runtime_expression(
-                        &page_offset.pageOffsetParam,
-                        serde_json::Value::Number(current_page.into()),
-                        parameters,
-                    )?;
-                    self.apply_runtime_expression(
-                        &page_offset.limitParam,
-                        serde_json::Value::Number(max_limit.into()),
-                        parameters,
-                    )?;
-
-                    max_limit
+    ) -> error::Result<Option<i32>> {
+        let requested = match pagination_config {
+            &Some(pagination::Value::PageOffset(ref page_offset)) => {
+                let current_page = page_offset
+                    .startPage
+                    .value
+                    .checked_add(current_page)
+                    .ok_or(error::APICaller::PagingOverflow)?;
+                let max_limit = page_offset.maxLimit.value;
+
+                self.apply_runtime_expression(
+                    &page_offset.pageOffsetParam,
+                    serde_json::Value::Number(current_page.into()),
+                    parameters,
+                )?;
+                self.apply_runtime_expression(
+                    &page_offset.limitParam,
+                    serde_json::Value::Number(max_limit.into()),
+                    parameters,
+                )?;
+
+                Some(max_limit)
+            }
+            &Some(pagination::Value::MultiCursor(ref cursor)) => {
+                if cursor.cursorsPath.len() != cursor.cursorsParam.len() {
+                    return Err(error::APICaller::MismatchedCursors(
+                        cursor.cursorsPath.len(),
+                        cursor.cursorsParam.len(),
+                    ));
                 }
-                &core_entities::service::pagination::Value::MultiCursor(ref cursor) => {
-                    let max_limit = cursor.maxLimit.value;
-                    self.apply_runtime_expression(
-                        &cursor.limitParam,
-                        serde_json::Value::Number(max_limit.into()),
-                        parameters,
-                    )?;
-
-                    if let Some(previous_response) = previous_response {
-                        let cursor_path = cursor
-                            .cursorsPath
-                            .first()
-                            .ok_or_else(|| error::APICaller::NotFound("Cursor Path".into()))?
-                            .jmesPath();
-
-                        let cursor_path = cursor_path
-                            .strip_prefix(constants::RESPONSE_BODY_PREFIX)
-                            .unwrap_or(cursor_path);
-
-                        let cursor_path = cursor_path.parse::<jsonptr::Pointer>()?;
-                        let next_cursor = cursor_path.resolve(previous_response)?;
-
-                        let cursor_param = cursor
-                            .cursorsParam
-                            .first()
-                            .ok_or_else(|| error::APICaller::NotFound("Cursor Param".into()))?;
-                        self.apply_runtime_expression(
-                            cursor_param,
-                            next_cursor.clone(),
-                            parameters,
-                        )?;
+
+                if let Some(previous_response) = previous_response {
+                    let mut found_cursor = false;
+
+                    for (cursor_path, cursor_param) in
+                        cursor.cursorsPath.iter().zip(&cursor.cursorsParam)
+                    {
+                        match lookup_path(cursor_path, previous_response)? {
+                            Some(serde_json::Value::Null) => {}
+                            Some(serde_json::Value::String(ref next)) if next.is_empty() => {}
+                            Some(next) => {
+                                found_cursor = true;
+                                self.apply_runtime_expression(cursor_param, next, parameters)?;
+                            }
+                            None if cursor.errorOnPathNotFound.value => {
+                                return Err(error::APICaller::NotFound(
+                                    path_expression(cursor_path).into(),
+                                ));
+                            }
+                            None => {}
+                        }
                     }
 
-                    max_limit
+                    // Only once every cursor has run dry are we out of pages
+                    if !found_cursor {
+                        return Ok(None);
+                    }
                 }
-                &core_entities::service::pagination::Value::Offset(ref offset) => {
-                    let max_limit = offset.maxLimit.value;
-
-                    self.apply_runtime_expression(
-                        &offset.offsetParam,
-                        serde_json::Value::Number(current_page.into()),
-                        parameters,
-                    )?;
-                    self.apply_runtime_expression(
-                        &offset.limitParam,
-                        serde_json::Value::Number(max_limit.into()),
-                        parameters,
-                    )?;
-
-                    max_limit
+
+                let max_limit = cursor.maxLimit.value;
+                self.apply_page_limit(&cursor.limitParam, max_limit, parameters)?;
+
+                return Ok(Some(max_limit));
+            }
+            &Some(pagination::Value::Offset(ref offset)) => {
+                let max_limit = offset.maxLimit.value;
+
+                self.apply_runtime_expression(
+                    &offset.offsetParam,
+                    serde_json::Value::Number(current_page.into()),
+                    parameters,
+                )?;
+                self.apply_runtime_expression(
+                    &offset.limitParam,
+                    serde_json::Value::Number(max_limit.into()),
+                    parameters,
+                )?;
+
+                Some(max_limit)
+            }
+            &Some(pagination::Value::LinkHeader(ref link_header)) => {
+                if let Some(previous_response) = previous_response {
+                    let next_url = previous_response
+                        .headers
+                        .get(constants::LINK_HEADER)
+                        .and_then(serde_json::Value::as_str)
+                        .map(parse_link_header)
+                        .and_then(|mut links| links.remove(&link_header.rel.to_lowercase()));
+
+                    let Some(next_url) = next_url else {
+                        return Ok(None);
+                    };
+
+                    self.follow_url(&previous_response.url, &next_url)?;
                 }
-                &pagination::Value::NextUrl(_) | &pagination::Value::Unpaginated(_) | &_ => 0_i32,
+
+                let max_limit = link_header.maxLimit.value;
+                self.apply_page_limit(&link_header.limitParam, max_limit, parameters)?;
+
+                // A missing next link is what tells us we're done, the page size is only a hint
+                return Ok(Some(max_limit));
             }
-        } else {
-            0_i32
+            &Some(pagination::Value::NextUrl(ref next_url)) => {
+                if let Some(previous_response) = previous_response {
+                    let next_url_path = &next_url.nextUrlPath;
+
+                    let next = match lookup_path(next_url_path, previous_response)? {
+                        Some(serde_json::Value::String(next)) if !next.is_empty() => next,
+                        Some(serde_json::Value::String(_) | serde_json::Value::Null) => {
+                            return Ok(None);
+                        }
+                        Some(_) => {
+                            return Err(error::APICaller::InvalidNextUrl(
+                                path_expression(next_url_path).into(),
+                            ));
+                        }
+                        None if next_url.errorOnPathNotFound.value => {
+                            return Err(error::APICaller::NotFound(
+                                path_expression(next_url_path).into(),
+                            ));
+                        }
+                        None => return Ok(None),
+                    };
+
+                    self.follow_url(&previous_response.url, &next)?;
+                }
+
+                let max_limit = next_url.maxLimit.value;
+                self.apply_page_limit(&next_url.limitParam, max_limit, parameters)?;
+
+                // Same as the link header, running out of next URLs is what stops us
+                return Ok(Some(max_limit));
+            }
+            &Some(pagination::Value::Unpaginated(_) | _) | &None => Some(0_i32),
         };
 
+        // Without a page size there isn't any way to tell if there are more pages
+        if requested == Some(0_i32) && previous_response.is_some() {
+            return Ok(None);
+        }
+
         Ok(requested)
     }
 
+    /// Points the request at a URL handed to us by the API, relative URLs are resolved
+    /// against the previous request. Any query or path parameters we collected are
+    /// dropped since the URL is expected to carry them. Credentials are attached to the
+    /// request, so only URLs on the connector's own origin are followed.
+    fn follow_url(&mut self, previous: &reqwest::Url, url: &str) -> error::Result<()> {
+        let url = previous.join(url)?;
+
+        // Still the connector's base URL at this point
+        let base = reqwest::Url::parse(&self.endpoint)?;
+        if url.origin() != base.origin() {
+            return Err(error::APICaller::CrossOriginNextUrl(
+                url.origin().ascii_serialization(),
+            ));
+        }
+
+        self.endpoint = url.to_string();
+        self.query_params.clear();
+        self.path_params.clear();
+
+        Ok(())
+    }
+
+    /// Asks for a specific page size, only when the connector tells us how.
+    fn apply_page_limit(
+        &mut self,
+        limit_param: &str,
+        max_limit: i32,
+        parameters: &[Parameter],
+    ) -> error::Result<()> {
+        if !limit_param.is_empty() && max_limit > 0_i32 {
+            self.apply_runtime_expression(
+                limit_param,
+                serde_json::Value::Number(max_limit.into()),
+                parameters,
+            )?;
+        }
+
+        Ok(())
+    }
+
     ///
     fn apply_runtime_expression(
         &mut self,
@@ -634,17 +1119,64 @@ impl APICallState {
 }
 
 ///
+#[derive(Default)]
 pub struct APICaller {
     ///
-    log: Arc<RwLock<File>>,
+    redactor: Redactor,
+
+    ///
+    clients: Clients,
+
+    ///
+    download_dir: Option<PathBuf>,
+
+    ///
+    cache: Option<ResponseCache>,
+
+    ///
+    cassettes: Cassettes,
 }
 
 impl APICaller {
     ///
     #[must_use]
     #[inline]
-    pub fn new(log: Arc<RwLock<File>>) -> Self {
-        Self { log }
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// JSON body fields (at any depth) to mask in the log, on top of the
+    /// credentials the connector's auth puts on each request.
+    #[must_use]
+    #[inline]
+    pub fn with_redacted_fields<I: IntoIterator<Item = String>>(mut self, fields: I) -> Self {
+        self.redactor = Redactor::new(fields);
+        self
+    }
+
+    /// Client settings for connectors that don't set their own in `config.json`.
+    #[must_use]
+    #[inline]
+    pub fn with_client_defaults(mut self, defaults: HttpClientConfig) -> Self {
+        self.clients = Clients::new(defaults);
+        self
+    }
+
+    /// Saves binary responses under a directory per execution instead of returning them base64
+    /// encoded, runs can pick another with the `download` option.
+    #[must_use]
+    #[inline]
+    pub fn with_download_dir(mut self, dir: PathBuf) -> Self {
+        self.download_dir = Some(dir);
+        self
+    }
+
+    /// Where responses are kept for connectors that turn caching on, it's off without one.
+    #[must_use]
+    #[inline]
+    pub fn with_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.cache = Some(ResponseCache::from(dir));
+        self
     }
 
     ///
@@ -682,50 +1214,125 @@ impl APICaller {
         let mut total: i32 = 0;
         let mut current_page: i32 = 0;
 
-        let mut page_responses: Vec<serde_json::Value> = Vec::new();
+        let downloads = options
+            .get(constants::DOWNLOAD_OPTION)
+            .and_then(serde_json::Value::as_str)
+            .map(Path::new)
+            .or(self.download_dir.as_deref());
+
+        let wrap = options
+            .get(constants::ENVELOPE_OPTION)
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        let error_statuses = ErrorStatuses::parse(
+            match options
+                .get(constants::ERROR_STATUSES_OPTION)
+                .and_then(serde_json::Value::as_str)
+            {
+                Some(rule) => rule,
+                None if !bundle.manifest.errorStatuses.is_empty() => &bundle.manifest.errorStatuses,
+                None if wrap => constants::NO_ERROR_STATUSES,
+                None => constants::DEFAULT_ERROR_STATUSES,
+            },
+        )?;
+
+        let no_cache = options
+            .get(constants::NO_CACHE_OPTION)
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+
+        let redactor = self.redactor.with_auth(bundle.manifest);
+        let call = CallInfo {
+            execution_id: &ctx.execution_id,
+            service: name,
+            operation: operation_name,
+            span: &ctx.span,
+            downloads,
+            // A cassette should have what the API actually said
+            cache: Caching::new(
+                self.cache.as_ref(),
+                &bundle.manifest.responseCache,
+                bundle.creds,
+            )
+            .filter(|_| !no_cache && ctx.cassette.is_none()),
+            cassette: ctx.cassette.as_ref(),
+            cassettes: &self.cassettes,
+        };
+
+        let client = self.clients.get(name, &bundle.manifest.httpClient)?;
+
+        let mut previous_response: Option<PageResponse> = None;
+        let mut results: Vec<serde_json::Value> = Vec::new();
 
         loop {
             // Create a request payload
             let mut call_state = APICallState::default();
             call_state.set_body(params.get("$body").cloned());
             call_state.collect_params(params, &operation.parameter, true)?;
-            call_state.handle_auth(bundle.manifest, bundle.creds)?;
             call_state.set_method(operation)?;
-            call_state.set_endpoint(bundle.api.basePath(), &operation.path);
+            call_state.set_endpoint(bundle.base_url(), &operation.path);
 
-            let request_size = call_state.handle_pagination(
+            let Some(request_size) = call_state.handle_pagination(
                 &operation.pagination.value,
-                page_responses.last(),
+                previous_response.as_ref(),
                 current_page,
                 &operation.parameter,
-            )?;
+            )?
+            else {
+                break;
+            };
+
+            // Auth goes last so that following a link to the next page keeps our credentials,
+            // which `follow_url` only allows on the connector's own origin
+            call_state.handle_auth(bundle.manifest, bundle.creds)?;
 
             // Send the request
-            let client = reqwest::blocking::Client::new();
-            let result = call_state.send(
-                format!("{name}.{operation_name}").as_str(),
-                &client,
-                &self.log,
-            )?;
+            let result = call_state.send(&call, current_page, &client, &redactor)?;
+            metrics::increment_counter!(constants::METRIC_PAGES, "connector" => name.to_owned());
+
+            if error_statuses.contains(result.status) {
+                return Err(error::APICaller::HttpStatus {
+                    status: result.status,
+                    body: redactor.body(&result.body).to_string(),
+                });
+            }
 
             // Unless the provided context told us to paginate,
             // we're going to bail early and just return the first raw response
             if ctx.raw_response {
-                return Ok(result);
+                return Ok(if wrap {
+                    envelope(Some(&result), result.body.clone())
+                } else {
+                    result.body
+                });
             }
 
-            // Peek at what the results path is
-            let actual_result = find_results(&result, &operation.pagination.value)?;
+            // Pull out the items on this page
+            let mut page_results = match find_results(&result, &operation.pagination.value)? {
+                serde_json::Value::Array(arr) => arr,
+                value @ (serde_json::Value::Null
+                | serde_json::Value::Bool(_)
+                | serde_json::Value::Number(_)
+                | serde_json::Value::String(_)
+                | serde_json::Value::Object(_)) => vec![value],
+            };
 
             // Determine how many items we got in a request
-            let current_size = if let &serde_json::Value::Array(ref arr) = actual_result {
-                i32::try_from(arr.len())?
+            let current_size = i32::try_from(page_results.len())?;
+
+            // Never hand back more than we were asked for
+            if total_limit > 0_i32 {
+                page_results.truncate(usize::try_from(total_limit.saturating_sub(total))?);
+            }
+
+            if ctx.is_streaming() {
+                ctx.emit(page_results)?;
             } else {
-                1_i32
-            };
+                results.extend(page_results);
+            }
 
-            // Push the raw response onto the vector for us to reference in the next iteration
-            page_responses.push(result);
+            // Hold onto the raw response for us to reference in the next iteration
+            previous_response = Some(result);
 
             current_page = current_page
                 .checked_add(1)
@@ -735,42 +1342,20 @@ impl APICaller {
                 .ok_or(error::APICaller::PagingOverflow)?;
 
             // Figure out if we're done or not
-            if request_size == 0_i32
-                || total_limit == 0_i32
-                || current_size < request_size
+            if total_limit == 0_i32
+                || (request_size > 0_i32 && current_size < request_size)
                 || total >= total_limit
             {
                 break;
             }
         }
 
-        let result: error::Result<Vec<serde_json::Value>> = page_responses
-            .into_iter()
-            .map(|response| {
-                let result = find_results(&response, &operation.pagination.value)?.clone();
-                Ok(result)
-            })
-            .collect();
-
-        let result: Vec<serde_json::Value> = result?
-            .into_iter()
-            .flat_map(|response| {
-                if let serde_json::Value::Array(arr) = response {
-                    arr
-                } else {
-                    vec![response]
-                }
-            })
-            .collect();
-
-        let total_limit: usize = total_limit.try_into()?;
-        let result = if total_limit > 0 {
-            result.get(..total_limit).unwrap_or(&result).to_vec()
+        let results = serde_json::Value::Array(results);
+        Ok(if wrap {
+            envelope(previous_response.as_ref(), results)
         } else {
-            result
-        };
-
-        Ok(serde_json::Value::Array(result))
+            results
+        })
     }
 }
 
@@ -789,3 +1374,616 @@ impl DataConnectionRunner for APICaller {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use std::io::Write;
+
+    fn response(headers: &[(&str, &str)], body: serde_json::Value) -> PageResponse {
+        PageResponse {
+            url: reqwest::Url::parse("https://example.com/items?page=1").unwrap(),
+            status: 200,
+            headers: headers
+                .iter()
+                .map(|&(key, value)| (key.to_owned(), value.into()))
+                .collect(),
+            body,
+        }
+    }
+
+    #[test]
+    fn test_parse_link_header() {
+        let header = r#"<https://example.com/items?page=2>; rel="next", <https://example.com/items?page=5>; rel="last""#;
+        let links = parse_link_header(header);
+
+        assert_eq!(
+            "https://example.com/items?page=2",
+            links.get("next").unwrap()
+        );
+        assert_eq!(
+            "https://example.com/items?page=5",
+            links.get("last").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_link_header_multiple_rels() {
+        let header = "<https://example.com/items?page=1>; rel=\"first prev\"";
+        let links = parse_link_header(header);
+
+        assert_eq!(links.get("first"), links.get("prev"));
+        assert!(!links.contains_key("next"));
+    }
+
+    #[test]
+    fn test_link_header_on_another_origin() -> error::Result<()> {
+        let mut link_header = pagination::LinkHeader::new();
+        link_header.rel = "next".into();
+        let config = Some(pagination::Value::LinkHeader(link_header));
+
+        let page = response(
+            &[("link", "<https://example.com/items?page=2>; rel=\"next\"")],
+            serde_json::Value::Null,
+        );
+        let mut state = APICallState::default();
+        state.set_endpoint("https://example.com", "/items");
+        state.handle_pagination(&config, Some(&page), 1, &[])?;
+        assert_eq!("https://example.com/items?page=2", state.endpoint);
+
+        let page = response(
+            &[(
+                "link",
+                "<https://attacker.example/items?page=2>; rel=\"next\"",
+            )],
+            serde_json::Value::Null,
+        );
+        let mut state = APICallState::default();
+        state.set_endpoint("https://example.com", "/items");
+        let requested = state.handle_pagination(&config, Some(&page), 1, &[]);
+        assert!(matches!(
+            requested,
+            Err(error::APICaller::CrossOriginNextUrl(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_header_expression() {
+        let page = response(&[("x-next-cursor", "abc")], serde_json::Value::Null);
+
+        let value = lookup_response_expression("$response.header.X-Next-Cursor", &page);
+        assert_eq!(Some(&serde_json::Value::from("abc")), value);
+
+        let value = lookup_response_expression("$response.header.X-Missing", &page);
+        assert_eq!(None, value);
+    }
+
+    #[test]
+    fn test_resolve_body_expression() {
+        let page = response(&[], serde_json::json!({ "data": { "items": [1, 2] } }));
+
+        let value = lookup_response_expression("$response.body#/data/items", &page);
+        assert_eq!(Some(&serde_json::json!([1, 2])), value);
+
+        let value = lookup_response_expression("$response.body#/", &page);
+        assert_eq!(Some(&page.body), value);
+    }
+
+    #[test]
+    fn test_follow_url_keeps_collected_query() -> error::Result<()> {
+        let previous = reqwest::Url::parse("https://example.com/items?page=1")?;
+
+        let mut state = APICallState::default();
+        state.set_endpoint("https://example.com", "/items");
+        state.follow_url(&previous, "https://example.com/items?page=2&per_page=10")?;
+        state
+            .query_params
+            .insert("per_page".into(), serde_json::Value::from(50));
+
+        let url = state.resolve_endpoint()?;
+        let pairs: HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        assert_eq!("2", pairs.get("page").unwrap());
+        assert_eq!("50", pairs.get("per_page").unwrap());
+
+        Ok(())
+    }
+
+    fn next_url_config(path: &str) -> pagination::NextUrl {
+        let mut next_url = pagination::NextUrl::new();
+        next_url
+            .nextUrlPath
+            .mut_or_insert_default()
+            .set_columnPath(path.into());
+
+        next_url
+    }
+
+    #[test]
+    fn test_follow_relative_url() -> error::Result<()> {
+        let previous = reqwest::Url::parse("https://example.com/api/items?page=1")?;
+
+        let mut state = APICallState::default();
+        state.set_endpoint("https://example.com/api", "/items");
+        state.follow_url(&previous, "/api/items?cursor=abc")?;
+
+        assert_eq!("https://example.com/api/items?cursor=abc", state.endpoint);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_url_follows_and_applies_limit() -> error::Result<()> {
+        let mut next_url = next_url_config("$response.body#/links/next");
+        next_url.limitParam = "$request.query.limit".into();
+        next_url.maxLimit.mut_or_insert_default().value = 25;
+        let config = Some(pagination::Value::NextUrl(next_url));
+
+        let page = response(&[], serde_json::json!({ "links": { "next": "?page=2" } }));
+
+        let mut state = APICallState::default();
+        state.set_endpoint("https://example.com", "/items");
+        let requested = state.handle_pagination(&config, Some(&page), 1, &[])?;
+        assert_eq!(Some(25), requested);
+
+        let url = state.resolve_endpoint()?;
+        assert_eq!("https://example.com/items?page=2&limit=25", url.as_str());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_url_on_another_origin() -> error::Result<()> {
+        let config = Some(pagination::Value::NextUrl(next_url_config(
+            "$response.body#/next",
+        )));
+
+        for next in [
+            "https://attacker.example/items?page=2",
+            "//example.com.attacker.example/items",
+            "http://example.com/items?page=2",
+            "https://example.com:8443/items?page=2",
+        ] {
+            let page = response(&[], serde_json::json!({ "next": next }));
+
+            let mut state = APICallState::default();
+            state.set_endpoint("https://example.com", "/items");
+            let requested = state.handle_pagination(&config, Some(&page), 1, &[]);
+            assert!(
+                matches!(requested, Err(error::APICaller::CrossOriginNextUrl(_))),
+                "{next}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_url_stops_on_null() -> error::Result<()> {
+        let config = Some(pagination::Value::NextUrl(next_url_config(
+            "$response.body#/next",
+        )));
+
+        let page = response(&[], serde_json::json!({ "next": null }));
+
+        let mut state = APICallState::default();
+        let requested = state.handle_pagination(&config, Some(&page), 1, &[])?;
+        assert_eq!(None, requested);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_next_url_missing_path() -> error::Result<()> {
+        let mut next_url = next_url_config("$response.body#/next");
+        let page = response(&[], serde_json::json!({}));
+
+        let config = Some(pagination::Value::NextUrl(next_url.clone()));
+        let mut state = APICallState::default();
+        assert_eq!(None, state.handle_pagination(&config, Some(&page), 1, &[])?);
+
+        next_url.errorOnPathNotFound.mut_or_insert_default().value = true;
+        let config = Some(pagination::Value::NextUrl(next_url));
+        let mut state = APICallState::default();
+        let requested = state.handle_pagination(&config, Some(&page), 1, &[]);
+        assert!(matches!(requested, Err(error::APICaller::NotFound(_))));
+
+        Ok(())
+    }
+
+    fn cursor_config(paths: &[&str], params: &[&str]) -> pagination::MultiCursor {
+        let mut cursor = pagination::MultiCursor::new();
+        cursor.cursorsPath = paths
+            .iter()
+            .map(|&path| {
+                let mut extended_path = pagination::ExtendedPath::new();
+                extended_path.set_columnPath(path.into());
+                extended_path
+            })
+            .collect();
+        cursor.cursorsParam = params.iter().map(|&param| param.to_owned()).collect();
+
+        cursor
+    }
+
+    #[test]
+    fn test_multi_cursor_applies_every_cursor() -> error::Result<()> {
+        let cursor = cursor_config(
+            &[
+                "$response.body#/next/after",
+                "$response.header.X-Sync-Token",
+            ],
+            &["$request.query.after", "$request.query.sync"],
+        );
+        let config = Some(pagination::Value::MultiCursor(cursor));
+
+        let page = response(
+            &[("x-sync-token", "xyz")],
+            serde_json::json!({ "next": { "after": "abc" } }),
+        );
+
+        let mut state = APICallState::default();
+        let requested = state.handle_pagination(&config, Some(&page), 1, &[])?;
+        assert_eq!(Some(0), requested);
+
+        assert_eq!(Some(&"abc".into()), state.query_params.get("after"));
+        assert_eq!(Some(&"xyz".into()), state.query_params.get("sync"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_cursor_stops_when_exhausted() -> error::Result<()> {
+        let cursor = cursor_config(
+            &[
+                "$response.body#/nextPageToken",
+                "$response.body#/nextRecordsUrl",
+            ],
+            &["$request.query.pageToken", "$request.query.records"],
+        );
+        let config = Some(pagination::Value::MultiCursor(cursor));
+
+        let page = response(&[], serde_json::json!({ "nextPageToken": "" }));
+
+        let mut state = APICallState::default();
+        assert_eq!(None, state.handle_pagination(&config, Some(&page), 1, &[])?);
+
+        let page = response(&[], serde_json::json!({ "nextPageToken": null }));
+
+        let mut state = APICallState::default();
+        assert_eq!(None, state.handle_pagination(&config, Some(&page), 1, &[])?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_cursor_mismatched() {
+        let cursor = cursor_config(&["$response.body#/next"], &[]);
+        let config = Some(pagination::Value::MultiCursor(cursor));
+
+        let mut state = APICallState::default();
+        let requested = state.handle_pagination(&config, None, 0, &[]);
+        assert!(matches!(
+            requested,
+            Err(error::APICaller::MismatchedCursors(1, 0))
+        ));
+    }
+
+    #[test]
+    fn test_jmes_path_results() -> error::Result<()> {
+        let mut unpaginated = pagination::Unpaginated::new();
+        unpaginated
+            .resultsPath
+            .mut_or_insert_default()
+            .set_jmesPath("data[?active].id".into());
+        let config = Some(pagination::Value::Unpaginated(unpaginated));
+
+        let page = response(
+            &[],
+            serde_json::json!({ "data": [
+                { "id": 1, "active": true },
+                { "id": 2, "active": false },
+                { "id": 3, "active": true },
+            ] }),
+        );
+
+        let results = find_results(&page, &config)?;
+        assert_eq!(serde_json::json!([1, 3]), results);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_results_path_not_found() -> error::Result<()> {
+        let mut unpaginated = pagination::Unpaginated::new();
+        unpaginated
+            .resultsPath
+            .mut_or_insert_default()
+            .set_columnPath("$response.body#/items".into());
+
+        let page = response(&[], serde_json::json!({}));
+
+        // The response changed shape, that's an error whatever `errorOnPathNotFound` says
+        for error_on_path_not_found in [false, true] {
+            unpaginated
+                .errorOnPathNotFound
+                .mut_or_insert_default()
+                .value = error_on_path_not_found;
+            let config = Some(pagination::Value::Unpaginated(unpaginated.clone()));
+            assert!(matches!(
+                find_results(&page, &config),
+                Err(error::APICaller::NotFound(_))
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jmes_path_results_null() -> error::Result<()> {
+        let mut unpaginated = pagination::Unpaginated::new();
+        unpaginated
+            .resultsPath
+            .mut_or_insert_default()
+            .set_jmesPath("data.items".into());
+
+        let page = response(&[], serde_json::json!({ "data": { "items": null } }));
+
+        let config = Some(pagination::Value::Unpaginated(unpaginated.clone()));
+        assert_eq!(serde_json::json!([]), find_results(&page, &config)?);
+
+        unpaginated
+            .errorOnPathNotFound
+            .mut_or_insert_default()
+            .value = true;
+        let config = Some(pagination::Value::Unpaginated(unpaginated));
+        assert!(matches!(
+            find_results(&page, &config),
+            Err(error::APICaller::NotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    /// Serves each of the bodies in order, one per connection, and hands back the base URL.
+    fn serve(bodies: Vec<String>) -> String {
+        serve_responses(bodies.into_iter().map(|body| ("200 OK", body)).collect())
+    }
+
+    /// Same as `serve` with a status line for each body.
+    fn serve_responses(responses: Vec<(&'static str, String)>) -> String {
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+
+                let mut stream = reader.into_inner();
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        format!("http://{address}")
+    }
+
+    #[test]
+    fn test_streams_each_page() -> error::Result<()> {
+        let base_path = serve(vec![
+            serde_json::json!({ "data": [1, 2], "next": "/items?page=2" }).to_string(),
+            serde_json::json!({ "data": [3], "next": null }).to_string(),
+        ]);
+
+        let mut next_url = next_url_config("$response.body#/next");
+        next_url
+            .resultsPath
+            .mut_or_insert_default()
+            .set_columnPath("$response.body#/data".into());
+
+        let mut operation = Operation::new();
+        operation.path = "items".into();
+        operation.method = core_entities::service::operation::HttpMethodType::GET.into();
+        operation
+            .pagination
+            .mut_or_insert_default()
+            .set_nextUrl(next_url);
+
+        let mut api = core_entities::service::CommonApi::new();
+        api.set_basePath(base_path);
+        api.operations.insert("list".into(), operation);
+
+        let manifest = SwaggerService::new();
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+
+        let caller = APICaller::new();
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        let ctx = EngineInputContext::new(None, "test".into(), false).with_stream(tx);
+
+        let options = serde_json::json!({ "limit": 10 });
+        let result = std::thread::scope(|scope| {
+            let handle = scope.spawn(|| {
+                caller.run_internal(
+                    "test",
+                    "list",
+                    &bundle,
+                    &serde_json::json!({}),
+                    &options,
+                    &ctx,
+                )
+            });
+
+            let pages: Vec<serde_json::Value> = rx.iter().take(2).collect();
+            assert_eq!(
+                vec![serde_json::json!([1, 2]), serde_json::json!([3])],
+                pages
+            );
+
+            handle.join().unwrap()
+        })?;
+
+        // Everything went out through the stream, nothing was buffered
+        assert_eq!(serde_json::json!([]), result);
+
+        Ok(())
+    }
+
+    fn single_operation(base_path: String) -> (SwaggerService, core_entities::service::CommonApi) {
+        let mut operation = Operation::new();
+        operation.path = "items".into();
+        operation.method = core_entities::service::operation::HttpMethodType::GET.into();
+
+        let mut api = core_entities::service::CommonApi::new();
+        api.set_basePath(base_path);
+        api.operations.insert("get".into(), operation);
+
+        (SwaggerService::new(), api)
+    }
+
+    #[test]
+    fn test_error_statuses_and_envelopes() -> error::Result<()> {
+        let not_found = || ("404 Not Found", r#"{ "error": "missing" }"#.to_owned());
+        let base_path = serve_responses(vec![not_found(), not_found(), not_found()]);
+
+        let (mut manifest, api) = single_operation(base_path);
+        let caller = APICaller::new();
+        let ctx = EngineInputContext::new(None, "test".into(), true);
+        let params = serde_json::json!({});
+
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+        let result = caller.run_internal(
+            "test",
+            "get",
+            &bundle,
+            &params,
+            &serde_json::json!({}),
+            &ctx,
+        );
+        assert!(matches!(
+            result,
+            Err(error::APICaller::HttpStatus { status: 404, ref body }) if body.contains("missing")
+        ));
+
+        let options = serde_json::json!({ "envelope": true });
+        let result = caller.run_internal("test", "get", &bundle, &params, &options, &ctx)?;
+        assert_eq!(404, result["status"]);
+        assert_eq!("application/json", result["headers"]["content-type"]);
+        assert_eq!(serde_json::json!({ "error": "missing" }), result["body"]);
+
+        // The connector's rule applies with an envelope too
+        manifest.errorStatuses = "404".into();
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+        let result = caller.run_internal("test", "get", &bundle, &params, &options, &ctx);
+        assert!(matches!(
+            result,
+            Err(error::APICaller::HttpStatus { status: 404, .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_responses() -> error::Result<()> {
+        let root =
+            std::env::temp_dir().join(format!("api-caller-responses-{}", std::process::id()));
+        let caller = APICaller::new().with_cache_dir(root.clone());
+        let ctx = EngineInputContext::new(None, "test".into(), true);
+        let params = serde_json::json!({});
+        let options = serde_json::json!({});
+
+        // A fresh response is used without calling the API again
+        let base_path = serve(vec![serde_json::json!({ "version": 1 }).to_string()]);
+        let (mut manifest, api) = single_operation(base_path);
+        let cache = manifest.responseCache.mut_or_insert_default();
+        cache.enabled = Some(protobuf::well_known_types::wrappers::BoolValue {
+            value: true,
+            ..Default::default()
+        })
+        .into();
+        cache.ttlSeconds = Some(protobuf::well_known_types::wrappers::UInt64Value {
+            value: 60,
+            ..Default::default()
+        })
+        .into();
+
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+        for _ in 0..2 {
+            let result = caller.run_internal("test", "get", &bundle, &params, &options, &ctx)?;
+            assert_eq!(serde_json::json!({ "version": 1 }), result);
+        }
+
+        // A stale one is revalidated
+        let base_path = serve_responses(vec![
+            (
+                "200 OK\r\nETag: \"v2\"",
+                serde_json::json!({ "version": 2 }).to_string(),
+            ),
+            ("304 Not Modified", String::new()),
+        ]);
+        let (_, api) = single_operation(base_path);
+        manifest.responseCache.mut_or_insert_default().ttlSeconds = None.into();
+
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+        for _ in 0..2 {
+            let result = caller.run_internal("test", "get", &bundle, &params, &options, &ctx)?;
+            assert_eq!(serde_json::json!({ "version": 2 }), result);
+        }
+
+        std::fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_records_and_replays() -> error::Result<()> {
+        let dir = std::env::temp_dir().join(format!("api-caller-cassettes-{}", std::process::id()));
+        let base_path = serve(vec![serde_json::json!({ "id": 7 }).to_string()]);
+        let (manifest, api) = single_operation(base_path);
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+
+        let caller = APICaller::new();
+        let params = serde_json::json!({});
+        let options = serde_json::json!({});
+
+        let ctx = EngineInputContext::new(None, "recorded".into(), true)
+            .with_cassette(Some(Cassette::Record(dir.clone())));
+        let recorded = caller.run_internal("test", "get", &bundle, &params, &options, &ctx)?;
+
+        // The server is gone, only the cassette can answer
+        let ctx = EngineInputContext::new(None, "replayed".into(), true)
+            .with_cassette(Some(Cassette::Replay(dir.join("recorded.jsonl"))));
+        let replayed = caller.run_internal("test", "get", &bundle, &params, &options, &ctx)?;
+        assert_eq!(recorded, replayed);
+
+        let mut operation = Operation::new();
+        operation.path = "other".into();
+        operation.method = core_entities::service::operation::HttpMethodType::GET.into();
+        let mut api = api.clone();
+        api.operations.insert("other".into(), operation);
+        let bundle = DataConnectorBundle::new(&manifest, &api, None);
+        assert!(matches!(
+            caller.run_internal("test", "other", &bundle, &params, &options, &ctx),
+            Err(error::APICaller::NotRecorded(_))
+        ));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
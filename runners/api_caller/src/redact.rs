@@ -0,0 +1,251 @@
+//! Masks secrets before requests and responses make it into the API log.
+
+use std::collections::{HashMap, HashSet};
+
+use core_entities::service::{swagger_service::service_auth, SwaggerService};
+
+use crate::constants;
+
+/// Names are compared case insensitively, values are replaced entirely.
+#[derive(Clone, Default)]
+pub struct Redactor {
+    ///
+    headers: HashSet<String>,
+
+    ///
+    query: HashSet<String>,
+
+    ///
+    path: HashSet<String>,
+
+    ///
+    body_fields: HashSet<String>,
+}
+
+impl Redactor {
+    ///
+    #[must_use]
+    pub fn new<I: IntoIterator<Item = String>>(body_fields: I) -> Self {
+        Self {
+            body_fields: body_fields
+                .into_iter()
+                .map(|field| field.to_lowercase())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds whatever the connector's auth definition puts on the request.
+    #[must_use]
+    pub fn with_auth(&self, manifest: &SwaggerService) -> Self {
+        let mut redactor = self.clone();
+
+        let defined_auth = &manifest.auth;
+        let param = |key: &str| {
+            defined_auth
+                .params
+                .get(key)
+                .map(|value| value.string().to_lowercase())
+        };
+
+        match defined_auth.type_.enum_value() {
+            Ok(service_auth::Type::HEADER | service_auth::Type::OAUTH) => {
+                redactor.headers.extend(param("header"));
+            }
+            Ok(service_auth::Type::PARAMETER) => {
+                redactor.query.extend(param("name"));
+            }
+            Ok(service_auth::Type::PATH) => {
+                redactor.path.extend(param("path"));
+            }
+            Ok(service_auth::Type::BASIC) => {
+                redactor
+                    .headers
+                    .insert(constants::AUTHORIZATION_HEADER.to_owned());
+            }
+            Ok(service_auth::Type::MULTIHEADER) => {
+                if let Some(headers) = defined_auth.params.get("headers") {
+                    redactor.headers.extend(
+                        headers
+                            .multiHeaderAuth()
+                            .strings
+                            .iter()
+                            .map(|header| header.to_lowercase()),
+                    );
+                }
+            }
+            Ok(service_auth::Type::UNSET) | Err(_) => {}
+        }
+
+        redactor
+    }
+
+    ///
+    #[must_use]
+    pub fn header<'value>(&self, name: &str, value: &'value str) -> &'value str {
+        if self.headers.contains(&name.to_lowercase()) {
+            constants::REDACTED
+        } else {
+            value
+        }
+    }
+
+    ///
+    #[must_use]
+    pub fn headers(
+        &self,
+        headers: &HashMap<String, serde_json::Value>,
+    ) -> HashMap<String, serde_json::Value> {
+        headers
+            .iter()
+            .map(|(key, value)| {
+                if self.headers.contains(&key.to_lowercase()) {
+                    (key.clone(), constants::REDACTED.into())
+                } else {
+                    (key.clone(), value.clone())
+                }
+            })
+            .collect()
+    }
+
+    /// Path parameters have to be masked before they're substituted into the endpoint.
+    #[must_use]
+    pub fn path_params(
+        &self,
+        params: &HashMap<String, serde_json::Value>,
+    ) -> HashMap<String, serde_json::Value> {
+        params
+            .iter()
+            .map(|(key, value)| {
+                if self.path.contains(&key.to_lowercase()) {
+                    (key.clone(), constants::REDACTED.into())
+                } else {
+                    (key.clone(), value.clone())
+                }
+            })
+            .collect()
+    }
+
+    ///
+    #[must_use]
+    pub fn url(&self, url: &reqwest::Url) -> reqwest::Url {
+        if self.query.is_empty() || url.query().is_none() {
+            return url.clone();
+        }
+
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(key, value)| {
+                if self.query.contains(&key.to_lowercase()) {
+                    (key.into_owned(), constants::REDACTED.to_owned())
+                } else {
+                    (key.into_owned(), value.into_owned())
+                }
+            })
+            .collect();
+
+        let mut url = url.clone();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+
+        url
+    }
+
+    /// Masks any denied field, no matter how deeply it's nested.
+    #[must_use]
+    pub fn body(&self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            &serde_json::Value::Object(ref fields) => fields
+                .iter()
+                .map(|(key, value)| {
+                    if self.body_fields.contains(&key.to_lowercase()) {
+                        (key.clone(), constants::REDACTED.into())
+                    } else {
+                        (key.clone(), self.body(value))
+                    }
+                })
+                .collect(),
+            &serde_json::Value::Array(ref items) => {
+                items.iter().map(|item| self.body(item)).collect()
+            }
+            &serde_json::Value::Null
+            | &serde_json::Value::Bool(_)
+            | &serde_json::Value::Number(_)
+            | &serde_json::Value::String(_) => value.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    fn auth(type_: service_auth::Type, key: &str, value: &str) -> SwaggerService {
+        let mut manifest = SwaggerService::new();
+        let auth = manifest.auth.mut_or_insert_default();
+        auth.type_ = type_.into();
+
+        let mut param = service_auth::AuthParam::new();
+        param.set_string(value.into());
+        auth.params.insert(key.into(), param);
+
+        manifest
+    }
+
+    #[test]
+    fn test_redacts_auth_header() {
+        let manifest = auth(service_auth::Type::HEADER, "header", "X-Api-Key");
+        let redactor = Redactor::default().with_auth(&manifest);
+
+        assert_eq!(constants::REDACTED, redactor.header("x-api-key", "secret"));
+        assert_eq!(
+            "application/json",
+            redactor.header("accept", "application/json")
+        );
+    }
+
+    #[test]
+    fn test_redacts_basic_auth() {
+        let manifest = auth(service_auth::Type::BASIC, "unused", "");
+        let redactor = Redactor::default().with_auth(&manifest);
+
+        assert_eq!(
+            constants::REDACTED,
+            redactor.header("Authorization", "Basic dXNlcjpwYXNz")
+        );
+    }
+
+    #[test]
+    fn test_redacts_query_auth() {
+        let manifest = auth(service_auth::Type::PARAMETER, "name", "api_key");
+        let redactor = Redactor::default().with_auth(&manifest);
+
+        let url = reqwest::Url::parse("https://example.com/items?api_key=secret&page=2").unwrap();
+        let url = redactor.url(&url);
+
+        let pairs: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(constants::REDACTED, pairs.get("api_key").unwrap());
+        assert_eq!("2", pairs.get("page").unwrap());
+    }
+
+    #[test]
+    fn test_redacts_body_fields() {
+        let redactor = Redactor::new(vec!["Password".to_owned(), "token".to_owned()]);
+
+        let body = serde_json::json!({
+            "username": "user",
+            "password": "hunter2",
+            "sessions": [{ "Token": "abc", "id": 1 }],
+        });
+
+        assert_eq!(
+            serde_json::json!({
+                "username": "user",
+                "password": constants::REDACTED,
+                "sessions": [{ "Token": constants::REDACTED, "id": 1 }],
+            }),
+            redactor.body(&body)
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Which response statuses fail a run, written as codes, classes and ranges: `4xx,5xx`,
+//! `500-599,429` or `none`.
+
+use crate::{constants, error};
+
+///
+#[derive(Debug, PartialEq, Eq)]
+pub struct ErrorStatuses {
+    /// Inclusive.
+    ranges: Vec<(u16, u16)>,
+}
+
+impl ErrorStatuses {
+    ///
+    /// # Errors
+    /// When part of the rule isn't a status, a class or a range.
+    #[inline]
+    pub fn parse(rule: &str) -> error::Result<Self> {
+        let invalid = || error::APICaller::InvalidStatusRule(rule.to_owned());
+
+        if rule
+            .trim()
+            .eq_ignore_ascii_case(constants::NO_ERROR_STATUSES)
+        {
+            return Ok(Self { ranges: vec![] });
+        }
+
+        let ranges = rule
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let lower = part.to_ascii_lowercase();
+                let range = if let Some(class) = lower.strip_suffix("xx") {
+                    let class = class.parse::<u16>().ok().ok_or_else(invalid)?;
+                    let start = class.checked_mul(100).ok_or_else(invalid)?;
+                    (start, start.saturating_add(99))
+                } else if let Some((start, end)) = lower.split_once('-') {
+                    (
+                        start.trim().parse().ok().ok_or_else(invalid)?,
+                        end.trim().parse().ok().ok_or_else(invalid)?,
+                    )
+                } else {
+                    let status = lower.parse().ok().ok_or_else(invalid)?;
+                    (status, status)
+                };
+
+                if range.0 > range.1 {
+                    return Err(invalid());
+                }
+                Ok(range)
+            })
+            .collect::<error::Result<Vec<_>>>()?;
+
+        Ok(Self { ranges })
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, status: u16) -> bool {
+        self.ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&status))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_classes_ranges_and_codes() {
+        let rule = ErrorStatuses::parse("5XX, 400-403,429").unwrap();
+        assert!(rule.contains(503));
+        assert!(rule.contains(401));
+        assert!(rule.contains(429));
+        assert!(!rule.contains(404));
+        assert!(!rule.contains(200));
+
+        assert!(!ErrorStatuses::parse("none").unwrap().contains(500));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(ErrorStatuses::parse("4xx,oops").is_err());
+        assert!(ErrorStatuses::parse("500-400").is_err());
+        assert!(ErrorStatuses::parse("x4xx").is_err());
+    }
+}
@@ -27,22 +27,13 @@ pub mod error;
 extern crate alloc;
 use alloc::{rc::Rc, sync::Arc};
 
-use lazy_static::lazy_static;
-use std::{fs::File, sync::RwLock};
+use std::sync::RwLock;
 
 use execution_engine::services::FilteredRunner;
-use regex::Regex;
-
-lazy_static! {
-    static ref OPERATION_REGEX: Option<Regex> =
-        Regex::new("(?P<group>.*)/(?P<app>.*):(?P<version>.*)").ok();
-}
+use service_loader::identity::ConnectorId;
 
 ///
 pub struct APIWrapper {
-    ///
-    _log: Arc<RwLock<File>>,
-
     ///
     engine: Arc<RwLock<execution_engine::Engine>>,
 }
@@ -51,8 +42,8 @@ impl APIWrapper {
     ///
     #[must_use]
     #[inline]
-    pub fn new(log: Arc<RwLock<File>>, engine: Arc<RwLock<execution_engine::Engine>>) -> Self {
-        Self { _log: log, engine }
+    pub fn new(engine: Arc<RwLock<execution_engine::Engine>>) -> Self {
+        Self { engine }
     }
 
     ///
@@ -81,11 +72,7 @@ impl APIWrapper {
             }
         }
 
-        let context = execution_engine::services::EngineInputContext::new(
-            Some(name.to_owned()),
-            ctx.execution_id.clone(),
-            true,
-        );
+        let context = ctx.child(true).with_parent(Some(name.to_owned()));
 
         let engine = self
             .engine
@@ -125,21 +112,13 @@ impl FilteredRunner for APIWrapper {
     }
 }
 
-///
-fn extract_connector_id(id: &str) -> error::Result<&str> {
-    let op_regex = OPERATION_REGEX
-        .as_ref()
-        .ok_or_else(|| error::FilteredRunner::UnknownConnectorId(id.to_owned()))?;
-    let captures = op_regex
-        .captures(id)
-        .ok_or_else(|| error::FilteredRunner::UnknownConnectorId(id.to_owned()))?;
-
-    let app = captures
-        .name("app")
+/// The key the wrapped connector is loaded under, resolved exactly as `group/app:version`
+/// so that another version or maintainer's connector of the same name is never run instead.
+fn extract_connector_id(id: &str) -> error::Result<String> {
+    let id = ConnectorId::parse(id)
         .ok_or_else(|| error::FilteredRunner::UnknownConnectorId(id.to_owned()))?;
-    let app = app.as_str();
 
-    Ok(app)
+    Ok(id.to_string())
 }
 
 ///
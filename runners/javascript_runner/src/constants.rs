@@ -1,4 +0,0 @@
-//!
-
-///
-pub const DATETIME_FORMAT: &str = "%a %b %e %Y %I:%M:%S %p";
@@ -21,7 +21,6 @@
 //!
 
 // pub mod bindings;
-mod constants;
 mod converters;
 pub mod error;
 
@@ -29,7 +28,7 @@ extern crate alloc;
 use alloc::sync::Arc;
 use mini_v8::MiniV8;
 
-use std::{fs::File, io::Write, sync::RwLock};
+use std::sync::RwLock;
 
 use execution_engine::services::CodeRunner;
 
@@ -84,9 +83,6 @@ fn wrap_source_code(source: &str) -> error::Result<String> {
 
 ///
 pub struct JsActionRunner {
-    ///
-    logger: Arc<RwLock<File>>,
-
     ///
     engine: Arc<RwLock<execution_engine::Engine>>,
 }
@@ -94,8 +90,8 @@ pub struct JsActionRunner {
 impl JsActionRunner {
     ///
     #[inline]
-    pub fn new(engine: Arc<RwLock<execution_engine::Engine>>, logger: Arc<RwLock<File>>) -> Self {
-        Self { logger, engine }
+    pub fn new(engine: Arc<RwLock<execution_engine::Engine>>) -> Self {
+        Self { engine }
     }
 
     ///
@@ -109,28 +105,20 @@ impl JsActionRunner {
     ) -> error::Result<serde_json::Value> {
         let mv8 = MiniV8::new();
 
-        let logger = Arc::clone(&self.logger);
         let engine = Arc::clone(&self.engine);
         let name = name.to_owned();
         let execution_id = ctx.execution_id.clone();
+        let context = ctx.child(false).with_parent(Some(name.clone()));
         let api_binding = mv8.create_function(move |inv| -> mini_v8::Result<mini_v8::Value> {
             let (id, params, options): (String, mini_v8::Value, Option<mini_v8::Value>) =
                 inv.args.into(&inv.mv8)?;
 
-            let now = chrono::offset::Local::now();
-            let now = now.format(constants::DATETIME_FORMAT).to_string();
-
-            {
-                let mut logger = logger.write().map_err(|err| {
-                    mini_v8::Error::ExternalError(Box::new(error::JsActionRunner::PoisonedLock(
-                        err.to_string(),
-                    )))
-                })?;
-
-                logger
-                    .write_all(format!("{now} ({}) [API] {id}\n", name.clone()).as_bytes())
-                    .map_err(|err| mini_v8::Error::ExternalError(Box::new(err)))?;
-            };
+            tracing::debug!(
+                execution_id = %execution_id,
+                parent = %name,
+                operation = %id,
+                "api called from script"
+            );
 
             let params = converters::from_v8(params)?;
             let options = if let Some(options) = options {
@@ -144,11 +132,6 @@ impl JsActionRunner {
                     err.to_string(),
                 )))
             })?;
-            let context = execution_engine::services::EngineInputContext::new(
-                Some(name.clone()),
-                execution_id.clone(),
-                false,
-            );
             let result = engine
                 .run(&id, params, options, &context)
                 .map_err(|err| mini_v8::Error::ExternalError(Box::new(err)))?;
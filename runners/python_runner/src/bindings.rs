@@ -5,13 +5,12 @@
 extern crate alloc;
 use alloc::sync::Arc;
 
-use std::io::Write;
 use std::sync::RwLock;
 use std::thread;
 
 use core::time::Duration;
 
-use super::{constants, converters, File};
+use super::{constants, converters};
 use pyo3::exceptions::{PyArithmeticError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyString};
@@ -28,9 +27,6 @@ pub struct TaskBinding {
 
     ///
     pub ctx: execution_engine::services::EngineInputContext,
-
-    ///
-    pub logger: Arc<RwLock<File>>,
 }
 
 #[pymethods]
@@ -42,12 +38,7 @@ impl TaskBinding {
             params: params.into(),
             name: self.name.clone(),
             engine: Arc::<RwLock<execution_engine::Engine>>::clone(&self.engine),
-            ctx: execution_engine::services::EngineInputContext::new(
-                self.ctx.parent.clone(),
-                self.ctx.execution_id.clone(),
-                false,
-            ),
-            logger: Arc::<RwLock<File>>::clone(&self.logger),
+            ctx: self.ctx.child(false),
         }
     }
 }
@@ -64,9 +55,6 @@ pub struct Task {
     ///
     pub ctx: execution_engine::services::EngineInputContext,
 
-    ///
-    pub logger: Arc<RwLock<File>>,
-
     ///
     pub id: String,
 
@@ -79,18 +67,14 @@ impl Task {
     ///
     #[pyo3(name = "continueAfter")]
     pub fn continue_after(&self, py: Python<'_>, delay: u64, unit: &str) -> PyResult<Py<PyAny>> {
-        let now = chrono::offset::Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
-
-        {
-            let mut logger = self
-                .logger
-                .write()
-                .map_err(|e| PyValueError::new_err(format!("Locking Error: {e}")))?;
-
-            logger
-                .write_all(format!("{now} ({}) [TASK|WAIT] {}\n", self.name, self.id).as_bytes())?;
-        };
+        tracing::info!(
+            execution_id = %self.ctx.execution_id,
+            name = %self.name,
+            task = %self.id,
+            delay,
+            unit,
+            "task waiting"
+        );
 
         match unit {
             "MINUTE" => {
@@ -130,19 +114,12 @@ impl Task {
     ///
     #[pyo3(name = "continueAfterUserInput")]
     pub fn continue_after_user_input(&self, py: Python<'_>, blocks: &PyAny) -> PyResult<Py<PyAny>> {
-        let now = chrono::offset::Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
-
-        {
-            let mut logger = self
-                .logger
-                .write()
-                .map_err(|e| PyValueError::new_err(format!("Locking Error: {e}")))?;
-
-            logger.write_all(
-                format!("{now} ({}) [TASK|INPUT] {}\n", self.name, self.id).as_bytes(),
-            )?;
-        };
+        tracing::info!(
+            execution_id = %self.ctx.execution_id,
+            name = %self.name,
+            task = %self.id,
+            "task waiting for input"
+        );
 
         let blocks = converters::from_py(blocks)?;
 
@@ -185,9 +162,6 @@ pub struct APIBindingWraper {
 
     ///
     pub ctx: execution_engine::services::EngineInputContext,
-
-    ///
-    pub logger: Arc<RwLock<File>>,
 }
 
 #[pymethods]
@@ -200,17 +174,12 @@ impl APIBindingWraper {
         params: &PyAny,
         options: Option<&PyAny>,
     ) -> PyResult<Py<PyAny>> {
-        let now = chrono::offset::Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
-
-        {
-            let mut logger = self
-                .logger
-                .write()
-                .map_err(|e| PyValueError::new_err(format!("Locking Error: {e}")))?;
-
-            logger.write_all(format!("{now} ({}) [API] {id}\n", self.name).as_bytes())?;
-        };
+        tracing::debug!(
+            execution_id = %self.ctx.execution_id,
+            name = %self.name,
+            operation = id,
+            "api called from script"
+        );
 
         let params = converters::from_py(params)?;
 
@@ -247,9 +216,6 @@ pub struct WorkflowLogger {
     ///
     pub name: String,
 
-    ///
-    pub loggers: Arc<RwLock<File>>,
-
     ///
     pub output: Py<PyDict>,
 }
@@ -333,36 +299,7 @@ impl WorkflowLogger {
 
     ///
     fn print_display(&mut self, display: &PyAny, log_level: &str) -> PyResult<()> {
-        let now = chrono::offset::Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
-
-        let mut logger = self
-            .loggers
-            .write()
-            .map_err(|e| PyValueError::new_err(format!("Locking Error: {e}")))?;
-
-        if display.is_instance_of::<PyDict>()? {
-            let display = display.downcast::<PyDict>()?;
-            let summary = display
-                .get_item("summary")
-                .and_then(|s| s.downcast::<PyString>().ok())
-                .and_then(|s| s.to_str().ok())
-                .ok_or_else(|| PyTypeError::new_err("Unable to find summary in display object"))?;
-
-            logger.write_all(
-                format!("{now} ({}) [workflow|{log_level}]: {summary}\n", self.name).as_bytes(),
-            )?;
-        } else if display.is_instance_of::<PyString>()? {
-            let summary = display.downcast::<PyString>()?.to_str()?;
-
-            logger.write_all(
-                format!("{now} ({}) [workflow|{log_level}]: {summary}\n", self.name).as_bytes(),
-            )?;
-        } else {
-            return Err(PyTypeError::new_err("Invalid type for display object"));
-        }
-
-        Ok(())
+        log_display("workflow", &self.name, display, log_level)
     }
 }
 
@@ -389,9 +326,6 @@ impl Action {
 pub struct ActionLogger {
     ///
     pub name: String,
-
-    ///
-    pub logger: Arc<RwLock<File>>,
 }
 
 #[pymethods]
@@ -416,35 +350,30 @@ impl ActionLogger {
 
     ///
     fn print_display(&mut self, display: &PyAny, log_level: &str) -> PyResult<()> {
-        let now = chrono::offset::Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
-
-        let mut logger = self
-            .logger
-            .write()
-            .map_err(|e| PyValueError::new_err(format!("Locking Error: {e}")))?;
-
-        if display.is_instance_of::<PyDict>()? {
-            let display = display.downcast::<PyDict>()?;
-            let summary = display
-                .get_item("summary")
-                .and_then(|s| s.downcast::<PyString>().ok())
-                .and_then(|s| s.to_str().ok())
-                .ok_or_else(|| PyTypeError::new_err("Unable to find summary in display object"))?;
-
-            logger.write_all(
-                format!("{now} ({}) [action|{log_level}]: {summary}\n", self.name).as_bytes(),
-            )?;
-        } else if display.is_instance_of::<PyString>()? {
-            let summary = display.downcast::<PyString>()?.to_str()?;
-
-            logger.write_all(
-                format!("{now} ({}) [action|{log_level}]: {summary}\n", self.name).as_bytes(),
-            )?;
-        } else {
-            return Err(PyTypeError::new_err("Invalid type for display object"));
-        }
+        log_display("action", &self.name, display, log_level)
+    }
+}
 
-        Ok(())
+/// Scripts hand us either a plain summary or a display object with one.
+fn log_display(kind: &str, name: &str, display: &PyAny, log_level: &str) -> PyResult<()> {
+    let summary = if display.is_instance_of::<PyDict>()? {
+        display
+            .downcast::<PyDict>()?
+            .get_item("summary")
+            .and_then(|s| s.downcast::<PyString>().ok())
+            .and_then(|s| s.to_str().ok())
+            .ok_or_else(|| PyTypeError::new_err("Unable to find summary in display object"))?
+    } else if display.is_instance_of::<PyString>()? {
+        display.downcast::<PyString>()?.to_str()?
+    } else {
+        return Err(PyTypeError::new_err("Invalid type for display object"));
+    };
+
+    match log_level {
+        constants::LOG_ERROR => tracing::error!(kind, name, level = log_level, summary),
+        constants::LOG_WARN => tracing::warn!(kind, name, level = log_level, summary),
+        _ => tracing::info!(kind, name, level = log_level, summary),
     }
+
+    Ok(())
 }
@@ -41,6 +41,3 @@ pub const LOG_STATUS: &str = "STATUS";
 
 ///
 pub const DEFAULT_FUNCTION_NAME: &str = "execute";
-
-///
-pub const DATETIME_FORMAT: &str = "%a %b %e %Y %I:%M:%S %p";
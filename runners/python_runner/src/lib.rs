@@ -29,7 +29,6 @@ mod pyconf;
 extern crate alloc;
 use alloc::sync::Arc;
 
-use std::fs::File;
 use std::sync::RwLock;
 
 use execution_engine::services::CodeRunner;
@@ -56,17 +55,14 @@ where
 pub struct PyActionRunner {
     ///
     engine: Arc<RwLock<execution_engine::Engine>>,
-
-    ///
-    loggers: Arc<RwLock<File>>,
 }
 
 impl PyActionRunner {
     ///
     #[inline]
     #[must_use]
-    pub fn new(loggers: Arc<RwLock<File>>, engine: Arc<RwLock<execution_engine::Engine>>) -> Self {
-        Self { engine, loggers }
+    pub fn new(engine: Arc<RwLock<execution_engine::Engine>>) -> Self {
+        Self { engine }
     }
 
     ///
@@ -98,38 +94,26 @@ impl PyActionRunner {
             let api = bindings::APIBindingWraper {
                 name: format!("{name}.{operation_name}"),
                 engine: Arc::clone(&self.engine),
-                ctx: execution_engine::services::EngineInputContext::new(
-                    Some(name.to_owned()),
-                    ctx.execution_id.clone(),
-                    false,
-                ),
-                logger: Arc::clone(&self.loggers),
+                ctx: ctx.child(false).with_parent(Some(name.to_owned())),
             };
 
             let workflow = bindings::Workflow {
                 log: bindings::WorkflowLogger {
                     name: format!("{name}.{operation_name}"),
                     output: output.into(),
-                    loggers: Arc::clone(&self.loggers),
                 },
             };
 
             let action = bindings::Action {
                 log: bindings::ActionLogger {
                     name: format!("{name}.{operation_name}"),
-                    logger: Arc::clone(&self.loggers),
                 },
             };
 
             let task = bindings::TaskBinding {
                 name: format!("{name}.{operation_name}"),
                 engine: Arc::clone(&self.engine),
-                ctx: execution_engine::services::EngineInputContext::new(
-                    Some(name.to_owned()),
-                    ctx.execution_id.clone(),
-                    true,
-                ),
-                logger: Arc::clone(&self.loggers),
+                ctx: ctx.child(true).with_parent(Some(name.to_owned())),
             };
 
             run_python(|| {
@@ -185,3 +169,169 @@ impl CodeRunner for PyActionRunner {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    use std::collections::HashMap;
+
+    use core_entities::service::{SwaggerService, VersionedServiceTree};
+    use credential_entities::credentials::Authentication;
+    use execution_engine::services::{
+        Cassette, DataConnectionRunner, DataConnectorBundle, EngineInputContext, EngineLookup,
+    };
+
+    /// Runs a task from Python and hands back what it ran with.
+    const NESTED_TASK: &str = "
+def execute(input):
+    return task.create('api.list', {}).continueAfter(0, 'SECOND')
+";
+
+    /// Only `api`, whose credentials exist for the `work` profile.
+    struct Lookup(Arc<VersionedServiceTree>);
+
+    impl EngineLookup for Lookup {
+        fn get_service(&self, id: &str) -> Option<Arc<VersionedServiceTree>> {
+            (id == "api").then(|| Arc::clone(&self.0))
+        }
+
+        fn get_credentials(&self, _: &str, profile: Option<&str>) -> Option<Arc<Authentication>> {
+            (profile == Some("work")).then(|| Arc::new(Authentication::new()))
+        }
+    }
+
+    /// Answers with the profile, whether there were credentials and where the call would go.
+    struct Connector;
+
+    impl DataConnectionRunner for Connector {
+        fn run(
+            &self,
+            _: &str,
+            _: &str,
+            bundle: &DataConnectorBundle,
+            _: Value,
+            _: Value,
+            ctx: &EngineInputContext,
+        ) -> execution_engine::error::Result<Value> {
+            Ok(serde_json::json!({
+                "profile": ctx.profile,
+                "authenticated": bundle.creds.is_some(),
+                "baseUrl": bundle.base_url(),
+            }))
+        }
+    }
+
+    fn api() -> VersionedServiceTree {
+        let mut service = VersionedServiceTree::new();
+        let v1 = service.mut_v1();
+        v1.manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .set_swagger(SwaggerService::new());
+        let api = v1.commonApi.mut_or_insert_default();
+        api.set_basePath("https://example.com".into());
+        api.environments = HashMap::from([(
+            "staging".to_owned(),
+            "https://staging.example.com".to_owned(),
+        )]);
+        service
+    }
+
+    fn engine(
+        service: VersionedServiceTree,
+        connector: Box<dyn DataConnectionRunner + Send + Sync>,
+    ) -> Arc<RwLock<execution_engine::Engine>> {
+        let engine = Arc::new(RwLock::new(execution_engine::Engine::new(Arc::new(
+            Lookup(Arc::new(service)),
+        ))));
+        engine.write().unwrap().register_connector(connector);
+        engine
+    }
+
+    #[test]
+    fn test_nested_tasks_use_the_parents_profile() {
+        let engine = engine(api(), Box::new(Connector));
+        let runner = PyActionRunner::new(Arc::clone(&engine));
+
+        let ctx =
+            EngineInputContext::new(None, "nested".into(), true).with_profile(Some("work".into()));
+        let result = runner
+            .run(
+                "script",
+                "execute",
+                NESTED_TASK,
+                serde_json::json!({}),
+                &ctx,
+            )
+            .unwrap();
+
+        assert_eq!(Some("work"), result[0]["profile"].as_str());
+        assert_eq!(Some(true), result[0]["authenticated"].as_bool());
+    }
+
+    #[test]
+    fn test_nested_tasks_use_the_parents_environment() {
+        let engine = engine(api(), Box::new(Connector));
+        let runner = PyActionRunner::new(Arc::clone(&engine));
+
+        let ctx = EngineInputContext::new(None, "nested".into(), true)
+            .with_environment(Some("staging".into()));
+        let result = runner
+            .run(
+                "script",
+                "execute",
+                NESTED_TASK,
+                serde_json::json!({}),
+                &ctx,
+            )
+            .unwrap();
+
+        assert_eq!(
+            Some("https://staging.example.com"),
+            result[0]["baseUrl"].as_str()
+        );
+    }
+
+    #[test]
+    fn test_nested_tasks_replay_the_parents_cassette() {
+        let dir = std::env::temp_dir().join(format!("python-runner-replay-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cassette = dir.join("replayed.jsonl");
+        let interaction = serde_json::json!({
+            "method": "GET",
+            "url": "http://127.0.0.1:9/items",
+            "response": { "status": 200, "headers": {}, "body": "[{\"id\":7}]" }
+        });
+        std::fs::write(&cassette, format!("{interaction}\n")).unwrap();
+
+        // Nothing listens on the discard port, only the cassette can answer
+        let mut service = api();
+        let api = service.mut_v1().commonApi.mut_or_insert_default();
+        api.set_basePath("http://127.0.0.1:9".into());
+        let mut operation = core_entities::service::Operation::new();
+        operation.path = "items".into();
+        operation.method = core_entities::service::operation::HttpMethodType::GET.into();
+        api.operations.insert("list".into(), operation);
+
+        let engine = engine(service, Box::new(api_caller::APICaller::new()));
+        let runner = PyActionRunner::new(Arc::clone(&engine));
+
+        let ctx = EngineInputContext::new(None, "replayed".into(), true)
+            .with_cassette(Some(Cassette::Replay(cassette)));
+        let result = runner
+            .run(
+                "script",
+                "execute",
+                NESTED_TASK,
+                serde_json::json!({}),
+                &ctx,
+            )
+            .unwrap();
+        assert_eq!(serde_json::json!([{ "id": 7 }]), result);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,4 @@
+//!
+
+/// Gauge of prompts waiting on the user to answer.
+pub const METRIC_WAITING_PROMPTS: &str = "apid_input_prompts_waiting";
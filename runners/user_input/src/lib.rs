@@ -7,6 +7,7 @@
     clippy::implicit_return,
     clippy::shadow_reuse,
     clippy::match_ref_pats,
+    clippy::separated_literal_suffix,
 
     // Would like to turn on (Configured to 50?)
     clippy::too_many_lines,
@@ -20,6 +21,7 @@
 
 //!
 
+mod constants;
 pub mod error;
 
 extern crate alloc;
@@ -69,7 +71,10 @@ impl UserInput {
             rx
         };
 
-        let value = rx.recv_timeout(Duration::from_secs(60))?;
+        metrics::increment_gauge!(constants::METRIC_WAITING_PROMPTS, 1.0_f64);
+        let value = rx.recv_timeout(Duration::from_secs(60));
+        metrics::decrement_gauge!(constants::METRIC_WAITING_PROMPTS, 1.0_f64);
+        let value = value?;
 
         {
             let mut signals = self
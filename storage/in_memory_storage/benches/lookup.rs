@@ -0,0 +1,127 @@
+//! Looking up a large connector the way `Engine::run` does, from one thread and from many.
+//!
+//! `clone_under_mutex` is how lookups used to work, a deep copy of the tree while holding
+//! the one lock around all of the repos. `snapshot` is the lookup the engine is given now.
+
+#![allow(clippy::restriction, clippy::pedantic)]
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use core_entities::service::{Operation, Parameter, VersionedServiceTree};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use execution_engine::services::EngineLookup;
+use in_memory_storage::{
+    published::{Snapshot, SnapshotLookup},
+    repo::{InMemoryRepository, Repository},
+};
+
+const THREADS: usize = 8;
+const LOOKUPS_PER_THREAD: usize = 10;
+
+/// Roughly the size of a parsed Stripe or GitHub spec.
+fn large_spec(operations: usize) -> VersionedServiceTree {
+    let mut service = VersionedServiceTree::new();
+    let v1 = service.mut_v1();
+    v1.manifest.mut_or_insert_default().mut_v2().mut_swagger();
+
+    let api = v1.commonApi.mut_or_insert_default();
+    api.set_basePath("https://api.example.com".into());
+    for index in 0..operations {
+        let mut operation = Operation::new();
+        operation.id = format!("operation{index}");
+        operation.path = format!("/v1/resources/{index}/{{id}}");
+        operation.description = "Lorem ipsum dolor sit amet ".repeat(8);
+        for param in 0..6 {
+            let mut parameter = Parameter::new();
+            parameter.name = format!("param{param}");
+            parameter.description = "A parameter".into();
+            operation.parameter.push(parameter);
+        }
+        api.operations.insert(operation.id.clone(), operation);
+    }
+
+    service
+}
+
+fn old_lookup(service: &VersionedServiceTree) -> Mutex<BTreeMap<String, VersionedServiceTree>> {
+    Mutex::new(BTreeMap::from([("stripe".to_owned(), service.clone())]))
+}
+
+fn snapshot_lookup(service: &VersionedServiceTree) -> SnapshotLookup {
+    let mut services = InMemoryRepository::new();
+    services.save("stripe".into(), service.clone()).unwrap();
+    let credentials = InMemoryRepository::new();
+
+    let lookup = SnapshotLookup::new();
+    lookup.publish(Snapshot::capture(&services, &credentials));
+    lookup
+}
+
+fn single(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+
+    for operations in [100, 1000] {
+        let service = large_spec(operations);
+
+        let old = old_lookup(&service);
+        group.bench_with_input(
+            BenchmarkId::new("clone_under_mutex", operations),
+            &old,
+            |b, old| b.iter(|| black_box(old.lock().unwrap().get("stripe").cloned())),
+        );
+
+        let lookup = snapshot_lookup(&service);
+        group.bench_with_input(
+            BenchmarkId::new("snapshot", operations),
+            &lookup,
+            |b, lookup| b.iter(|| black_box(lookup.get_service("stripe"))),
+        );
+    }
+
+    group.finish();
+}
+
+fn contended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup_contended");
+    group.sample_size(20);
+    let service = large_spec(1000);
+
+    let old = Arc::new(old_lookup(&service));
+    group.bench_function(BenchmarkId::new("clone_under_mutex", THREADS), |b| {
+        b.iter(|| {
+            thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|| {
+                        for _ in 0..LOOKUPS_PER_THREAD {
+                            black_box(old.lock().unwrap().get("stripe").cloned());
+                        }
+                    });
+                }
+            });
+        })
+    });
+
+    let lookup = Arc::new(snapshot_lookup(&service));
+    group.bench_function(BenchmarkId::new("snapshot", THREADS), |b| {
+        b.iter(|| {
+            thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|| {
+                        for _ in 0..LOOKUPS_PER_THREAD {
+                            black_box(lookup.get_service("stripe"));
+                        }
+                    });
+                }
+            });
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, single, contended);
+criterion_main!(benches);
@@ -20,13 +20,18 @@
 //!
 
 pub mod error;
+pub mod published;
 pub mod repo;
 
+extern crate alloc;
+use alloc::sync::Arc;
+
 use core_entities::service::VersionedServiceTree;
 use credential_entities::credentials::Authentication;
 use execution_engine::services::EngineLookup;
+use published::{Snapshot, SnapshotLookup};
 use repo::Repository;
-use service_loader::LoaderOutput;
+use service_loader::{credentials::Profiles, dependencies::DependencyGraph, LoaderOutput};
 
 ///
 #[non_exhaustive]
@@ -34,8 +39,14 @@ pub struct OperationRepos {
     ///
     pub services: Box<dyn Repository<VersionedServiceTree> + Send + Sync>,
 
-    ///
-    pub credentials: Box<dyn Repository<Authentication> + Send + Sync>,
+    /// Every credential profile of a connector, keyed by the connector.
+    pub credentials: Box<dyn Repository<Profiles> + Send + Sync>,
+
+    /// Kept up to date as services are saved and removed.
+    pub dependencies: DependencyGraph,
+
+    /// Shared with the engine, holds whatever was last published with [`OperationRepos::publish`].
+    pub lookup: Arc<SnapshotLookup>,
 }
 
 impl OperationRepos {
@@ -44,11 +55,43 @@ impl OperationRepos {
     #[must_use]
     pub fn new(
         services: Box<dyn Repository<VersionedServiceTree> + Send + Sync>,
-        credentials: Box<dyn Repository<Authentication> + Send + Sync>,
+        credentials: Box<dyn Repository<Profiles> + Send + Sync>,
     ) -> Self {
         Self {
             services,
             credentials,
+            dependencies: DependencyGraph::new(),
+            lookup: Arc::new(SnapshotLookup::new()),
+        }
+    }
+
+    /// Swaps in everything saved or removed since the last publish all at once, so a run
+    /// never sees a service that's only halfway through reloading.
+    #[inline]
+    pub fn publish(&self) {
+        self.lookup
+            .publish(Snapshot::capture(&*self.services, &*self.credentials));
+    }
+
+    /// Drops a connector and its credentials, i.e. once its directory is gone.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn remove(&mut self, id: &str) -> error::Result<()> {
+        self.services.remove(id)?;
+        self.credentials.remove(id)?;
+
+        self.dependencies.remove(id);
+        self.revalidate_dependents(id);
+        Ok(())
+    }
+
+    /// Rechecks the references of everything that depends on a service that just changed.
+    fn revalidate_dependents(&mut self, id: &str) {
+        let services = &self.services;
+        for dependent in self.dependencies.dependents(id) {
+            self.dependencies
+                .revalidate(&dependent, |name| services.get(name));
         }
     }
 }
@@ -60,7 +103,12 @@ impl LoaderOutput for OperationRepos {
         id: &str,
         service: VersionedServiceTree,
     ) -> service_loader::error::Result<()> {
+        self.dependencies.update(id, &service);
         self.services.save(id.to_owned(), service)?;
+
+        let services = &self.services;
+        self.dependencies.revalidate(id, |name| services.get(name));
+        self.revalidate_dependents(id);
         Ok(())
     }
 
@@ -68,7 +116,7 @@ impl LoaderOutput for OperationRepos {
     fn handle_credentials(
         &mut self,
         id: &str,
-        credentials: Authentication,
+        credentials: Profiles,
     ) -> service_loader::error::Result<()> {
         self.credentials.save(id.to_owned(), credentials)?;
         Ok(())
@@ -77,12 +125,23 @@ impl LoaderOutput for OperationRepos {
 
 impl EngineLookup for OperationRepos {
     #[inline]
-    fn get_service(&self, id: &str) -> Option<VersionedServiceTree> {
+    fn get_service(&self, id: &str) -> Option<Arc<VersionedServiceTree>> {
         self.services.get(id)
     }
 
     #[inline]
-    fn get_credentials(&self, id: &str) -> Option<Authentication> {
-        self.credentials.get(id)
+    fn get_credentials(&self, id: &str, profile: Option<&str>) -> Option<Arc<Authentication>> {
+        let group = self
+            .services
+            .get(id)
+            .is_some_and(|service| published::group_credentials(&service));
+
+        self.credentials.get(id)?.resolve(profile, group)
+    }
+
+    /// Changes as things are saved, runs pin what's published through [`SnapshotLookup`].
+    #[inline]
+    fn pin(&self) -> Option<Arc<dyn EngineLookup + Send + Sync>> {
+        None
     }
 }
@@ -0,0 +1,260 @@
+//! Snapshots of what has been loaded, published for the engine to read.
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc};
+
+use arc_swap::ArcSwap;
+use core_entities::service::VersionedServiceTree;
+use credential_entities::credentials::Authentication;
+use execution_engine::services::EngineLookup;
+use service_loader::credentials::{self, Profiles};
+
+use crate::repo::Repository;
+
+/// Everything loaded at one point in time, never changed once published.
+#[derive(Default)]
+#[non_exhaustive]
+pub struct Snapshot {
+    ///
+    pub services: BTreeMap<String, Arc<VersionedServiceTree>>,
+
+    ///
+    pub credentials: BTreeMap<String, Arc<Profiles>>,
+}
+
+impl Snapshot {
+    /// Only the `Arc`s are copied, not what they point to.
+    #[must_use]
+    #[inline]
+    pub fn capture(
+        services: &dyn Repository<VersionedServiceTree>,
+        credentials: &dyn Repository<Profiles>,
+    ) -> Self {
+        Self {
+            services: services
+                .list()
+                .into_iter()
+                .filter_map(|id| services.get(&id).map(|service| (id, service)))
+                .collect(),
+            credentials: credentials
+                .list()
+                .into_iter()
+                .filter_map(|id| credentials.get(&id).map(|creds| (id, creds)))
+                .collect(),
+        }
+    }
+}
+
+impl EngineLookup for Snapshot {
+    #[inline]
+    fn get_service(&self, id: &str) -> Option<Arc<VersionedServiceTree>> {
+        self.services.get(id).map(Arc::clone)
+    }
+
+    #[inline]
+    fn get_credentials(&self, id: &str, profile: Option<&str>) -> Option<Arc<Authentication>> {
+        let group = self
+            .services
+            .get(id)
+            .is_some_and(|service| group_credentials(service));
+
+        self.credentials.get(id)?.resolve(profile, group)
+    }
+
+    /// Never changes once published.
+    #[inline]
+    fn pin(&self) -> Option<Arc<dyn EngineLookup + Send + Sync>> {
+        None
+    }
+}
+
+/// The lookup handed to the engine. Runs read the current snapshot without taking a lock
+/// and a reload swaps in a new one. Each run pins the snapshot it started with, so it and
+/// everything it runs keep seeing the same services and credentials until it's done.
+#[derive(Default)]
+pub struct SnapshotLookup {
+    ///
+    current: ArcSwap<Snapshot>,
+}
+
+impl SnapshotLookup {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    #[inline]
+    pub fn publish(&self, snapshot: Snapshot) {
+        self.current.store(Arc::new(snapshot));
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn current(&self) -> Arc<Snapshot> {
+        self.current.load_full()
+    }
+}
+
+impl EngineLookup for SnapshotLookup {
+    #[inline]
+    fn get_service(&self, id: &str) -> Option<Arc<VersionedServiceTree>> {
+        self.current.load().get_service(id)
+    }
+
+    #[inline]
+    fn get_credentials(&self, id: &str, profile: Option<&str>) -> Option<Arc<Authentication>> {
+        self.current.load().get_credentials(id, profile)
+    }
+
+    #[inline]
+    fn pin(&self) -> Option<Arc<dyn EngineLookup + Send + Sync>> {
+        Some(self.current())
+    }
+}
+
+/// Whether the service's OAuth config shares one client between every profile.
+#[must_use]
+#[inline]
+pub fn group_credentials(service: &VersionedServiceTree) -> bool {
+    let auth = &service.v1().manifest.v2().swagger().auth;
+    auth.has_oauthConfig() && credentials::group_enabled(&auth.oauthConfig().enableGroupCredentials)
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use core_entities::service::SwaggerService;
+    use execution_engine::services::{
+        DataConnectionRunner, DataConnectorBundle, EngineInputContext,
+    };
+    use std::sync::Mutex;
+
+    fn snapshot(id: &str, service: &Arc<VersionedServiceTree>) -> Snapshot {
+        Snapshot {
+            services: BTreeMap::from([(id.to_owned(), Arc::clone(service))]),
+            credentials: BTreeMap::new(),
+        }
+    }
+
+    /// `api` at `base_url`, with a single profile holding `token`.
+    fn version(base_url: &str, token: &str) -> Snapshot {
+        let mut service = VersionedServiceTree::new();
+        let v1 = service.mut_v1();
+        v1.manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .set_swagger(SwaggerService::new());
+        v1.commonApi
+            .mut_or_insert_default()
+            .set_basePath(base_url.into());
+
+        let mut credentials = Authentication::new();
+        credentials.mut_header().value = token.into();
+
+        Snapshot {
+            services: BTreeMap::from([("api".to_owned(), Arc::new(service))]),
+            credentials: BTreeMap::from([(
+                "api".to_owned(),
+                Arc::new(Profiles::single(credentials)),
+            )]),
+        }
+    }
+
+    /// Publishes a reload in the middle of the first call, and keeps the context a nested
+    /// run would have been given.
+    struct Connector {
+        lookup: Arc<SnapshotLookup>,
+        reload: Mutex<Option<Snapshot>>,
+        nested: Arc<Mutex<Option<EngineInputContext>>>,
+    }
+
+    impl DataConnectionRunner for Connector {
+        fn run(
+            &self,
+            _: &str,
+            _: &str,
+            bundle: &DataConnectorBundle,
+            _: serde_json::Value,
+            _: serde_json::Value,
+            ctx: &EngineInputContext,
+        ) -> execution_engine::error::Result<serde_json::Value> {
+            if let Some(reload) = self.reload.lock().unwrap().take() {
+                self.lookup.publish(reload);
+                *self.nested.lock().unwrap() = Some(ctx.child(false));
+            }
+
+            Ok(serde_json::json!({
+                "baseUrl": bundle.base_url(),
+                "token": bundle.creds.map(|creds| creds.header().value.clone()),
+            }))
+        }
+    }
+
+    #[test]
+    fn test_readers_keep_their_snapshot() {
+        let lookup = SnapshotLookup::new();
+        let old = Arc::new(VersionedServiceTree::new());
+        let new = Arc::new(VersionedServiceTree::new());
+
+        lookup.publish(snapshot("github", &old));
+        let reader = lookup.current();
+
+        lookup.publish(snapshot("gitlab", &new));
+
+        // A run that started before the reload still sees what it started with
+        assert_eq!(vec!["github"], reader.services.keys().collect::<Vec<_>>());
+        assert!(Arc::ptr_eq(&old, &reader.services["github"]));
+
+        // Anything after it sees the new one
+        assert!(lookup.get_service("github").is_none());
+        assert!(Arc::ptr_eq(&new, &lookup.get_service("gitlab").unwrap()));
+        assert_eq!(
+            vec!["gitlab"],
+            lookup.current().services.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_runs_see_one_snapshot_throughout() {
+        let lookup = Arc::new(SnapshotLookup::new());
+        lookup.publish(version("https://v1.example.com", "one"));
+
+        let nested = Arc::new(Mutex::new(None));
+        let mut engine = execution_engine::Engine::new(Arc::clone(&lookup) as _);
+        engine.register_connector(Box::new(Connector {
+            lookup: Arc::clone(&lookup),
+            reload: Mutex::new(Some(version("https://v2.example.com", "two"))),
+            nested: Arc::clone(&nested),
+        }));
+
+        let run = |ctx: &EngineInputContext| {
+            engine
+                .run(
+                    "api.list",
+                    serde_json::json!({}),
+                    serde_json::json!({}),
+                    ctx,
+                )
+                .unwrap()
+        };
+        let v1 = serde_json::json!([{ "baseUrl": "https://v1.example.com", "token": "one" }]);
+        let v2 = serde_json::json!([{ "baseUrl": "https://v2.example.com", "token": "two" }]);
+
+        // The reload lands during this run, which carries on with what it started with
+        let top = EngineInputContext::new(None, "pinned".into(), false);
+        assert_eq!(v1, run(&top));
+
+        // And so does anything it runs after the reload
+        let nested = nested.lock().unwrap().take().unwrap();
+        assert_eq!(v1, run(&nested));
+
+        // A new run picks up the reload
+        assert_eq!(v2, run(&top));
+    }
+}
@@ -3,15 +3,15 @@
 use super::error;
 
 extern crate alloc;
-use alloc::collections::BTreeMap;
+use alloc::{collections::BTreeMap, sync::Arc};
 
 ///
 pub trait Repository<V> {
     ///
     fn list(&self) -> Vec<String>;
 
-    ///
-    fn get(&self, id: &str) -> Option<V>;
+    /// Shared rather than cloned, a reload replaces the value instead of changing it.
+    fn get(&self, id: &str) -> Option<Arc<V>>;
 
     ///
     /// # Errors
@@ -24,7 +24,7 @@ pub trait Repository<V> {
 /// This below could be a different crate...
 pub struct InMemoryRepository<V> {
     ///
-    storage: BTreeMap<String, V>,
+    storage: BTreeMap<String, Arc<V>>,
 }
 
 impl<V> InMemoryRepository<V> {
@@ -46,7 +46,7 @@ impl<V> Default for InMemoryRepository<V> {
     }
 }
 
-impl<V: Clone> Repository<V> for InMemoryRepository<V> {
+impl<V> Repository<V> for InMemoryRepository<V> {
     #[inline]
     fn list(&self) -> Vec<String> {
         self.storage
@@ -56,13 +56,13 @@ impl<V: Clone> Repository<V> for InMemoryRepository<V> {
     }
 
     #[inline]
-    fn get(&self, id: &str) -> Option<V> {
-        self.storage.get(id).cloned()
+    fn get(&self, id: &str) -> Option<Arc<V>> {
+        self.storage.get(id).map(Arc::clone)
     }
 
     #[inline]
     fn save(&mut self, id: String, value: V) -> Result<(), error::OperationRepo> {
-        self.storage.insert(id, value);
+        self.storage.insert(id, Arc::new(value));
         Ok(())
     }
 
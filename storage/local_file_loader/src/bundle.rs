@@ -0,0 +1,285 @@
+//! Connectors shipped as a single `.zip` or `.tar.gz` file.
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use std::{
+    fs::File,
+    io::{self, Read as _},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use service_loader::Fetcher;
+
+/// Found at the root of every connector.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+///
+const ZIP_EXTENSION: &str = ".zip";
+
+///
+const TAR_GZ_EXTENSIONS: [&str; 2] = [".tar.gz", ".tgz"];
+
+/// Reads the whole bundle up front so every fetch is served from memory.
+#[derive(Clone, Debug)]
+pub struct ArchiveFetcher {
+    /// Keyed by the path relative to the connector root, without a leading `./`.
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ArchiveFetcher {
+    /// Bundles are allowed to wrap the connector in a single top level directory,
+    /// i.e. what `zip -r github.zip github/` produces.
+    ///
+    /// # Errors
+    /// When the bundle can't be read or isn't a format that's supported.
+    #[inline]
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        let files = if name.ends_with(ZIP_EXTENSION) {
+            read_zip(File::open(path)?)?
+        } else if TAR_GZ_EXTENSIONS
+            .iter()
+            .any(|extension| name.ends_with(extension))
+        {
+            read_tar(GzDecoder::new(File::open(path)?))?
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a .zip or .tar.gz bundle", path.display()),
+            ));
+        };
+
+        Ok(Self {
+            files: strip_root(files),
+        })
+    }
+
+    /// Every file in the bundle, relative to the connector root.
+    #[inline]
+    pub fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.files
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_slice()))
+    }
+
+    /// Whether the file name looks like a bundle this fetcher can open.
+    #[must_use]
+    #[inline]
+    pub fn is_bundle(path: &Path) -> bool {
+        Self::service_name(path).is_some()
+    }
+
+    /// The bundle for a connector under `root`, if it's packaged as one.
+    #[must_use]
+    #[inline]
+    pub fn locate(root: &Path, name: &str) -> Option<PathBuf> {
+        core::iter::once(ZIP_EXTENSION)
+            .chain(TAR_GZ_EXTENSIONS)
+            .map(|extension| root.join(format!("{name}{extension}")))
+            .find(|path| path.is_file())
+    }
+
+    /// The name of the connector in a bundle, i.e. `github` for `github.tar.gz`.
+    #[must_use]
+    #[inline]
+    pub fn service_name(path: &Path) -> Option<&str> {
+        let name = path.file_name()?.to_str()?;
+
+        core::iter::once(ZIP_EXTENSION)
+            .chain(TAR_GZ_EXTENSIONS)
+            .find_map(|extension| name.strip_suffix(extension))
+            .filter(|service| !service.is_empty())
+    }
+}
+
+impl Fetcher<io::Cursor<Vec<u8>>> for ArchiveFetcher {
+    #[inline]
+    fn fetch(&self, location: &str) -> io::Result<io::Cursor<Vec<u8>>> {
+        self.files
+            .get(&normalize(location))
+            .cloned()
+            .map(io::Cursor::new)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{location} is not in the bundle"),
+                )
+            })
+    }
+}
+
+///
+fn read_zip(file: File) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+    let mut files = BTreeMap::new();
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(io::Error::other)?;
+        if entry.is_dir() {
+            continue;
+        }
+
+        let mut content = vec![];
+        entry.read_to_end(&mut content)?;
+        files.insert(normalize(entry.name()), content);
+    }
+
+    Ok(files)
+}
+
+///
+fn read_tar<R: io::Read>(reader: R) -> io::Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut content = vec![];
+        entry.read_to_end(&mut content)?;
+        files.insert(normalize(&name), content);
+    }
+
+    Ok(files)
+}
+
+/// Treats a single top level directory holding the manifest as the connector root.
+fn strip_root(files: BTreeMap<String, Vec<u8>>) -> BTreeMap<String, Vec<u8>> {
+    if files.contains_key(MANIFEST_FILE) {
+        return files;
+    }
+
+    let roots: Vec<_> = files
+        .keys()
+        .filter_map(|name| name.strip_suffix(MANIFEST_FILE))
+        .filter(|prefix| prefix.ends_with('/') && prefix.matches('/').count() == 1)
+        .map(ToOwned::to_owned)
+        .collect();
+
+    match <[String; 1]>::try_from(roots) {
+        Ok([root]) => files
+            .into_iter()
+            .filter_map(|(name, content)| {
+                name.strip_prefix(root.as_str())
+                    .map(|name| (name.to_owned(), content))
+            })
+            .collect(),
+        Err(_) => files,
+    }
+}
+
+/// Paths in the manifest are written as `./spec.yaml` while bundles list them as `spec.yaml`.
+fn normalize(location: &str) -> String {
+    location
+        .split('/')
+        .filter(|&part| !part.is_empty() && part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::{fs, io::Write as _};
+
+    const FILES: [(&str, &str); 3] = [
+        (MANIFEST_FILE, "{\"spec\":\"./spec.yaml\"}"),
+        ("spec.yaml", "openapi: 3.0.0"),
+        ("scripts/list.py", "pass"),
+    ];
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "local_file_loader_bundle_{name}_{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn assert_round_trip(bundle: &Path) {
+        let fetcher = ArchiveFetcher::open(bundle).unwrap();
+
+        let names: Vec<_> = fetcher.files().map(|(name, _)| name).collect();
+        assert_eq!(vec![MANIFEST_FILE, "scripts/list.py", "spec.yaml"], names);
+
+        for (name, content) in FILES {
+            let mut fetched = String::new();
+            fetcher
+                .fetch(&format!("./{name}"))
+                .unwrap()
+                .read_to_string(&mut fetched)
+                .unwrap();
+            assert_eq!(content, fetched);
+        }
+
+        let err = fetcher.fetch("./missing.yaml").unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+    }
+
+    // What `zip -r github.zip github/` produces
+    #[test]
+    fn test_zip_round_trip() {
+        let dir = temp_dir("zip");
+        let path = dir.join("github.zip");
+
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.add_directory("github/", zip::write::FileOptions::default())
+            .unwrap();
+        for (name, content) in FILES {
+            zip.start_file(format!("github/{name}"), zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        assert_eq!(Some("github"), ArchiveFetcher::service_name(&path));
+        assert_eq!(Some(path.clone()), ArchiveFetcher::locate(&dir, "github"));
+        assert_round_trip(&path);
+    }
+
+    // What `tar czf github.tar.gz .` produces from within the connector
+    #[test]
+    fn test_tar_gz_round_trip() {
+        let dir = temp_dir("tar");
+        let path = dir.join("github.tar.gz");
+
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::default(),
+        ));
+        for (name, content) in FILES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("./{name}"), content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(Some("github"), ArchiveFetcher::service_name(&path));
+        assert_round_trip(&path);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!("spec.yaml", normalize("./spec.yaml"));
+        assert_eq!("scripts/list.py", normalize("./scripts//list.py"));
+        assert_eq!("spec.yaml", normalize("spec.yaml"));
+    }
+}
@@ -7,17 +7,26 @@
     clippy::implicit_return,
     clippy::shadow_reuse,
     clippy::match_ref_pats,
+    clippy::single_call_fn,
 
     // Would like to turn on (Configured to 50?)
     clippy::too_many_lines,
-    clippy::absolute_paths
+    clippy::absolute_paths,
+    clippy::question_mark_used
 )]
 
 //!
 
-use std::{fs::File, path::PathBuf};
+pub mod bundle;
+pub mod packages;
 
-use service_loader::Fetcher;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use service_loader::{cache::Cache, Fetcher};
 use service_writer::Storage;
 
 ///
@@ -52,3 +61,43 @@ impl Storage<File> for LocalFileFetcher {
         File::create(file)
     }
 }
+
+/// Compiled services kept as files in a directory, one per key.
+pub struct LocalCache {
+    ///
+    root: PathBuf,
+
+    /// Keeps temporary files apart when the same key is written from a few threads at once.
+    writes: AtomicUsize,
+}
+
+impl From<PathBuf> for LocalCache {
+    ///
+    #[inline]
+    fn from(value: PathBuf) -> Self {
+        Self {
+            root: value,
+            writes: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Cache for LocalCache {
+    #[inline]
+    fn get(&self, key: &str) -> Option<Vec<u8>> {
+        fs::read(self.root.join(key)).ok()
+    }
+
+    /// Written to the side and then renamed so a reader never sees half an entry.
+    #[inline]
+    fn put(&self, key: &str, value: &[u8]) -> std::io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        let temp = self
+            .root
+            .join(format!(".{key}.{}.{write}", std::process::id()));
+        fs::write(&temp, value)?;
+        fs::rename(temp, self.root.join(key))
+    }
+}
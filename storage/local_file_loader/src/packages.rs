@@ -0,0 +1,361 @@
+//! Connectors packaged along with who maintains them and which version they are, and the
+//! registry they're installed into so that several versions can sit side by side.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read as _},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use service_loader::{identity::ConnectorId, Fetcher as _};
+
+use crate::bundle::{ArchiveFetcher, MANIFEST_FILE};
+
+/// At the root of every package, the `ConnectorId` it installs as.
+pub const PACKAGE_FILE: &str = "package.json";
+
+/// Writes a connector directory out as a `.tar.gz` package, hidden files are left out.
+///
+/// # Errors
+/// When the id isn't fully versioned, the directory isn't a connector or the package can't be written.
+#[inline]
+pub fn pack(dir: &Path, id: &ConnectorId, output: &Path) -> io::Result<()> {
+    if id.install_path().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{id} needs a maintainer, name and tag to be packaged"),
+        ));
+    }
+
+    if !dir.join(MANIFEST_FILE).is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} has no {MANIFEST_FILE}", dir.display()),
+        ));
+    }
+
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        File::create(output)?,
+        Compression::default(),
+    ));
+
+    let metadata = serde_json::to_vec_pretty(id)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(u64::try_from(metadata.len()).unwrap_or(u64::MAX));
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, PACKAGE_FILE, metadata.as_slice())?;
+
+    for file in connector_files(dir, Path::new(""))? {
+        builder.append_path_with_name(dir.join(&file), &file)?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Every file in a connector relative to its root, in a stable order so packages are reproducible.
+fn connector_files(root: &Path, relative: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries: Vec<_> = fs::read_dir(root.join(relative))?.collect::<io::Result<_>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+
+    let mut files = vec![];
+    for entry in entries {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.')
+            || (relative.as_os_str().is_empty() && name == PACKAGE_FILE)
+        {
+            continue;
+        }
+
+        let path = relative.join(&name);
+        if entry.file_type()?.is_dir() {
+            files.extend(connector_files(root, &path)?);
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Installed connectors, kept as `<root>/<maintainer>/<name>/<tag>`.
+#[derive(Clone, Debug)]
+pub struct Registry {
+    ///
+    root: PathBuf,
+}
+
+impl From<PathBuf> for Registry {
+    #[inline]
+    fn from(value: PathBuf) -> Self {
+        Self { root: value }
+    }
+}
+
+impl Registry {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Unpacks a package next to whatever other versions are installed. The files are
+    /// written to the side first so a watcher never loads half a connector.
+    ///
+    /// # Errors
+    /// When the package is invalid, or the same version is installed and `force` isn't set.
+    #[inline]
+    pub fn install(&self, package: &Path, force: bool) -> io::Result<ConnectorId> {
+        let bundle = ArchiveFetcher::open(package)?;
+
+        let mut metadata = vec![];
+        bundle.fetch(PACKAGE_FILE)?.read_to_end(&mut metadata)?;
+        let id: ConnectorId = serde_json::from_slice(&metadata)?;
+
+        let target = id
+            .install_path()
+            .map(|path| self.root.join(path))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{id} needs a maintainer, name and tag to be installed"),
+                )
+            })?;
+
+        if target.exists() && !force {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{id} is already installed"),
+            ));
+        }
+
+        let outside = bundle.files().find(|&(name, _)| {
+            !Path::new(name)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        });
+        if let Some((name, _)) = outside {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{name} is outside of the package"),
+            ));
+        }
+
+        let parent = target.parent().unwrap_or(&self.root);
+        fs::create_dir_all(parent)?;
+        let staging = parent.join(format!(".{}.{}", id.tag, std::process::id()));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+
+        for (name, content) in bundle.files() {
+            let path = staging.join(name);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, content)?;
+        }
+
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(staging, target)?;
+
+        Ok(id)
+    }
+
+    /// Every installed version along with where it lives, sorted by id.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn installed(&self) -> io::Result<Vec<(ConnectorId, PathBuf)>> {
+        if !self.root.is_dir() {
+            return Ok(vec![]);
+        }
+
+        let mut installed = vec![];
+        for (maintainer, maintainer_path) in directories(&self.root)? {
+            for (name, name_path) in directories(&maintainer_path)? {
+                for (tag, path) in directories(&name_path)? {
+                    let id = ConnectorId::new(maintainer.clone(), name.clone(), tag);
+                    if id.is_valid() {
+                        installed.push((id, path));
+                    }
+                }
+            }
+        }
+
+        installed.sort();
+        Ok(installed)
+    }
+
+    /// Installed versions of a connector, `id` is matched by name and by maintainer when it has one.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn versions(&self, id: &ConnectorId) -> io::Result<Vec<ConnectorId>> {
+        Ok(self
+            .installed()?
+            .into_iter()
+            .map(|(installed, _)| installed)
+            .filter(|installed| {
+                installed.name == id.name
+                    && (id.maintainer.is_empty() || installed.maintainer == id.maintainer)
+            })
+            .collect())
+    }
+
+    /// Where exactly this version is installed, if it is.
+    #[must_use]
+    #[inline]
+    pub fn locate(&self, id: &ConnectorId) -> Option<PathBuf> {
+        id.install_path()
+            .map(|path| self.root.join(path))
+            .filter(|path| path.is_dir())
+    }
+
+    /// The installed connector a path within the registry belongs to, and the file within it.
+    #[must_use]
+    #[inline]
+    pub fn connector_id(&self, path: &Path) -> Option<(ConnectorId, PathBuf)> {
+        let mut components = path.strip_prefix(&self.root).ok()?.components();
+        let mut next = || match components.next() {
+            Some(Component::Normal(part)) => part.to_str().map(ToOwned::to_owned),
+            _ => None,
+        };
+
+        let id = ConnectorId::new(next()?, next()?, next()?);
+        id.is_valid()
+            .then(|| (id, components.as_path().to_path_buf()))
+    }
+}
+
+/// Visible directories within `path` along with their names.
+fn directories(path: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut directories = vec![];
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+
+        if !name.starts_with('.') && entry.file_type()?.is_dir() {
+            directories.push((name, entry.path()));
+        }
+    }
+
+    Ok(directories)
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use std::io::Write as _;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("local_file_loader_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn id(tag: &str) -> ConnectorId {
+        ConnectorId::new("acme".into(), "github".into(), tag.into())
+    }
+
+    /// Packs a connector whose manifest is just `manifest`.
+    fn package(dir: &Path, id: &ConnectorId, manifest: &str) -> PathBuf {
+        let connector = dir.join(format!("connector-{}", id.tag));
+        fs::create_dir_all(&connector).unwrap();
+        fs::write(connector.join(MANIFEST_FILE), manifest).unwrap();
+
+        let output = dir.join(format!("github-{}.tar.gz", id.tag));
+        pack(&connector, id, &output).unwrap();
+        output
+    }
+
+    #[test]
+    fn test_rejects_entries_outside_the_package() {
+        let dir = temp_dir("outside");
+        let registry = Registry::from(dir.join("registry"));
+
+        let output = dir.join("github.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&output).unwrap());
+        for (name, content) in [
+            (PACKAGE_FILE, serde_json::to_vec(&id("1.0.0")).unwrap()),
+            (MANIFEST_FILE, b"{}".to_vec()),
+            ("../escape.txt", b"gotcha".to_vec()),
+        ] {
+            zip.start_file(name, zip::write::FileOptions::default())
+                .unwrap();
+            zip.write_all(&content).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let err = registry.install(&output, false).unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert!(err.to_string().contains("../escape.txt"));
+
+        assert!(!dir.join("registry/acme/github/escape.txt").exists());
+        assert_eq!(None, registry.locate(&id("1.0.0")));
+    }
+
+    #[test]
+    fn test_reinstall_needs_force() {
+        let dir = temp_dir("reinstall");
+        let registry = Registry::from(dir.join("registry"));
+
+        let first = package(&dir, &id("1.0.0"), "{\"version\":1}");
+        assert_eq!(id("1.0.0"), registry.install(&first, false).unwrap());
+
+        fs::remove_dir_all(dir.join("connector-1.0.0")).unwrap();
+        let second = package(&dir, &id("1.0.0"), "{\"version\":2}");
+        let err = registry.install(&second, false).unwrap_err();
+        assert_eq!(io::ErrorKind::AlreadyExists, err.kind());
+
+        let installed = registry.locate(&id("1.0.0")).unwrap();
+        let manifest = || fs::read_to_string(installed.join(MANIFEST_FILE)).unwrap();
+        assert_eq!("{\"version\":1}", manifest());
+
+        registry.install(&second, true).unwrap();
+        assert_eq!("{\"version\":2}", manifest());
+    }
+
+    #[test]
+    fn test_versions_side_by_side() {
+        let dir = temp_dir("versions");
+        let registry = Registry::from(dir.join("registry"));
+
+        for tag in ["1.0.0", "2.0.0"] {
+            let output = package(&dir, &id(tag), &format!("{{\"tag\":\"{tag}\"}}"));
+            registry.install(&output, false).unwrap();
+        }
+
+        let any = ConnectorId::new(String::new(), "github".into(), String::new());
+        assert_eq!(
+            vec![id("1.0.0"), id("2.0.0")],
+            registry.versions(&any).unwrap()
+        );
+
+        for tag in ["1.0.0", "2.0.0"] {
+            let path = registry.locate(&id(tag)).unwrap();
+            assert_eq!(dir.join("registry/acme/github").join(tag), path);
+            assert_eq!(
+                format!("{{\"tag\":\"{tag}\"}}"),
+                fs::read_to_string(path.join(MANIFEST_FILE)).unwrap()
+            );
+
+            let file = path.join(MANIFEST_FILE);
+            assert_eq!(
+                Some((id(tag), PathBuf::from(MANIFEST_FILE))),
+                registry.connector_id(&file)
+            );
+        }
+    }
+}
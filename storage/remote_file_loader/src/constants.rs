@@ -0,0 +1,10 @@
+//!
+
+/// Long enough for a large spec on a slow connection.
+pub const REQUEST_TIMEOUT: core::time::Duration = core::time::Duration::from_secs(30);
+
+///
+pub const BODY_EXTENSION: &str = "body";
+
+///
+pub const METADATA_EXTENSION: &str = "json";
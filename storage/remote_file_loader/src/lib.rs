@@ -0,0 +1,489 @@
+#![warn(clippy::restriction, clippy::pedantic)]
+#![allow(
+    clippy::blanket_clippy_restriction_lints,
+    clippy::mod_module_files,
+    clippy::self_named_module_files,
+
+    clippy::implicit_return,
+    clippy::shadow_reuse,
+    clippy::match_ref_pats,
+
+    // Would like to turn on (Configured to 50?)
+    clippy::too_many_lines,
+    clippy::question_mark_used,
+    clippy::needless_borrowed_reference,
+    clippy::absolute_paths,
+    clippy::ref_patterns,
+    clippy::single_call_fn
+)]
+
+//! Fetches documents over HTTP(S), i.e. components shared between specs that are
+//! referenced with a `$ref` to a URL.
+
+mod constants;
+
+use std::{fs, io, path::PathBuf};
+
+use reqwest::{
+    blocking::{Client, Response},
+    header, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use service_loader::Fetcher;
+use sha2::{Digest as _, Sha256};
+
+/// What's needed to ask the server whether a cached copy is still good.
+#[derive(Default, Serialize, Deserialize)]
+struct Validators {
+    ///
+    url: String,
+
+    ///
+    etag: Option<String>,
+
+    ///
+    last_modified: Option<String>,
+}
+
+///
+struct Cached {
+    ///
+    validators: Validators,
+
+    ///
+    body: Vec<u8>,
+}
+
+/// Copies of everything fetched are kept when there's a cache directory. They're revalidated
+/// with the server each time and used as is when the server can't be reached.
+pub struct HttpFetcher {
+    ///
+    client: Client,
+
+    ///
+    cache: Option<PathBuf>,
+
+    /// Only ever reads from the cache.
+    offline: bool,
+}
+
+impl HttpFetcher {
+    ///
+    /// # Errors
+    /// When the HTTP client can't be built, i.e. no TLS backend is available.
+    #[inline]
+    pub fn new() -> io::Result<Self> {
+        let client = Client::builder()
+            .timeout(constants::REQUEST_TIMEOUT)
+            .build()
+            .map_err(io::Error::other)?;
+
+        Ok(Self {
+            client,
+            cache: None,
+            offline: false,
+        })
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_cache(self, cache: PathBuf) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Where a URL's body and validators are cached.
+    fn cache_paths(&self, url: &str) -> Option<(PathBuf, PathBuf)> {
+        let cache = self.cache.as_ref()?;
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+
+        Some((
+            cache.join(format!("{key}.{}", constants::BODY_EXTENSION)),
+            cache.join(format!("{key}.{}", constants::METADATA_EXTENSION)),
+        ))
+    }
+
+    ///
+    fn read_cache(&self, url: &str) -> Option<Cached> {
+        let (body, validators) = self.cache_paths(url)?;
+        let validators: Validators = serde_json::from_slice(&fs::read(validators).ok()?).ok()?;
+        let body = fs::read(body).ok()?;
+
+        // Guards against the odd hash collision
+        (validators.url == url).then_some(Cached { validators, body })
+    }
+
+    ///
+    fn write_cache(&self, validators: &Validators, body: &[u8]) -> io::Result<()> {
+        let Some((body_path, validators_path)) = self.cache_paths(&validators.url) else {
+            return Ok(());
+        };
+
+        if let Some(parent) = body_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(body_path, body)?;
+        fs::write(validators_path, serde_json::to_vec(validators)?)
+    }
+
+    ///
+    fn request(&self, url: &str, cached: Option<&Cached>) -> reqwest::Result<Response> {
+        let mut request = self.client.get(url);
+
+        if let Some(cached) = cached {
+            if let &Some(ref etag) = &cached.validators.etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            if let &Some(ref last_modified) = &cached.validators.last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        request.send()
+    }
+}
+
+impl Fetcher<io::Cursor<Vec<u8>>> for HttpFetcher {
+    #[inline]
+    fn fetch(&self, location: &str) -> io::Result<io::Cursor<Vec<u8>>> {
+        let cached = self.read_cache(location);
+
+        if self.offline {
+            return cached
+                .map(|cached| io::Cursor::new(cached.body))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{location} hasn't been cached and fetching is offline"),
+                    )
+                });
+        }
+
+        let response = match self.request(location, cached.as_ref()) {
+            Ok(response) => response,
+            Err(err) => {
+                return match cached {
+                    Some(cached) => {
+                        tracing::warn!(url = location, error = %err, "unreachable, using cached copy");
+                        Ok(io::Cursor::new(cached.body))
+                    }
+                    None => Err(io::Error::other(err)),
+                };
+            }
+        };
+
+        let status = response.status();
+        match cached {
+            Some(cached) if status == StatusCode::NOT_MODIFIED => {
+                return Ok(io::Cursor::new(cached.body));
+            }
+            Some(cached) if status.is_server_error() => {
+                tracing::warn!(url = location, %status, "server error, using cached copy");
+                return Ok(io::Cursor::new(cached.body));
+            }
+            Some(_) | None => {}
+        }
+
+        if !status.is_success() {
+            return Err(io::Error::new(
+                if status == StatusCode::NOT_FOUND {
+                    io::ErrorKind::NotFound
+                } else {
+                    io::ErrorKind::Other
+                },
+                format!("{location} responded with {status}"),
+            ));
+        }
+
+        let validator = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+        let validators = Validators {
+            url: location.to_owned(),
+            etag: validator(header::ETAG),
+            last_modified: validator(header::LAST_MODIFIED),
+        };
+
+        let body = response.bytes().map_err(io::Error::other)?.to_vec();
+        if let Err(err) = self.write_cache(&validators, &body) {
+            tracing::warn!(url = location, error = %err, "unable to cache");
+        }
+
+        Ok(io::Cursor::new(body))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use service_loader::{routing::CompositeFetcher, ServiceLoader};
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+    };
+
+    /// Stands in for a server hosting shared components, answers conditional requests
+    /// and counts how many full bodies it sent.
+    struct StandIn {
+        base: String,
+        docs: Arc<Mutex<HashMap<String, String>>>,
+        sent: Arc<AtomicUsize>,
+    }
+
+    impl StandIn {
+        fn start(docs: &[(&str, &str)]) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            let docs = Arc::new(Mutex::new(
+                docs.iter()
+                    .map(|&(path, doc)| (path.to_owned(), doc.to_owned()))
+                    .collect::<HashMap<_, _>>(),
+            ));
+            let sent = Arc::new(AtomicUsize::new(0));
+
+            let (served, counter) = (Arc::clone(&docs), Arc::clone(&sent));
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let path = request_line.split(' ').nth(1).unwrap_or("/").to_owned();
+
+                    let mut if_none_match = None;
+                    loop {
+                        let mut header = String::new();
+                        reader.read_line(&mut header).unwrap();
+                        if header.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':') {
+                            if name.eq_ignore_ascii_case("if-none-match") {
+                                if_none_match = Some(value.trim().to_owned());
+                            }
+                        }
+                    }
+
+                    let doc = served.lock().unwrap().get(&path).cloned();
+                    let response = match doc {
+                        Some(doc) => {
+                            let etag = format!("\"{}\"", doc.len());
+                            if if_none_match.as_deref() == Some(etag.as_str()) {
+                                "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_owned()
+                            } else {
+                                counter.fetch_add(1, Ordering::SeqCst);
+                                format!(
+                                    "HTTP/1.1 200 OK\r\nETag: {etag}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{doc}",
+                                    doc.len()
+                                )
+                            }
+                        }
+                        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_owned(),
+                    };
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+            });
+
+            Self { base, docs, sent }
+        }
+
+        fn url(&self, path: &str) -> String {
+            format!("{}{path}", self.base)
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("remote_file_loader_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn read(fetcher: &HttpFetcher, url: &str) -> io::Result<String> {
+        let mut content = String::new();
+        fetcher.fetch(url)?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_revalidates_cached_copy() {
+        let server = StandIn::start(&[("/common.yaml", "Pet: {}")]);
+        let fetcher = HttpFetcher::new()
+            .unwrap()
+            .with_cache(temp_dir("revalidate"));
+
+        assert_eq!(
+            "Pet: {}",
+            read(&fetcher, &server.url("/common.yaml")).unwrap()
+        );
+        assert_eq!(
+            "Pet: {}",
+            read(&fetcher, &server.url("/common.yaml")).unwrap()
+        );
+        assert_eq!(1, server.sent.load(Ordering::SeqCst));
+
+        server
+            .docs
+            .lock()
+            .unwrap()
+            .insert("/common.yaml".into(), "Pet: { type: object }".into());
+        assert_eq!(
+            "Pet: { type: object }",
+            read(&fetcher, &server.url("/common.yaml")).unwrap()
+        );
+        assert_eq!(2, server.sent.load(Ordering::SeqCst));
+
+        let err = read(&fetcher, &server.url("/missing.yaml")).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+    }
+
+    #[test]
+    fn test_falls_back_to_cache_when_offline() {
+        let cache = temp_dir("offline");
+        let url = {
+            let server = StandIn::start(&[("/common.yaml", "Pet: {}")]);
+            let fetcher = HttpFetcher::new().unwrap().with_cache(cache.clone());
+            read(&fetcher, &server.url("/common.yaml")).unwrap();
+            server.url("/common.yaml")
+        };
+
+        // Nothing is listening on this port anymore
+        let unreachable = url.replace(
+            url.split('/').nth(2).unwrap(),
+            &TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .to_string(),
+        );
+        let fetcher = HttpFetcher::new().unwrap().with_cache(cache.clone());
+        let err = read(&fetcher, &unreachable).unwrap_err();
+        assert_ne!(io::ErrorKind::NotFound, err.kind());
+
+        let offline = HttpFetcher::new()
+            .unwrap()
+            .with_cache(cache)
+            .with_offline(true);
+        assert_eq!("Pet: {}", read(&offline, &url).unwrap());
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            read(&offline, &unreachable).unwrap_err().kind()
+        );
+    }
+
+    struct Connector(HashMap<&'static str, String>);
+
+    impl Fetcher<io::Cursor<String>> for Connector {
+        fn fetch(&self, location: &str) -> io::Result<io::Cursor<String>> {
+            self.0
+                .get(location)
+                .cloned()
+                .map(io::Cursor::new)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    #[test]
+    fn test_spec_with_shared_remote_components() {
+        const COMMON: &str = r##"
+components:
+  parameters:
+    Limit:
+      name: limit
+      in: query
+      schema:
+        $ref: "#/components/schemas/Limit"
+  schemas:
+    Limit:
+      type: integer
+    Pet:
+      type: object
+      properties:
+        tag:
+          $ref: "./tags.yaml#/Tag"
+"##;
+        const TAGS: &str = r#"
+Tag:
+  type: string
+"#;
+        let server =
+            StandIn::start(&[("/shared/common.yaml", COMMON), ("/shared/tags.yaml", TAGS)]);
+        let common = server.url("/shared/common.yaml");
+
+        let spec = format!(
+            r##"
+openapi: 3.0.0
+info:
+  title: Pets
+servers:
+  - url: https://example.com
+paths:
+  /pets:
+    get:
+      operationId: list_pets
+      parameters:
+        - $ref: "{common}#/components/parameters/Limit"
+      responses:
+        "200":
+          description: ok
+          content:
+            application/json:
+              schema:
+                $ref: "{common}#/components/schemas/Pet"
+"##
+        );
+        let local = Connector(HashMap::from([
+            (
+                "./manifest.json",
+                r#"{ "v2": { "swagger": { "source": "./spec.yaml" } } }"#.to_owned(),
+            ),
+            ("./spec.yaml", spec),
+        ]));
+        let remote = HttpFetcher::new().unwrap();
+        let fetcher = CompositeFetcher::new(&local)
+            .route("http://", &remote)
+            .route("https://", &remote);
+
+        let service = ServiceLoader::new().load_base(&fetcher, false).unwrap();
+        let api = &service.v1().commonApi;
+
+        let limit = &api.operations["list_pets"].parameter[0];
+        assert_eq!("limit", limit.name);
+        assert_eq!(
+            format!("{common}#/components/schemas/Limit"),
+            limit.schema.ref_()
+        );
+
+        // Nested references resolve against the document they're in, not the spec
+        let pet = &api.schemas[&format!("{common}#/components/schemas/Pet")];
+        let tag = &pet.schemaObject().properties["tag"];
+        assert_eq!(server.url("/shared/tags.yaml#/Tag"), tag.ref_());
+        assert!(api
+            .schemas
+            .contains_key(&server.url("/shared/tags.yaml#/Tag")));
+    }
+}
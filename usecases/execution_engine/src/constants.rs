@@ -1,3 +1,28 @@
-/// Formatting for usage in execution logging.
-/// This is meant to be human readable.
-pub const DATETIME_FORMAT: &str = "%a %b %e %Y %I:%M:%S %p";
+//!
+
+///
+pub const OUTCOME_COMPLETED: &str = "completed";
+
+///
+pub const OUTCOME_FAILED: &str = "failed";
+
+/// Marks a span as failed for OpenTelemetry.
+pub const SPAN_STATUS_ERROR: &str = "ERROR";
+
+/// Counter, labelled by service, operation and status.
+pub const METRIC_EXECUTIONS: &str = "apid_executions_total";
+
+/// Histogram in seconds, labelled by service and operation.
+pub const METRIC_EXECUTION_DURATION: &str = "apid_execution_duration_seconds";
+
+/// Option naming the credential profile to run with, i.e. `{ "profile": "sandbox" }`.
+pub const PROFILE_OPTION: &str = "profile";
+
+/// Option naming the environment to run against, i.e. `{ "environment": "staging" }`.
+pub const ENVIRONMENT_OPTION: &str = "environment";
+
+/// Option naming a directory to record the execution's HTTP traffic to.
+pub const RECORD_OPTION: &str = "record";
+
+/// Option naming a cassette to answer the execution's HTTP calls from.
+pub const REPLAY_OPTION: &str = "replay";
@@ -34,6 +34,10 @@ pub enum ExecutionEngine {
     #[error("Get out of here! The Lock is poisoned: {0}")]
     PoisonedLock(String),
 
+    ///
+    #[error("Result stream was closed before the execution finished")]
+    StreamClosed,
+
     /// TODO: Rename to OutputPort
     #[error(transparent)]
     Other {
@@ -28,26 +28,29 @@ use alloc::sync::Arc;
 
 use serde_json::Value;
 use services::{
-    CodeRunner, DataConnectionRunner, DataConnectorBundle, EngineInputContext, EngineLookup,
-    FilteredRunner, InputPrompter, ScriptRunner,
-};
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::Write,
-    sync::{Mutex, RwLock},
+    Cassette, CodeRunner, DataConnectionRunner, DataConnectorBundle, EngineInputContext,
+    EngineLookup, FilteredRunner, InputPrompter, ScriptRunner,
 };
+use std::{collections::HashMap, time::Instant};
 
-use chrono::offset::Local;
 use core_entities::service::{code_resource::Language, service_manifest_latest};
+use credential_entities::credentials::Authentication;
+use tracing::field;
+
+/// Which of a run's settings were picked for it rather than inherited from whatever ran it.
+#[derive(Clone, Copy)]
+struct Picked {
+    ///
+    profile: bool,
 
-///
-pub struct Engine {
     ///
-    lookup: Arc<Mutex<dyn EngineLookup + Send + Sync>>,
+    environment: bool,
+}
 
+///
+pub struct Engine {
     ///
-    logger: Arc<RwLock<File>>,
+    lookup: Arc<dyn EngineLookup + Send + Sync>,
 
     ///
     connector: Option<Box<dyn DataConnectionRunner + Send + Sync>>,
@@ -68,13 +71,9 @@ pub struct Engine {
 impl Engine {
     ///
     #[inline]
-    pub fn new(
-        lookup: Arc<Mutex<dyn EngineLookup + Send + Sync>>,
-        logger: Arc<RwLock<File>>,
-    ) -> Self {
+    pub fn new(lookup: Arc<dyn EngineLookup + Send + Sync>) -> Self {
         Self {
             lookup,
-            logger,
             connector: None,
             code_runners: HashMap::new(),
             script_runner: None,
@@ -113,6 +112,7 @@ impl Engine {
         self.input_handler = Some(handler);
     }
 
+    /// Runs an operation in its own span, logging how long it took and how it went.
     ///
     /// # Errors
     #[inline]
@@ -122,6 +122,112 @@ impl Engine {
         params: Value,
         options: Value,
         context: &EngineInputContext,
+    ) -> error::Result<Value> {
+        let (service, operation) = split_identifier(identifier).unwrap_or((identifier, ""));
+        let service = match &context.parent {
+            &Some(ref parent) if service == "this" => parent,
+            _ => service,
+        };
+
+        let span = tracing::info_span!(
+            parent: &context.span,
+            "engine.run",
+            execution_id = %context.execution_id,
+            service,
+            operation,
+            outcome = field::Empty,
+            otel.status_code = field::Empty,
+        );
+        let _entered = span.enter();
+
+        // Anything run on our behalf hangs off of this span, and uses the same profile,
+        // environment and cassette unless it picks its own
+        let option = |name: &str| {
+            options
+                .get(name)
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+        };
+        let profile = option(constants::PROFILE_OPTION);
+        let environment = option(constants::ENVIRONMENT_OPTION);
+        let picked = Picked {
+            profile: profile.is_some(),
+            environment: environment.is_some(),
+        };
+        let cassette = option(constants::REPLAY_OPTION)
+            .map(|path| Cassette::Replay(path.into()))
+            .or_else(|| option(constants::RECORD_OPTION).map(|path| Cassette::Record(path.into())));
+        let context = context
+            .clone()
+            .with_span(span.clone())
+            .with_profile(profile.or_else(|| context.profile.clone()))
+            .with_environment(environment.or_else(|| context.environment.clone()))
+            .with_cassette(cassette.or_else(|| context.cassette.clone()))
+            .with_lookup(context.lookup.clone().or_else(|| self.lookup.pin()));
+
+        let started = Instant::now();
+        let result = self.run_internal(identifier, params, options, &context, picked);
+        let elapsed = started.elapsed();
+        let duration_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+
+        let outcome = if result.is_ok() {
+            constants::OUTCOME_COMPLETED
+        } else {
+            constants::OUTCOME_FAILED
+        };
+        metrics::increment_counter!(
+            constants::METRIC_EXECUTIONS,
+            "service" => service.to_owned(),
+            "operation" => operation.to_owned(),
+            "status" => outcome,
+        );
+        metrics::histogram!(
+            constants::METRIC_EXECUTION_DURATION,
+            elapsed,
+            "service" => service.to_owned(),
+            "operation" => operation.to_owned(),
+        );
+
+        match &result {
+            &Ok(_) => {
+                span.record("outcome", constants::OUTCOME_COMPLETED);
+                tracing::info!(
+                    execution_id = %context.execution_id,
+                    parent = context.parent.as_deref(),
+                    service,
+                    operation,
+                    duration_ms,
+                    outcome = constants::OUTCOME_COMPLETED,
+                    "execution finished"
+                );
+            }
+            &Err(ref err) => {
+                span.record("outcome", constants::OUTCOME_FAILED);
+                span.record("otel.status_code", constants::SPAN_STATUS_ERROR);
+                tracing::error!(
+                    execution_id = %context.execution_id,
+                    parent = context.parent.as_deref(),
+                    service,
+                    operation,
+                    duration_ms,
+                    outcome = constants::OUTCOME_FAILED,
+                    error = %err,
+                    "execution finished"
+                );
+            }
+        }
+
+        result
+    }
+
+    ///
+    fn run_internal(
+        &self,
+        identifier: &str,
+        params: Value,
+        options: Value,
+        context: &EngineInputContext,
+        picked: Picked,
     ) -> error::Result<Value> {
         // SimpleCode -> CodeRunner
         // ApiWrapper -> FilteredRunner
@@ -137,33 +243,18 @@ impl Engine {
             ));
         }
 
-        let parts: Vec<&str> = identifier.split('.').collect();
-
-        let service_name = parts
-            .first()
-            .ok_or_else(|| error::ExecutionEngine::InvalidIdentifier(identifier.into()))?;
-        let operation_name = parts
-            .get(1)
+        let (service_name, operation_name) = split_identifier(identifier)
             .ok_or_else(|| error::ExecutionEngine::InvalidIdentifier(identifier.into()))?;
 
         let service_name = match &context.parent {
-            &Some(ref parent) if *service_name == "this" => parent,
-            _ => *service_name,
+            &Some(ref parent) if service_name == "this" => parent,
+            _ => service_name,
         };
 
-        let (service, credentials) = {
-            let lookup = self
-                .lookup
-                .lock()
-                .map_err(|err| error::ExecutionEngine::PoisonedLock(err.to_string()))?;
-            let service = lookup
-                .get_service(service_name)
-                .ok_or_else(|| error::ExecutionEngine::NotFound(identifier.into()))?;
-
-            let credentials = lookup.get_credentials(service_name);
-
-            (service, credentials)
-        };
+        let service = self
+            .lookup(context)
+            .get_service(service_name)
+            .ok_or_else(|| error::ExecutionEngine::NotFound(identifier.into()))?;
         let service = service.v1();
         let manifest = service.manifest.v2();
 
@@ -171,13 +262,28 @@ impl Engine {
             &Some(service_manifest_latest::Value::Swagger(ref swagger)) => {
                 if let &Some(ref connector) = &self.connector {
                     let api = &service.commonApi;
-                    let creds = credentials.as_ref();
-
-                    let bundle = DataConnectorBundle {
-                        manifest: swagger,
-                        api,
-                        creds,
+                    let credentials = self.credentials(context, service_name, picked.profile)?;
+                    let creds = credentials.as_deref();
+
+                    // Same as with profiles, an inherited environment the service doesn't
+                    // define leaves it on its default server
+                    let base_url = match context.environment.as_deref() {
+                        Some(environment) => match api.environments.get(environment) {
+                            Some(url) => Some(url.as_str()),
+                            None if picked.environment => {
+                                return Err(error::ExecutionEngine::NotFound(format!(
+                                    "Environment {environment} for {service_name}"
+                                )));
+                            }
+                            None => None,
+                        },
+                        None => None,
                     };
+
+                    let bundle =
+                        DataConnectorBundle::new(swagger, api, creds).with_base_url(base_url);
+
+                    Self::log_started(context, service_name, operation_name, "swagger");
                     connector.run(
                         service_name,
                         operation_name,
@@ -196,7 +302,7 @@ impl Engine {
                 let operation = action
                     .operations
                     .iter()
-                    .find(|item| item.id == *operation_name);
+                    .find(|item| item.id == operation_name);
                 if let Some(operation) = operation {
                     let operation = operation.function();
 
@@ -211,17 +317,14 @@ impl Engine {
                         )))?;
 
                     if let Some(code_runner) = self.code_runners.get(&operation.lang) {
-                        self.log(identifier, "ACTION", "STARTED")?;
-                        let result = code_runner.run(
+                        Self::log_started(context, service_name, operation_name, "action");
+                        code_runner.run(
                             service_name,
                             operation_name,
                             &source.content,
                             params,
                             context,
-                        )?;
-                        self.log(identifier, "ACTION", "COMPLETED")?;
-
-                        Ok(result)
+                        )
                     } else {
                         Err(error::ExecutionEngine::NotFound(format!(
                             "Code Runner for language {} not found",
@@ -236,17 +339,8 @@ impl Engine {
             }
             &Some(service_manifest_latest::Value::ApiWrapped(ref api_wrapped)) => {
                 if let &Some(ref filtered_runner) = &self.filtered_runner {
-                    self.log(identifier, "API_WRAPPED", "STARTED")?;
-                    let result = filtered_runner.run(
-                        service_name,
-                        operation_name,
-                        api_wrapped,
-                        params,
-                        context,
-                    )?;
-                    self.log(identifier, "API_WRAPPED", "COMPLETED")?;
-
-                    Ok(result)
+                    Self::log_started(context, service_name, operation_name, "api_wrapped");
+                    filtered_runner.run(service_name, operation_name, api_wrapped, params, context)
                 } else {
                     Err(error::ExecutionEngine::NotFound(
                         "API Wrapper runner not found".into(),
@@ -257,17 +351,14 @@ impl Engine {
                 match simple_code.code.language.enum_value() {
                     Ok(Language::PYTHON) => {
                         if let Some(code_runner) = self.code_runners.get("python") {
-                            self.log(identifier, "SIMPLE_CODE", "STARTED")?;
-                            let result = code_runner.run(
+                            Self::log_started(context, service_name, operation_name, "simple_code");
+                            code_runner.run(
                                 service_name,
                                 operation_name,
                                 simple_code.code.codeString(),
                                 params,
                                 context,
-                            )?;
-                            self.log(identifier, "SIMPLE_CODE", "COMPLETED")?;
-
-                            Ok(result)
+                            )
                         } else {
                             Err(error::ExecutionEngine::NotFound(
                                 "Code runner not found for python".into(),
@@ -276,17 +367,14 @@ impl Engine {
                     }
                     Ok(Language::JAVASCRIPT) => {
                         if let Some(code_runner) = self.code_runners.get("js") {
-                            self.log(identifier, "SIMPLE_CODE", "STARTED")?;
-                            let result = code_runner.run(
+                            Self::log_started(context, service_name, operation_name, "simple_code");
+                            code_runner.run(
                                 service_name,
                                 operation_name,
                                 simple_code.code.codeString(),
                                 params,
                                 context,
-                            )?;
-                            self.log(identifier, "SIMPLE_CODE", "COMPLETED")?;
-
-                            Ok(result)
+                            )
                         } else {
                             Err(error::ExecutionEngine::NotFound(
                                 "Code runner not found for python".into(),
@@ -299,7 +387,23 @@ impl Engine {
             _ => Err(error::ExecutionEngine::Unimplemented("API Runner".into())),
         }?;
 
-        if context.raw_response {
+        // The data connector streams page by page on its own, everything else
+        // finishes as a single page
+        let streamed = matches!(
+            &manifest.value,
+            &Some(service_manifest_latest::Value::Swagger(_))
+        );
+
+        if context.is_streaming() && !context.raw_response && !streamed {
+            let page = if let Value::Array(items) = result {
+                items
+            } else {
+                vec![result]
+            };
+            context.emit(page)?;
+
+            Ok(Value::Array(vec![]))
+        } else if context.raw_response {
             Ok(result)
         } else if let Value::Array(_) = result {
             Ok(result)
@@ -308,17 +412,166 @@ impl Engine {
         }
     }
 
+    /// A profile that was asked for has to exist, an inherited one falls back to the default
+    /// since the service that was run first might have profiles this one doesn't.
+    fn credentials(
+        &self,
+        context: &EngineInputContext,
+        service_name: &str,
+        explicit: bool,
+    ) -> error::Result<Option<Arc<Authentication>>> {
+        let lookup = self.lookup(context);
+        let Some(profile) = context.profile.as_deref() else {
+            return Ok(lookup.get_credentials(service_name, None));
+        };
+
+        match lookup.get_credentials(service_name, Some(profile)) {
+            Some(credentials) => Ok(Some(credentials)),
+            None if explicit => Err(error::ExecutionEngine::NotFound(format!(
+                "Credential profile {profile} for {service_name}"
+            ))),
+            None => Ok(lookup.get_credentials(service_name, None)),
+        }
+    }
+
+    /// What the run pinned when it started, the engine's own lookup when it can't change
+    /// underneath a run.
+    fn lookup<'lookup>(
+        &'lookup self,
+        context: &'lookup EngineInputContext,
+    ) -> &'lookup (dyn EngineLookup + Send + Sync) {
+        context.lookup.as_deref().unwrap_or(&*self.lookup)
+    }
+
     ///
-    fn log(&self, id: &str, action_type: &str, status: &str) -> error::Result<()> {
-        let now = Local::now();
-        let now = now.format(constants::DATETIME_FORMAT).to_string();
+    fn log_started(context: &EngineInputContext, service: &str, operation: &str, kind: &str) {
+        tracing::debug!(
+            execution_id = %context.execution_id,
+            parent = context.parent.as_deref(),
+            service,
+            operation,
+            kind,
+            "execution started"
+        );
+    }
+}
+
+/// Splits `service.operation`. A service pinned to a tag (`acme/github:1.2.0`) can have dots of
+/// its own, so the operation is whatever follows the last one.
+fn split_identifier(identifier: &str) -> Option<(&str, &str)> {
+    if identifier.contains(':') {
+        identifier.rsplit_once('.')
+    } else {
+        identifier.split_once('.')
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    use std::sync::mpsc;
 
-        let mut logger = self
-            .logger
-            .write()
-            .map_err(|err| error::ExecutionEngine::PoisonedLock(err.to_string()))?;
-        logger.write_all(format!("{now} ({action_type}) [{status}] {id}\n").as_bytes())?;
+    use core_entities::service::{CodeResource, SimpleCodeService, VersionedServiceTree};
+    use serde_json::json;
+
+    /// Only `script`, a python snippet whose code is the result it gives back.
+    struct Lookup(Arc<VersionedServiceTree>);
+
+    impl EngineLookup for Lookup {
+        fn get_service(&self, id: &str) -> Option<Arc<VersionedServiceTree>> {
+            (id == "script").then(|| Arc::clone(&self.0))
+        }
+
+        fn get_credentials(&self, _: &str, _: Option<&str>) -> Option<Arc<Authentication>> {
+            None
+        }
+    }
+
+    /// Answers with the source code parsed as JSON.
+    struct Runner;
+
+    impl CodeRunner for Runner {
+        fn run(
+            &self,
+            _: &str,
+            _: &str,
+            source_code: &str,
+            _: Value,
+            _: &EngineInputContext,
+        ) -> error::Result<Value> {
+            Ok(serde_json::from_str(source_code).unwrap())
+        }
+    }
+
+    fn engine(result: &Value) -> Engine {
+        let mut code = CodeResource::new();
+        code.language = Language::PYTHON.into();
+        code.set_codeString(result.to_string());
+        let mut simple_code = SimpleCodeService::new();
+        simple_code.code = Some(code).into();
+
+        let mut service = VersionedServiceTree::new();
+        service
+            .mut_v1()
+            .manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .set_simpleCode(simple_code);
+
+        let mut engine = Engine::new(Arc::new(Lookup(Arc::new(service))));
+        engine.register_language("python", Box::new(Runner));
+        engine
+    }
+
+    fn stream(engine: &Engine, context: EngineInputContext) -> (error::Result<Value>, Vec<Value>) {
+        let (sender, receiver) = mpsc::sync_channel(4);
+        let result = engine.run(
+            "script.execute",
+            json!({}),
+            json!({}),
+            &context.with_stream(sender),
+        );
+        (result, receiver.try_iter().collect())
+    }
+
+    #[test]
+    fn test_streams_results_as_one_page() {
+        let context = || EngineInputContext::new(None, "streamed".into(), false);
+
+        let (result, pages) = stream(&engine(&json!([1, 2])), context());
+        assert_eq!(result.unwrap(), json!([]));
+        assert_eq!(pages, vec![json!([1, 2])]);
+
+        let (result, pages) = stream(&engine(&json!({"id": 1})), context());
+        assert_eq!(result.unwrap(), json!([]));
+        assert_eq!(pages, vec![json!([{"id": 1}])]);
+    }
+
+    #[test]
+    fn test_raw_responses_are_not_streamed() {
+        let context = EngineInputContext::new(None, "raw".into(), true);
+
+        let (result, pages) = stream(&engine(&json!({"id": 1})), context);
+        assert_eq!(result.unwrap(), json!({"id": 1}));
+        assert!(pages.is_empty());
+    }
 
-        Ok(())
+    #[test]
+    fn test_closed_stream_stops_the_run() {
+        let (sender, receiver) = mpsc::sync_channel(4);
+        drop(receiver);
+        let context = EngineInputContext::new(None, "closed".into(), false).with_stream(sender);
+
+        assert!(matches!(
+            context.emit(vec![json!(1)]),
+            Err(error::ExecutionEngine::StreamClosed)
+        ));
+        assert!(matches!(
+            engine(&json!([1, 2])).run("script.execute", json!({}), json!({}), &context),
+            Err(error::ExecutionEngine::StreamClosed)
+        ));
     }
 }
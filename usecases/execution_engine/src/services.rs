@@ -1,14 +1,19 @@
 //!
 
+extern crate alloc;
+use alloc::sync::Arc;
+
 use core_entities::service::{
     APIWrappedService, CommonApi, ScriptedAction, SwaggerService, VersionedServiceTree,
 };
 use credential_entities::credentials::Authentication;
 use serde_json::Value;
+use std::{path::PathBuf, sync::mpsc::SyncSender};
 
 use crate::error;
 
 ///
+#[derive(Clone)]
 #[non_exhaustive]
 pub struct EngineInputContext {
     ///
@@ -19,6 +24,37 @@ pub struct EngineInputContext {
 
     ///
     pub raw_response: bool,
+
+    /// Set when the caller wants each page of results as it arrives instead
+    /// of one array at the end. Bounded so a slow reader holds up the runner.
+    pub stream: Option<SyncSender<Value>>,
+
+    /// The span nested executions and HTTP calls are children of, none for a top level run.
+    pub span: tracing::Span,
+
+    /// Credential profile picked for the run, nested runs use it too unless they pick their own.
+    pub profile: Option<String>,
+
+    /// Environment picked for the run, passed along the same way as the profile.
+    pub environment: Option<String>,
+
+    /// HTTP traffic is recorded or replayed for the whole execution, nested runs included.
+    pub cassette: Option<Cassette>,
+
+    /// Where services and credentials are looked up, pinned when the top level run starts so
+    /// it and every nested run see the same ones even if a reload lands halfway through.
+    pub lookup: Option<Arc<dyn EngineLookup + Send + Sync>>,
+}
+
+/// What happens to an execution's HTTP calls, see `api_caller` for the file format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Cassette {
+    /// Every exchange is saved (redacted) to a file named after the execution in this directory.
+    Record(PathBuf),
+
+    /// Calls are answered from this file instead of the network.
+    Replay(PathBuf),
 }
 
 impl EngineInputContext {
@@ -30,17 +66,121 @@ impl EngineInputContext {
             parent,
             execution_id,
             raw_response,
+            stream: None,
+            span: tracing::Span::none(),
+            profile: None,
+            environment: None,
+            cassette: None,
+            lookup: None,
         }
     }
+
+    /// For a run made on this one's behalf, same parent and execution with the span, profile,
+    /// environment, cassette and lookup carried over. Results aren't streamed to this run's reader.
+    #[must_use]
+    #[inline]
+    pub fn child(&self, raw_response: bool) -> Self {
+        Self::new(self.parent.clone(), self.execution_id.clone(), raw_response)
+            .with_span(self.span.clone())
+            .with_profile(self.profile.clone())
+            .with_environment(self.environment.clone())
+            .with_cassette(self.cassette.clone())
+            .with_lookup(self.lookup.clone())
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_parent(mut self, parent: Option<String>) -> Self {
+        self.parent = parent;
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_span(mut self, span: tracing::Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_environment(mut self, environment: Option<String>) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_cassette(mut self, cassette: Option<Cassette>) -> Self {
+        self.cassette = cassette;
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_lookup(mut self, lookup: Option<Arc<dyn EngineLookup + Send + Sync>>) -> Self {
+        self.lookup = lookup;
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_stream(mut self, stream: SyncSender<Value>) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Hands a page of results to whoever is streaming this execution.
+    ///
+    /// # Errors
+    /// Fails when nobody is listening anymore (i.e. the client went away).
+    #[inline]
+    pub fn emit(&self, page: Vec<Value>) -> error::Result<()> {
+        if let &Some(ref stream) = &self.stream {
+            stream
+                .send(Value::Array(page))
+                .map_err(|_err| error::ExecutionEngine::StreamClosed)?;
+        }
+
+        Ok(())
+    }
 }
 
-///
+/// Hands out shared snapshots so a run neither copies the service nor holds a lock while it runs.
 pub trait EngineLookup {
     ///
-    fn get_service(&self, id: &str) -> Option<VersionedServiceTree>;
+    fn get_service(&self, id: &str) -> Option<Arc<VersionedServiceTree>>;
 
-    ///
-    fn get_credentials(&self, id: &str) -> Option<Authentication>;
+    /// The named credential profile, or the service's default one when `profile` is `None`.
+    fn get_credentials(&self, id: &str, profile: Option<&str>) -> Option<Arc<Authentication>>;
+
+    /// What's loaded right now, held onto for a whole run. `None` when what this looks up
+    /// never changes underneath a run.
+    #[inline]
+    fn pin(&self) -> Option<Arc<dyn EngineLookup + Send + Sync>> {
+        None
+    }
 }
 
 ///
@@ -61,6 +201,42 @@ pub struct DataConnectorBundle<'bundle> {
 
     ///
     pub creds: Option<&'bundle Authentication>,
+
+    /// Set when an environment sends calls somewhere other than the api's `basePath`.
+    pub base_url: Option<&'bundle str>,
+}
+
+impl<'bundle> DataConnectorBundle<'bundle> {
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn new(
+        manifest: &'bundle SwaggerService,
+        api: &'bundle CommonApi,
+        creds: Option<&'bundle Authentication>,
+    ) -> Self {
+        Self {
+            manifest,
+            api,
+            creds,
+            base_url: None,
+        }
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn with_base_url(mut self, base_url: Option<&'bundle str>) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Where calls go, the environment's server or the api's `basePath`.
+    #[must_use]
+    #[inline]
+    pub fn base_url(&self) -> &'bundle str {
+        self.base_url.unwrap_or_else(|| self.api.basePath())
+    }
 }
 
 ///
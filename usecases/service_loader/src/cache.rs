@@ -0,0 +1,13 @@
+//! Where compiled services are kept between runs, see `loaders::cached` for how they're keyed.
+
+use std::io;
+
+/// Compiled services are stored under a hash of every file that went into them.
+pub trait Cache {
+    ///
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    ///
+    /// # Errors
+    fn put(&self, key: &str, value: &[u8]) -> io::Result<()>;
+}
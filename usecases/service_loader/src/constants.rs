@@ -8,3 +8,39 @@ pub const CONFIG_LOCATION: &str = "./config.json";
 
 ///
 pub const MANIFEST_LOCATION: &str = "./manifest.json";
+
+/// Relation used to find the next page when a `linkHeader` pagination doesn't specify one.
+pub const DEFAULT_LINK_REL: &str = "next";
+
+/// Pagination paths starting with this are runtime expressions rather than `JMESPath`.
+pub const RUNTIME_EXPRESSION_PREFIX: &str = "$";
+
+/// Pagination paths starting with this are JSON pointers into the body rather than `JMESPath`.
+pub const JSON_POINTER_PREFIX: &str = "/";
+
+/// A pagination path with none of these in it is a JSON pointer, `items` and `data/items` the
+/// same as `/items` and `/data/items`.
+pub const JMESPATH_SYNTAX: [char; 18] = [
+    '.', '[', ']', '{', '}', '(', ')', '|', '&', '!', '=', '<', '>', '@', '*', '?', '`', '\'',
+];
+
+/// The only operation wrapped, code and scripted services have.
+pub const EXECUTE_OPERATION: &str = "execute";
+
+///
+pub const CACHE_INDEX_PREFIX: &str = "index-";
+
+/// Versioned so that services compiled before a change to what's compiled are left behind.
+pub const CACHE_OBJECT_PREFIX: &str = "service-v2-";
+
+/// Compiled variants remembered per manifest.
+pub const CACHE_VARIANTS: usize = 4;
+
+/// What a `credentials.json` without profiles is loaded as, and the default default.
+pub const DEFAULT_PROFILE: &str = "default";
+
+///
+pub const PROFILES_KEY: &str = "profiles";
+
+/// Names the profile runs use when they don't pick one.
+pub const DEFAULT_PROFILE_KEY: &str = "default";
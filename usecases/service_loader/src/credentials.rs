@@ -0,0 +1,252 @@
+//! Named sets of credentials for a connector, i.e. sandbox and production or one per tenant.
+//!
+//! `credentials.json` holds either a single set of credentials, which becomes the `default`
+//! profile, or every profile along with which one to use when a run doesn't pick one:
+//!
+//! ```json
+//! { "default": "sandbox", "profiles": { "sandbox": { "header": { ... } }, "prod": { ... } } }
+//! ```
+
+extern crate alloc;
+use alloc::{collections::BTreeMap, sync::Arc};
+
+use credential_entities::credentials::Authentication;
+
+use crate::{constants, error};
+
+/// Every profile a connector has, never empty once loaded.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Profiles {
+    /// Used when a run doesn't ask for a profile.
+    pub default: String,
+
+    ///
+    pub profiles: BTreeMap<String, Arc<Authentication>>,
+}
+
+impl Profiles {
+    /// What a `credentials.json` without profiles loads as.
+    #[must_use]
+    #[inline]
+    pub fn single(credentials: Authentication) -> Self {
+        Self {
+            default: constants::DEFAULT_PROFILE.to_owned(),
+            profiles: BTreeMap::from([(
+                constants::DEFAULT_PROFILE.to_owned(),
+                Arc::new(credentials),
+            )]),
+        }
+    }
+
+    ///
+    /// # Errors
+    /// When the JSON isn't credentials, or the default names a profile that isn't there.
+    #[inline]
+    pub fn parse(content: &str) -> error::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+
+        let Some(profiles) = value.get(constants::PROFILES_KEY) else {
+            return Ok(Self::single(protobuf_json_mapping::parse_from_str(
+                content,
+            )?));
+        };
+
+        let profiles = profiles
+            .as_object()
+            .ok_or_else(|| error::ServiceLoader::WrongType {
+                field: constants::PROFILES_KEY.into(),
+                expected: "object".into(),
+            })?
+            .iter()
+            .map(|(name, credentials)| {
+                let credentials: Authentication =
+                    protobuf_json_mapping::parse_from_str(&credentials.to_string())?;
+                Ok((name.clone(), Arc::new(credentials)))
+            })
+            .collect::<error::Result<BTreeMap<_, _>>>()?;
+
+        let default = value
+            .get(constants::DEFAULT_PROFILE_KEY)
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or(constants::DEFAULT_PROFILE)
+            .to_owned();
+        if !profiles.contains_key(&default) {
+            return Err(error::ServiceLoader::NotFound(format!(
+                "Default credential profile {default}"
+            )));
+        }
+
+        Ok(Self { default, profiles })
+    }
+
+    /// Written back without profiles when there's only the one, so files that never used them stay the same.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn to_json(&self) -> error::Result<serde_json::Value> {
+        let print = |credentials: &Authentication| -> error::Result<serde_json::Value> {
+            let credentials = protobuf_json_mapping::print_to_string(credentials)?;
+            Ok(serde_json::from_str(&credentials)?)
+        };
+
+        if let (1, Some(only)) = (
+            self.profiles.len(),
+            self.profiles.get(constants::DEFAULT_PROFILE),
+        ) {
+            if self.default == constants::DEFAULT_PROFILE {
+                return print(only);
+            }
+        }
+
+        let mut profiles = serde_json::Map::new();
+        for (name, credentials) in &self.profiles {
+            profiles.insert(name.clone(), print(credentials)?);
+        }
+
+        let mut value = serde_json::Map::new();
+        value.insert(
+            constants::DEFAULT_PROFILE_KEY.into(),
+            self.default.clone().into(),
+        );
+        value.insert(constants::PROFILES_KEY.into(), profiles.into());
+        Ok(value.into())
+    }
+
+    /// Adds or replaces a profile, the first one added becomes the default.
+    #[inline]
+    pub fn insert(&mut self, name: String, credentials: Authentication) {
+        if self.profiles.is_empty() {
+            self.default.clone_from(&name);
+        }
+        self.profiles.insert(name, Arc::new(credentials));
+    }
+
+    /// Profile names, in order.
+    #[must_use]
+    #[inline]
+    pub fn names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// The named profile or the default one. With group credentials an OAuth profile that
+    /// only has its own token shares the client id and secret of the default profile.
+    #[must_use]
+    #[inline]
+    pub fn resolve(&self, profile: Option<&str>, group: bool) -> Option<Arc<Authentication>> {
+        let name = profile.unwrap_or(&self.default);
+        let credentials = self.profiles.get(name)?;
+
+        if !group || name == self.default || !credentials.has_oauth() {
+            return Some(Arc::clone(credentials));
+        }
+
+        let Some(shared) = self
+            .profiles
+            .get(&self.default)
+            .filter(|shared| shared.has_oauth())
+        else {
+            return Some(Arc::clone(credentials));
+        };
+
+        let mut merged = (**credentials).clone();
+        let oauth = merged.mut_oauth();
+        if oauth.clientId.is_empty() {
+            oauth.clientId.clone_from(&shared.oauth().clientId);
+        }
+        if oauth.clientSecret.is_empty() {
+            oauth.clientSecret.clone_from(&shared.oauth().clientSecret);
+        }
+
+        Some(Arc::new(merged))
+    }
+}
+
+/// `enableGroupCredentials` is a string in the manifest, anything but empty or `false` turns it on.
+#[must_use]
+#[inline]
+pub fn group_enabled(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty() && !value.eq_ignore_ascii_case("false")
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_single_credentials_are_the_default_profile() {
+        let content = r#"{ "header": { "value": "token" } }"#;
+        let profiles = Profiles::parse(content).unwrap();
+
+        assert_eq!(vec!["default".to_owned()], profiles.names());
+        assert_eq!(
+            "token",
+            profiles.resolve(None, false).unwrap().header().value
+        );
+        assert!(profiles.resolve(Some("prod"), false).is_none());
+
+        // Stays in the format it was written in
+        assert_eq!(
+            serde_json::json!({ "header": { "value": "token" } }),
+            profiles.to_json().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_named_profiles() {
+        let content = r#"{
+            "default": "sandbox",
+            "profiles": {
+                "sandbox": { "header": { "value": "test" } },
+                "prod": { "header": { "value": "live" } }
+            }
+        }"#;
+        let profiles = Profiles::parse(content).unwrap();
+
+        assert_eq!(
+            "test",
+            profiles.resolve(None, false).unwrap().header().value
+        );
+        assert_eq!(
+            "live",
+            profiles
+                .resolve(Some("prod"), false)
+                .unwrap()
+                .header()
+                .value
+        );
+        assert_eq!(
+            profiles,
+            Profiles::parse(&profiles.to_json().unwrap().to_string()).unwrap()
+        );
+
+        let missing = r#"{ "default": "staging", "profiles": { "prod": { "header": {} } } }"#;
+        assert!(Profiles::parse(missing).is_err());
+    }
+
+    #[test]
+    fn test_group_credentials_share_the_client() {
+        let content = r#"{
+            "profiles": {
+                "default": { "oauth": { "clientId": "app", "clientSecret": "secret" } },
+                "tenant": { "oauth": { "accessToken": "tenant-token" } }
+            }
+        }"#;
+        let profiles = Profiles::parse(content).unwrap();
+
+        let separate = profiles.resolve(Some("tenant"), false).unwrap();
+        assert!(separate.oauth().clientId.is_empty());
+
+        let grouped = profiles.resolve(Some("tenant"), true).unwrap();
+        assert_eq!("app", grouped.oauth().clientId);
+        assert_eq!("secret", grouped.oauth().clientSecret);
+        assert_eq!(Some("tenant-token"), grouped.oauth().accessToken.as_deref());
+
+        assert!(group_enabled("true"));
+        assert!(!group_enabled(""));
+        assert!(!group_enabled("False"));
+    }
+}
@@ -0,0 +1,384 @@
+//! Which services point at which, so dependents can be revalidated when what they
+//! depend on reloads or goes away.
+
+extern crate alloc;
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use core::fmt;
+
+use core_entities::service::{
+    action, chain_item, service_manifest_latest, APIWrappedService, ChainItem, VersionedServiceTree,
+};
+
+use crate::identity::ConnectorId;
+
+/// A service referred to by another one, along with the operation when one is named.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub struct Reference {
+    ///
+    pub service: String,
+
+    ///
+    pub operation: Option<String>,
+}
+
+impl Reference {
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn new(service: String, operation: Option<String>) -> Self {
+        Self { service, operation }
+    }
+
+    /// `connectorId` is written as `maintainer/name:tag`, which is also how the service is keyed.
+    fn api_wrapped(wrapped: &APIWrappedService) -> Self {
+        let operation = if wrapped.connectorOperation.is_empty() {
+            None
+        } else {
+            Some(wrapped.connectorOperation.clone())
+        };
+        let service = ConnectorId::parse(&wrapped.connectorId)
+            .map_or_else(|| wrapped.connectorId.clone(), |id| id.to_string());
+
+        Self::new(service, operation)
+    }
+}
+
+impl fmt::Display for Reference {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let &Some(ref operation) = &self.operation {
+            write!(f, "{}.{operation}", self.service)
+        } else {
+            write!(f, "{}", self.service)
+        }
+    }
+}
+
+/// A reference that can't be resolved with what's currently loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BrokenReference {
+    ///
+    MissingService(Reference),
+
+    /// The service is there but no longer has the operation, i.e. after its spec changed.
+    MissingOperation(Reference),
+}
+
+impl fmt::Display for BrokenReference {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            &Self::MissingService(ref reference) => {
+                write!(f, "{reference} (service not found)")
+            }
+            &Self::MissingOperation(ref reference) => {
+                write!(f, "{reference} (operation not found)")
+            }
+        }
+    }
+}
+
+/// Every service another one refers to, wrapped connectors and the actions in a script's chain.
+#[must_use]
+#[inline]
+pub fn references(service: &VersionedServiceTree) -> Vec<Reference> {
+    let mut references = vec![];
+
+    match &service.v1().manifest.v2().value {
+        &Some(service_manifest_latest::Value::ApiWrapped(ref wrapped)) => {
+            references.push(Reference::api_wrapped(wrapped));
+        }
+        &Some(service_manifest_latest::Value::ScriptedAction(ref scripted)) => {
+            chain_references(&scripted.chainItems, &mut references);
+            chain_references(&scripted.errorChainItems, &mut references);
+        }
+        _ => {}
+    }
+
+    references.sort();
+    references.dedup();
+    references
+}
+
+///
+fn chain_references(items: &[ChainItem], references: &mut Vec<Reference>) {
+    for item in items {
+        match &item.item {
+            &Some(chain_item::Item::Action(ref action)) => match &action.payload {
+                &Some(action::Payload::App(ref app)) => {
+                    references.push(Reference::new(ConnectorId::from(app).to_string(), None));
+                }
+                &Some(action::Payload::ApiWrapped(ref wrapped)) => {
+                    references.push(Reference::api_wrapped(wrapped));
+                }
+                _ => {}
+            },
+            &Some(chain_item::Item::Conditional(ref conditional)) => {
+                chain_references(&conditional.chainItems, references);
+            }
+            &Some(chain_item::Item::ForEach(ref for_each)) => {
+                chain_references(&for_each.chainItems, references);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether a loaded service can run the named operation.
+#[must_use]
+#[inline]
+pub fn has_operation(service: &VersionedServiceTree, operation: &str) -> bool {
+    let service = service.v1();
+
+    match &service.manifest.v2().value {
+        &Some(service_manifest_latest::Value::Swagger(_)) => {
+            service.commonApi.operations.contains_key(operation)
+        }
+        &Some(service_manifest_latest::Value::Action(ref action)) => {
+            action.operations.iter().any(|item| item.id == operation)
+        }
+        &Some(
+            service_manifest_latest::Value::ApiWrapped(_)
+            | service_manifest_latest::Value::SimpleCode(_)
+            | service_manifest_latest::Value::ScriptedAction(_),
+        ) => operation == crate::constants::EXECUTE_OPERATION,
+        _ => false,
+    }
+}
+
+/// Built up as services load, keeps track of who depends on whom and which references are broken.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct DependencyGraph {
+    ///
+    references: BTreeMap<String, Vec<Reference>>,
+
+    /// Reverse of `references`, kept even for services that aren't loaded so that
+    /// dependents can be found once they show up.
+    dependents: BTreeMap<String, BTreeSet<String>>,
+
+    ///
+    broken: BTreeMap<String, Vec<BrokenReference>>,
+}
+
+impl DependencyGraph {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records what a (re)loaded service refers to, replacing whatever it referred to before.
+    #[inline]
+    pub fn update(&mut self, id: &str, service: &VersionedServiceTree) {
+        self.remove(id);
+
+        let references = references(service);
+        for reference in &references {
+            self.dependents
+                .entry(reference.service.clone())
+                .or_default()
+                .insert(id.to_owned());
+        }
+        self.references.insert(id.to_owned(), references);
+    }
+
+    /// Forgets what a service refers to, services referring to it still depend on it.
+    #[inline]
+    pub fn remove(&mut self, id: &str) {
+        if let Some(references) = self.references.remove(id) {
+            for reference in references {
+                if let Some(dependents) = self.dependents.get_mut(&reference.service) {
+                    dependents.remove(id);
+                    if dependents.is_empty() {
+                        self.dependents.remove(&reference.service);
+                    }
+                }
+            }
+        }
+        self.broken.remove(id);
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn references(&self, id: &str) -> &[Reference] {
+        self.references.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Services that refer to this one directly.
+    #[must_use]
+    #[inline]
+    pub fn dependents(&self, id: &str) -> Vec<String> {
+        self.dependents
+            .get(id)
+            .map(|dependents| dependents.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Checks each of a service's references against what `lookup` can find.
+    #[inline]
+    pub fn revalidate<F>(&mut self, id: &str, lookup: F)
+    where
+        F: Fn(&str) -> Option<Arc<VersionedServiceTree>>,
+    {
+        let broken: Vec<_> = self
+            .references(id)
+            .iter()
+            .filter_map(|reference| match lookup(&reference.service) {
+                None => Some(BrokenReference::MissingService(reference.clone())),
+                Some(service) => match &reference.operation {
+                    &Some(ref operation) if !has_operation(&service, operation) => {
+                        Some(BrokenReference::MissingOperation(reference.clone()))
+                    }
+                    &Some(_) | &None => None,
+                },
+            })
+            .collect();
+
+        if broken.is_empty() {
+            self.broken.remove(id);
+        } else {
+            self.broken.insert(id.to_owned(), broken);
+        }
+    }
+
+    /// Found the last time the service was revalidated.
+    #[must_use]
+    #[inline]
+    pub fn broken(&self, id: &str) -> &[BrokenReference] {
+        self.broken.get(id).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use core_entities::service::{Action, ScriptedAction, ServiceId};
+    use std::collections::HashMap;
+
+    fn swagger(operations: &[&str]) -> VersionedServiceTree {
+        let mut service = VersionedServiceTree::new();
+        let v1 = service.mut_v1();
+        v1.manifest.mut_or_insert_default().mut_v2().mut_swagger();
+        for operation in operations {
+            v1.commonApi
+                .mut_or_insert_default()
+                .operations
+                .insert((*operation).into(), Default::default());
+        }
+        service
+    }
+
+    fn wrapped(connector: &str, operation: &str) -> VersionedServiceTree {
+        let mut service = VersionedServiceTree::new();
+        let wrapped = service
+            .mut_v1()
+            .manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .mut_apiWrapped();
+        wrapped.connectorId = connector.into();
+        wrapped.connectorOperation = operation.into();
+        service
+    }
+
+    #[test]
+    fn test_references_in_scripted_chain() {
+        let mut app = Action::new();
+        app.set_app(ServiceId {
+            name: "slack".into(),
+            ..Default::default()
+        });
+
+        let mut pinned = Action::new();
+        pinned.set_app(ServiceId {
+            name: "slack".into(),
+            maintainer: "acme".into(),
+            tag: "2.0".into(),
+            ..Default::default()
+        });
+
+        let mut inline = Action::new();
+        inline.mut_apiWrapped().connectorId = "acme/github:1.2.0".into();
+        inline.mut_apiWrapped().connectorOperation = "listRepos".into();
+
+        let mut nested = ChainItem::new();
+        nested.mut_forEach().chainItems.push({
+            let mut item = ChainItem::new();
+            item.set_action(inline);
+            item
+        });
+
+        let mut first = ChainItem::new();
+        first.set_action(app);
+
+        let mut second = ChainItem::new();
+        second.set_action(pinned);
+
+        let mut scripted = ScriptedAction::new();
+        scripted.chainItems = vec![first, second, nested];
+
+        let mut service = VersionedServiceTree::new();
+        service
+            .mut_v1()
+            .manifest
+            .mut_or_insert_default()
+            .mut_v2()
+            .set_scriptedAction(scripted);
+
+        assert_eq!(
+            vec![
+                Reference::new("acme/github:1.2.0".into(), Some("listRepos".into())),
+                Reference::new("acme/slack:2.0".into(), None),
+                Reference::new("slack".into(), None),
+            ],
+            references(&service)
+        );
+    }
+
+    #[test]
+    fn test_revalidate_dependents() {
+        let mut loaded = HashMap::new();
+        let mut graph = DependencyGraph::new();
+
+        // Loaded before what it wraps
+        loaded.insert("repos", Arc::new(wrapped("github", "listRepos")));
+        graph.update("repos", &loaded["repos"]);
+        graph.revalidate("repos", |id| loaded.get(id).cloned());
+        assert_eq!(
+            vec![BrokenReference::MissingService(Reference::new(
+                "github".into(),
+                Some("listRepos".into())
+            ))],
+            graph.broken("repos")
+        );
+
+        loaded.insert("github", Arc::new(swagger(&["listRepos"])));
+        graph.update("github", &loaded["github"]);
+        assert_eq!(vec!["repos".to_owned()], graph.dependents("github"));
+        graph.revalidate("repos", |id| loaded.get(id).cloned());
+        assert!(graph.broken("repos").is_empty());
+
+        // The spec changed and the operation went away
+        loaded.insert("github", Arc::new(swagger(&["listIssues"])));
+        graph.update("github", &loaded["github"]);
+        graph.revalidate("repos", |id| loaded.get(id).cloned());
+        assert_eq!(
+            "github.listRepos (operation not found)",
+            graph.broken("repos")[0].to_string()
+        );
+
+        graph.remove("repos");
+        assert!(graph.broken("repos").is_empty());
+        assert!(graph.dependents("github").is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+//! Picking which of a spec's servers runs go to, and what its variables are set to.
+//!
+//! Variables are taken from the first place that sets them: the environment, `serverVariables`
+//! in `config.json`, then the default from the spec.
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+
+use core::hash::BuildHasher;
+use std::collections::HashMap;
+
+use core_entities::service::{CommonApi, ServerWithVariables, SwaggerOverrides};
+
+use crate::error;
+
+/// Fills in a server's variables, values outside of a variable's `enum` are rejected.
+///
+/// # Errors
+#[inline]
+pub fn server_url<S: BuildHasher>(
+    url: &str,
+    server: Option<&ServerWithVariables>,
+    layers: &[&HashMap<String, String, S>],
+) -> error::Result<String> {
+    let mut url = url.to_owned();
+
+    if let Some(server) = server {
+        let variables: BTreeMap<_, _> = server.variables.iter().collect();
+        for (name, variable) in variables {
+            let value = layers
+                .iter()
+                .find_map(|layer| layer.get(name))
+                .unwrap_or(&variable.default);
+
+            if !variable.enum_.is_empty() && !variable.enum_.contains(value) {
+                return Err(error::ServiceLoader::OverrideError(format!(
+                    "{value} isn't one of {} for server variable {name}",
+                    variable.enum_.join(", ")
+                )));
+            }
+
+            url = url.replace(&["{", name, "}"].join(""), value);
+        }
+    }
+
+    // Variables the spec doesn't declare can still be set from the configuration
+    for layer in layers.iter().rev() {
+        let values: BTreeMap<_, _> = layer.iter().collect();
+        for (name, value) in values {
+            url = url.replace(&["{", name, "}"].join(""), value);
+        }
+    }
+
+    Ok(url)
+}
+
+/// A server picked by its url or its description, ignoring case.
+#[must_use]
+#[inline]
+pub fn find_server<'api>(api: &'api CommonApi, server: &str) -> Option<&'api ServerWithVariables> {
+    api.servers.iter().find(|candidate| {
+        candidate.url == server || candidate.description.eq_ignore_ascii_case(server)
+    })
+}
+
+/// Resolves the default base path and every named environment from `config.json`.
+///
+/// # Errors
+/// When an environment names a server the spec doesn't have, a variable is set outside of its
+/// `enum` or the default environment isn't defined.
+#[inline]
+pub fn apply(api: &mut CommonApi, overrides: &SwaggerOverrides) -> error::Result<()> {
+    let default = api.servers.first();
+
+    let mut base_path =
+        default.map_or_else(|| api.basePath().to_owned(), |server| server.url.clone());
+    if !overrides.baseUrl.is_empty() {
+        if base_path.contains("{{baseUrl}}") {
+            base_path = base_path.replace("{{baseUrl}}", &overrides.baseUrl);
+        } else {
+            base_path.clone_from(&overrides.baseUrl);
+        }
+    }
+
+    let mut environments = HashMap::new();
+    let named: BTreeMap<_, _> = overrides.environments.iter().collect();
+    for (name, environment) in named {
+        let layers = [&environment.variables, &overrides.serverVariables];
+
+        let url = if environment.server.is_empty() {
+            server_url(&base_path, default, &layers)?
+        } else {
+            let server = find_server(api, &environment.server).ok_or_else(|| {
+                error::ServiceLoader::OverrideError(format!(
+                    "Environment {name} uses server {} which isn't in the spec",
+                    environment.server
+                ))
+            })?;
+            server_url(&server.url, Some(server), &layers)?
+        };
+
+        environments.insert(name.clone(), url);
+    }
+
+    let base_path = if overrides.environment.is_empty() {
+        server_url(&base_path, default, &[&overrides.serverVariables])?
+    } else {
+        environments
+            .get(&overrides.environment)
+            .cloned()
+            .ok_or_else(|| {
+                error::ServiceLoader::NotFound(format!("Environment {}", overrides.environment))
+            })?
+    };
+
+    api.set_basePath(base_path);
+    api.environments = environments;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use core_entities::service::{server_with_variables::Variable, Environment};
+
+    fn api() -> CommonApi {
+        let mut api = CommonApi::new();
+
+        let mut production = ServerWithVariables::new();
+        production.url = "https://{region}.example.com/v1".into();
+        production.description = "Production".into();
+        production.variables.insert(
+            "region".into(),
+            Variable {
+                default: "us".into(),
+                enum_: vec!["us".into(), "eu".into()],
+                ..Default::default()
+            },
+        );
+
+        let mut staging = ServerWithVariables::new();
+        staging.url = "https://staging.example.com/v1".into();
+        staging.description = "Staging".into();
+
+        api.servers = vec![production, staging];
+        api.set_basePath("https://us.example.com/v1".into());
+        api
+    }
+
+    #[test]
+    fn test_environments_pick_servers_and_variables() {
+        let mut api = api();
+        let mut overrides = SwaggerOverrides::new();
+        overrides
+            .serverVariables
+            .insert("region".into(), "eu".into());
+        overrides.environments.insert(
+            "staging".into(),
+            Environment {
+                server: "staging".into(),
+                ..Default::default()
+            },
+        );
+        overrides.environments.insert(
+            "us".into(),
+            Environment {
+                variables: HashMap::from([("region".into(), "us".into())]),
+                ..Default::default()
+            },
+        );
+
+        apply(&mut api, &overrides).unwrap();
+
+        assert_eq!("https://eu.example.com/v1", api.basePath());
+        assert_eq!(
+            "https://staging.example.com/v1",
+            api.environments["staging"]
+        );
+        assert_eq!("https://us.example.com/v1", api.environments["us"]);
+
+        overrides.environment = "staging".into();
+        apply(&mut api, &overrides).unwrap();
+        assert_eq!("https://staging.example.com/v1", api.basePath());
+    }
+
+    #[test]
+    fn test_invalid_environments() {
+        let mut overrides = SwaggerOverrides::new();
+        overrides
+            .serverVariables
+            .insert("region".into(), "ap".into());
+        assert!(apply(&mut api(), &overrides).is_err());
+
+        let mut overrides = SwaggerOverrides::new();
+        overrides.environments.insert(
+            "dev".into(),
+            Environment {
+                server: "https://dev.example.com".into(),
+                ..Default::default()
+            },
+        );
+        assert!(apply(&mut api(), &overrides).is_err());
+
+        let mut overrides = SwaggerOverrides::new();
+        overrides.environment = "prod".into();
+        assert!(apply(&mut api(), &overrides).is_err());
+    }
+}
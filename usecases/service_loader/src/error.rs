@@ -44,6 +44,16 @@ pub enum ServiceLoader {
         source: jsonptr::Error,
     },
 
+    ///
+    #[error("Invalid JMESPath expression (field={field}): {message}")]
+    InvalidJmesPath {
+        ///
+        field: String,
+
+        ///
+        message: String,
+    },
+
     ///
     #[error("Wrong Type (field={field}, expected={expected})")]
     WrongType {
@@ -86,6 +96,14 @@ pub enum ServiceLoader {
         source: protobuf_json_mapping::ParseError,
     },
 
+    ///
+    #[error(transparent)]
+    ProtobufPrint {
+        ///
+        #[from]
+        source: protobuf_json_mapping::PrintError,
+    },
+
     /// TODO: Rename to OutputPortError
     #[error(transparent)]
     Other {
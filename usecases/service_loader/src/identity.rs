@@ -0,0 +1,161 @@
+//! How services refer to each other, by name alone or pinned to a maintainer and tag.
+
+use core::fmt;
+use std::path::PathBuf;
+
+use core_entities::service::ServiceId;
+use serde::{Deserialize, Serialize};
+
+/// Written as `maintainer/name:tag`, a connector that lives in the connector root is just `name`.
+/// Ids resolve exactly, `acme/github:1.0` never falls back to `github` or another tag.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ConnectorId {
+    ///
+    #[serde(default)]
+    pub maintainer: String,
+
+    ///
+    pub name: String,
+
+    ///
+    #[serde(default)]
+    pub tag: String,
+}
+
+impl ConnectorId {
+    ///
+    #[must_use]
+    #[inline]
+    pub const fn new(maintainer: String, name: String, tag: String) -> Self {
+        Self {
+            maintainer,
+            name,
+            tag,
+        }
+    }
+
+    /// Accepts `name`, `maintainer/name`, `name:tag` and `maintainer/name:tag`.
+    #[must_use]
+    #[inline]
+    pub fn parse(id: &str) -> Option<Self> {
+        let (maintainer, rest) = id.split_once('/').unwrap_or(("", id));
+        let (name, tag) = rest.split_once(':').unwrap_or((rest, ""));
+
+        let id = Self::new(maintainer.to_owned(), name.to_owned(), tag.to_owned());
+        id.is_valid().then_some(id)
+    }
+
+    /// Installed from a package rather than living in the connector root.
+    #[must_use]
+    #[inline]
+    pub fn is_versioned(&self) -> bool {
+        !self.maintainer.is_empty() || !self.tag.is_empty()
+    }
+
+    /// Every part can be used as a directory name, and only the name is required.
+    #[must_use]
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        let valid = |part: &str| {
+            !part.starts_with('.') && !part.contains(['/', '\\', ':']) && part.trim() == part
+        };
+
+        !self.name.is_empty()
+            && valid(&self.name)
+            && (self.maintainer.is_empty() || valid(&self.maintainer))
+            && (self.tag.is_empty() || valid(&self.tag))
+    }
+
+    /// Where a fully versioned connector is installed within a registry, `maintainer/name/tag`.
+    #[must_use]
+    #[inline]
+    pub fn install_path(&self) -> Option<PathBuf> {
+        if self.maintainer.is_empty() || self.tag.is_empty() || !self.is_valid() {
+            return None;
+        }
+
+        Some(
+            [&self.maintainer, &self.name, &self.tag]
+                .into_iter()
+                .collect(),
+        )
+    }
+}
+
+impl From<&ServiceId> for ConnectorId {
+    #[inline]
+    fn from(value: &ServiceId) -> Self {
+        Self::new(
+            value.maintainer.clone(),
+            value.name.clone(),
+            value.tag.clone(),
+        )
+    }
+}
+
+impl fmt::Display for ConnectorId {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.maintainer.is_empty() {
+            write!(f, "{}/", self.maintainer)?;
+        }
+        write!(f, "{}", self.name)?;
+        if !self.tag.is_empty() {
+            write!(f, ":{}", self.tag)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+
+    #[test]
+    fn test_parse_round_trips() {
+        for id in ["github", "acme/github", "github:1.2.0", "acme/github:1.2.0"] {
+            assert_eq!(id, ConnectorId::parse(id).unwrap().to_string());
+        }
+
+        let id = ConnectorId::parse("acme/github:1.2.0").unwrap();
+        assert_eq!(
+            ConnectorId::new("acme".into(), "github".into(), "1.2.0".into()),
+            id
+        );
+        assert!(id.is_versioned());
+        assert_eq!(Some(PathBuf::from("acme/github/1.2.0")), id.install_path());
+
+        assert!(!ConnectorId::parse("github").unwrap().is_versioned());
+        assert_eq!(None, ConnectorId::parse("github").unwrap().install_path());
+    }
+
+    #[test]
+    fn test_parse_rejects_unusable_parts() {
+        for id in [
+            "",
+            "acme/",
+            ":1.0",
+            "acme/../x:1.0",
+            "acme/.hidden:1.0",
+            "a/b/c:1.0",
+        ] {
+            assert_eq!(None, ConnectorId::parse(id), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_from_service_id() {
+        let id = ServiceId {
+            name: "slack".into(),
+            maintainer: "acme".into(),
+            tag: "2.0".into(),
+            ..Default::default()
+        };
+
+        assert_eq!("acme/slack:2.0", ConnectorId::from(&id).to_string());
+    }
+}
@@ -11,6 +11,7 @@
     clippy::needless_borrowed_reference,
     clippy::absolute_paths,
     clippy::ref_patterns,
+    clippy::match_ref_pats,
     clippy::single_call_fn
 )]
 
@@ -19,12 +20,18 @@
 mod constants;
 mod loaders;
 
+pub mod cache;
+pub mod credentials;
+pub mod dependencies;
+pub mod environments;
 pub mod error;
+pub mod identity;
+pub mod routing;
 
-use std::io;
+use std::{io, path::Path};
 
-use core_entities::service::{SwaggerOverrides, VersionedServiceTree};
-use credential_entities::credentials::Authentication;
+use core_entities::service::{SwaggerOverrides, SwaggerService, VersionedServiceTree};
+use credentials::Profiles;
 use loaders::{load_configuration, load_credentials, load_service};
 
 ///
@@ -33,9 +40,9 @@ pub trait LoaderOutput {
     /// # Errors
     fn handle_service(&mut self, id: &str, service: VersionedServiceTree) -> error::Result<()>;
 
-    ///
+    /// Every profile in `credentials.json`.
     /// # Errors
-    fn handle_credentials(&mut self, id: &str, credentials: Authentication) -> error::Result<()>;
+    fn handle_credentials(&mut self, id: &str, credentials: Profiles) -> error::Result<()>;
 }
 
 ///
@@ -57,6 +64,15 @@ macro_rules! apply_if_exists {
     };
 }
 
+/// Same as `apply_if_exists` for fields wrapped so that unset and zero can be told apart.
+macro_rules! apply_if_set {
+    ($field:ident, $source:expr => $sink:expr) => {
+        if $source.$field.is_some() {
+            $sink.$field = $source.$field.clone();
+        }
+    };
+}
+
 ///
 /// # Errors
 /// # Panics
@@ -72,22 +88,7 @@ pub fn merge(
         .as_mut()
         .ok_or_else(|| error::ServiceLoader::NotFound("Common API".into()))?;
 
-    let mut base_path = api.basePath().to_owned();
-    if !overrides.baseUrl.is_empty() {
-        if base_path.contains("{{baseUrl}}") {
-            base_path = base_path.replace("{{baseUrl}}", &overrides.baseUrl);
-        } else {
-            base_path = overrides.baseUrl.clone();
-        }
-    }
-
-    // Set server variables
-    for (key, value) in &overrides.serverVariables {
-        let key = ["{", key, "}"].join("");
-        base_path = base_path.replace(&key, value);
-    }
-
-    api.set_basePath(base_path);
+    environments::apply(api, overrides)?;
 
     let manifest = service
         .manifest
@@ -135,19 +136,98 @@ pub fn merge(
         }
     }
 
+    merge_http_client(manifest, overrides);
+    merge_response_cache(manifest, overrides);
+    apply_if_exists!(errorStatuses, overrides => manifest);
+
     Ok(())
 }
 
+/// Client settings from `config.json` go on top of whatever the manifest has.
+fn merge_http_client(manifest: &mut SwaggerService, overrides: &SwaggerOverrides) {
+    let Some(client_override) = overrides.httpClient.as_ref() else {
+        return;
+    };
+
+    let client = manifest.httpClient.mut_or_insert_default();
+
+    apply_if_set!(connectTimeoutMs, client_override => client);
+    apply_if_set!(readTimeoutMs, client_override => client);
+    apply_if_exists!(proxy, client_override => client);
+    apply_if_exists!(httpProxy, client_override => client);
+    apply_if_exists!(httpsProxy, client_override => client);
+    apply_if_exists!(noProxy, client_override => client);
+    apply_if_set!(maxRedirects, client_override => client);
+    apply_if_set!(http2, client_override => client);
+    apply_if_exists!(userAgent, client_override => client);
+    client
+        .caCertificates
+        .extend(client_override.caCertificates.iter().cloned());
+}
+
+/// Same as the client settings, `varyHeaders` from `config.json` replace the manifest's.
+fn merge_response_cache(manifest: &mut SwaggerService, overrides: &SwaggerOverrides) {
+    let Some(cache_override) = overrides.responseCache.as_ref() else {
+        return;
+    };
+
+    let cache = manifest.responseCache.mut_or_insert_default();
+
+    apply_if_set!(enabled, cache_override => cache);
+    apply_if_set!(ttlSeconds, cache_override => cache);
+    apply_if_exists!(varyHeaders, cache_override => cache);
+}
+
+/// What part of a connector a changed file affects, so a reload only re-parses what it has to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Change {
+    /// The manifest or anything it points at, everything gets reloaded.
+    Service,
+
+    ///
+    Credentials,
+
+    ///
+    Configuration,
+}
+
+impl Change {
+    /// Takes a path relative to the connector's directory.
+    #[must_use]
+    #[inline]
+    pub fn from_file(file: &Path) -> Self {
+        let file = Some(file.as_os_str());
+        if file == Path::new(constants::CREDENTIALS_LOCATION).file_name() {
+            Self::Credentials
+        } else if file == Path::new(constants::CONFIG_LOCATION).file_name() {
+            Self::Configuration
+        } else {
+            Self::Service
+        }
+    }
+}
+
 ///
 #[non_exhaustive]
-pub struct ServiceLoader;
+pub struct ServiceLoader {
+    /// Skips parsing services whose files haven't changed since they were last compiled.
+    cache: Option<Box<dyn cache::Cache + Send + Sync>>,
+}
 
 impl ServiceLoader {
     ///
     #[must_use]
     #[inline]
     pub fn new() -> Self {
-        Self
+        Self { cache: None }
+    }
+
+    ///
+    #[must_use]
+    #[inline]
+    pub fn with_cache(self, cache: Box<dyn cache::Cache + Send + Sync>) -> Self {
+        Self { cache: Some(cache) }
     }
 
     ///
@@ -161,25 +241,76 @@ impl ServiceLoader {
         merge_overrides: bool,
         only_manifest: bool,
     ) -> error::Result<()> {
-        let mut value = load_service(fetcher, only_manifest)?;
+        let base = self.load_base(fetcher, only_manifest)?;
 
-        if !only_manifest && value.v1().manifest.v2().has_swagger() {
+        if !only_manifest && base.v1().manifest.v2().has_swagger() {
             let creds = load_credentials(fetcher);
             if let Ok(creds) = creds {
                 output.handle_credentials(id, creds)?;
             }
+        }
 
-            if merge_overrides {
-                let config = load_configuration(fetcher);
-                if let Ok(config) = config {
-                    merge(&mut value, &config)?;
-                }
-            }
+        self.reload_configuration(
+            id,
+            &base,
+            fetcher,
+            output,
+            merge_overrides && !only_manifest,
+        )
+    }
+
+    /// Parses the service without any of the overrides from `config.json`, hold onto this to
+    /// reapply the configuration later on.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn load_base<R: io::Read>(
+        &self,
+        fetcher: &dyn Fetcher<R>,
+        only_manifest: bool,
+    ) -> error::Result<VersionedServiceTree> {
+        match &self.cache {
+            &Some(ref cache) if !only_manifest => loaders::cached::load(&**cache, fetcher),
+            _ => load_service(fetcher, only_manifest),
         }
+    }
 
-        output.handle_service(id, value)?;
+    /// Re-parses only `credentials.json`.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn reload_credentials<R: io::Read>(
+        &self,
+        id: &str,
+        fetcher: &dyn Fetcher<R>,
+        output: &mut dyn LoaderOutput,
+    ) -> error::Result<()> {
+        let creds = load_credentials(fetcher)?;
+        output.handle_credentials(id, creds)
+    }
 
-        Ok(())
+    /// Re-parses only `config.json`, merging it into a service from [`ServiceLoader::load_base`].
+    ///
+    /// # Errors
+    #[inline]
+    pub fn reload_configuration<R: io::Read>(
+        &self,
+        id: &str,
+        base: &VersionedServiceTree,
+        fetcher: &dyn Fetcher<R>,
+        output: &mut dyn LoaderOutput,
+        merge_overrides: bool,
+    ) -> error::Result<()> {
+        let mut value = base.clone();
+
+        if merge_overrides && value.v1().manifest.v2().has_swagger() {
+            let config = load_configuration(fetcher);
+            if let Ok(config) = config {
+                merge(&mut value, &config)?;
+            }
+        }
+
+        output.handle_service(id, value)
     }
 }
 
@@ -189,3 +320,149 @@ impl Default for ServiceLoader {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_change_from_file() {
+        assert_eq!(
+            Change::Credentials,
+            Change::from_file(Path::new("credentials.json"))
+        );
+        assert_eq!(
+            Change::Configuration,
+            Change::from_file(Path::new("config.json"))
+        );
+        assert_eq!(
+            Change::Service,
+            Change::from_file(Path::new("manifest.json"))
+        );
+        assert_eq!(
+            Change::Service,
+            Change::from_file(Path::new("src/index.js"))
+        );
+    }
+
+    struct Files(HashMap<&'static str, String>);
+
+    impl Fetcher<io::Cursor<String>> for Files {
+        fn fetch(&self, location: &str) -> io::Result<io::Cursor<String>> {
+            self.0
+                .get(location)
+                .cloned()
+                .map(io::Cursor::new)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    #[derive(Default)]
+    struct Output {
+        services: HashMap<String, VersionedServiceTree>,
+        credentials: HashMap<String, Profiles>,
+    }
+
+    impl LoaderOutput for Output {
+        fn handle_service(&mut self, id: &str, service: VersionedServiceTree) -> error::Result<()> {
+            self.services.insert(id.into(), service);
+            Ok(())
+        }
+
+        fn handle_credentials(&mut self, id: &str, credentials: Profiles) -> error::Result<()> {
+            self.credentials.insert(id.into(), credentials);
+            Ok(())
+        }
+    }
+
+    fn swagger_service() -> VersionedServiceTree {
+        let mut service = VersionedServiceTree::new();
+        let v1 = service.mut_v1();
+        v1.manifest.mut_or_insert_default().mut_v2().mut_swagger();
+        v1.commonApi
+            .mut_or_insert_default()
+            .set_basePath("{{baseUrl}}/api".into());
+        service
+    }
+
+    #[test]
+    fn test_reload_configuration_merges_into_base() {
+        let base = swagger_service();
+        let mut output = Output::default();
+
+        let fetcher = Files(HashMap::from([(
+            constants::CONFIG_LOCATION,
+            r#"{ "baseUrl": "https://one.example.com" }"#.to_owned(),
+        )]));
+        ServiceLoader::new()
+            .reload_configuration("test", &base, &fetcher, &mut output, true)
+            .unwrap();
+        assert_eq!(
+            "https://one.example.com/api",
+            output.services["test"].v1().commonApi.basePath()
+        );
+
+        // The base is untouched so a second config applies cleanly
+        let fetcher = Files(HashMap::from([(
+            constants::CONFIG_LOCATION,
+            r#"{ "baseUrl": "https://two.example.com" }"#.to_owned(),
+        )]));
+        ServiceLoader::new()
+            .reload_configuration("test", &base, &fetcher, &mut output, true)
+            .unwrap();
+        assert_eq!(
+            "https://two.example.com/api",
+            output.services["test"].v1().commonApi.basePath()
+        );
+        assert!(output.credentials.is_empty());
+    }
+
+    #[test]
+    fn test_configuration_sets_http_client() {
+        let base = swagger_service();
+        let mut output = Output::default();
+
+        let fetcher = Files(HashMap::from([(
+            constants::CONFIG_LOCATION,
+            r#"{
+                "httpClient.readTimeoutMs": 5000,
+                "httpClient.maxRedirects": "0",
+                "httpClient.caCertificates": ["/etc/ssl/proxy.pem"],
+                "httpClient.userAgent": "acme"
+            }"#
+            .to_owned(),
+        )]));
+        ServiceLoader::new()
+            .reload_configuration("test", &base, &fetcher, &mut output, true)
+            .unwrap();
+
+        let manifest = output.services["test"].v1().manifest.v2().swagger();
+        assert_eq!(5000, manifest.httpClient.readTimeoutMs.value);
+        assert!(manifest.httpClient.maxRedirects.is_some());
+        assert!(manifest.httpClient.connectTimeoutMs.is_none());
+        assert_eq!("acme", manifest.httpClient.userAgent);
+        assert_eq!(
+            vec!["/etc/ssl/proxy.pem"],
+            manifest.httpClient.caCertificates
+        );
+    }
+
+    #[test]
+    fn test_reload_credentials_only() {
+        let mut output = Output::default();
+        let fetcher = Files(HashMap::from([(
+            constants::CREDENTIALS_LOCATION,
+            "{}".to_owned(),
+        )]));
+
+        ServiceLoader::new()
+            .reload_credentials("test", &fetcher, &mut output)
+            .unwrap();
+
+        assert!(output.credentials.contains_key("test"));
+        assert!(output.services.is_empty());
+    }
+}
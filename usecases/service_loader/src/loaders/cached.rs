@@ -0,0 +1,267 @@
+//! Compiled services, keyed by a hash of every file that went into them.
+//!
+//! Entries are found by the hash of the manifest, which lists the files the service was
+//! compiled from last time. Those are hashed again and if they all match, the compiled
+//! bytes are used instead of parsing the spec and resolving all of its references.
+
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use core::cell::RefCell;
+
+use std::io;
+
+use core_entities::service::VersionedServiceTree;
+use protobuf::Message as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use super::load_service;
+use crate::{cache::Cache, constants, error, Fetcher};
+
+/// The files a service was compiled from along with their hashes.
+type Sources = BTreeMap<String, String>;
+
+/// Stored under the hash of the manifest, a few variants so that connectors
+/// sharing a manifest don't keep evicting each other.
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    ///
+    variants: Vec<Sources>,
+}
+
+///
+fn hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Combines the hashes of every source into the key the compiled bytes live under.
+fn key(sources: &Sources) -> String {
+    let mut hasher = Sha256::new();
+    for (location, content) in sources {
+        hasher.update(location.as_bytes());
+        hasher.update([0]);
+        hasher.update(content.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+///
+fn index_key(manifest: &str) -> String {
+    format!("{}{manifest}", constants::CACHE_INDEX_PREFIX)
+}
+
+///
+fn object_key(key: &str) -> String {
+    format!("{}{key}", constants::CACHE_OBJECT_PREFIX)
+}
+
+/// Passes everything through while keeping track of what was read.
+struct Recording<'fetcher, R> {
+    ///
+    inner: &'fetcher dyn Fetcher<R>,
+
+    ///
+    sources: RefCell<Sources>,
+}
+
+impl<R: io::Read> Fetcher<io::Cursor<Vec<u8>>> for Recording<'_, R> {
+    #[inline]
+    fn fetch(&self, location: &str) -> io::Result<io::Cursor<Vec<u8>>> {
+        let mut content = vec![];
+        self.inner.fetch(location)?.read_to_end(&mut content)?;
+
+        self.sources
+            .borrow_mut()
+            .insert(location.to_owned(), hash(&content));
+
+        Ok(io::Cursor::new(content))
+    }
+}
+
+///
+fn read<R: io::Read>(fetcher: &dyn Fetcher<R>, location: &str) -> io::Result<Vec<u8>> {
+    let mut content = vec![];
+    fetcher.fetch(location)?.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// Uses the compiled service when none of its sources changed, otherwise compiles it
+/// and stores the result. Problems with the cache itself only ever cost a full load.
+pub fn load<R: io::Read>(
+    cache: &dyn Cache,
+    fetcher: &dyn Fetcher<R>,
+) -> error::Result<VersionedServiceTree> {
+    let manifest = hash(&read(fetcher, constants::MANIFEST_LOCATION)?);
+    let mut index: Index = cache
+        .get(&index_key(&manifest))
+        .and_then(|index| serde_json::from_slice(&index).ok())
+        .unwrap_or_default();
+
+    for sources in &index.variants {
+        let unchanged = sources.iter().all(|(location, expected)| {
+            if location == constants::MANIFEST_LOCATION {
+                return *expected == manifest;
+            }
+            read(fetcher, location).is_ok_and(|content| hash(&content) == *expected)
+        });
+        if !unchanged {
+            continue;
+        }
+
+        let compiled = cache
+            .get(&object_key(&key(sources)))
+            .and_then(|bytes| VersionedServiceTree::parse_from_bytes(&bytes).ok());
+        if let Some(compiled) = compiled {
+            return Ok(compiled);
+        }
+    }
+
+    let recording = Recording {
+        inner: fetcher,
+        sources: RefCell::new(Sources::new()),
+    };
+    let service = load_service(&recording, false)?;
+    let sources = recording.sources.into_inner();
+
+    let stored = service
+        .write_to_bytes()
+        .map_err(io::Error::other)
+        .and_then(|bytes| cache.put(&object_key(&key(&sources)), &bytes));
+    if stored.is_ok() {
+        index.variants.retain(|variant| *variant != sources);
+        index.variants.insert(0, sources);
+        index.variants.truncate(constants::CACHE_VARIANTS);
+
+        if let Ok(index) = serde_json::to_vec(&index) {
+            // Worst case the next start compiles this one again
+            let _ignored = cache.put(&index_key(&manifest), &index);
+        }
+    }
+
+    Ok(service)
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use std::{collections::HashMap, sync::Mutex};
+
+    #[derive(Default)]
+    struct MemoryCache(Mutex<HashMap<String, Vec<u8>>>);
+
+    impl Cache for MemoryCache {
+        fn get(&self, key: &str) -> Option<Vec<u8>> {
+            self.0.lock().unwrap().get(key).cloned()
+        }
+
+        fn put(&self, key: &str, value: &[u8]) -> io::Result<()> {
+            self.0.lock().unwrap().insert(key.into(), value.to_vec());
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Files {
+        docs: HashMap<String, String>,
+        fetched: RefCell<Vec<String>>,
+    }
+
+    impl Files {
+        fn with(mut self, location: &str, doc: &str) -> Self {
+            self.docs.insert(location.into(), doc.into());
+            self
+        }
+    }
+
+    impl Fetcher<io::Cursor<String>> for Files {
+        fn fetch(&self, location: &str) -> io::Result<io::Cursor<String>> {
+            self.fetched.borrow_mut().push(location.into());
+            self.docs
+                .get(location)
+                .cloned()
+                .map(io::Cursor::new)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    const MANIFEST: &str = r#"{ "v2": { "swagger": { "source": "./spec.yaml" } } }"#;
+
+    fn spec(title: &str) -> String {
+        format!(
+            r##"
+openapi: 3.0.0
+info:
+  title: {title}
+servers:
+  - url: https://example.com
+paths:
+  /hello:
+    get:
+      operationId: say_hello
+      parameters:
+        - $ref: "./params.yaml#/name"
+"##
+        )
+    }
+
+    const PARAMS: &str = r#"
+name:
+  name: name
+  in: query
+  schema:
+    type: string
+"#;
+
+    fn connector(title: &str) -> Files {
+        Files::default()
+            .with(constants::MANIFEST_LOCATION, MANIFEST)
+            .with("./spec.yaml", &spec(title))
+            .with("./params.yaml", PARAMS)
+    }
+
+    #[test]
+    fn test_unchanged_sources_use_compiled() {
+        let cache = MemoryCache::default();
+
+        let first = connector("Example");
+        let compiled = load(&cache, &first).unwrap();
+        assert_eq!("Example", compiled.v1().commonApi.title);
+
+        let second = connector("Example");
+        let cached = load(&cache, &second).unwrap();
+        assert_eq!(compiled, cached);
+
+        // Only read to check the hashes, each exactly once
+        let mut fetched = second.fetched.borrow().clone();
+        fetched.sort();
+        assert_eq!(
+            vec!["./manifest.json", "./params.yaml", "./spec.yaml"],
+            fetched
+        );
+    }
+
+    #[test]
+    fn test_changed_reference_recompiles() {
+        let cache = MemoryCache::default();
+        load(&cache, &connector("Example")).unwrap();
+
+        let changed =
+            connector("Example").with("./params.yaml", &PARAMS.replace("query", "header"));
+        let recompiled = load(&cache, &changed).unwrap();
+        let parameter = &recompiled.v1().commonApi.operations["say_hello"].parameter[0];
+        assert_eq!(
+            core_entities::service::parameter::InType::HEADER,
+            parameter.in_.unwrap()
+        );
+
+        // Both variants share a manifest and stay cached
+        load(&cache, &connector("Example")).unwrap();
+        let index: Index =
+            serde_json::from_slice(&cache.get(&index_key(&hash(MANIFEST.as_bytes()))).unwrap())
+                .unwrap();
+        assert_eq!(2, index.variants.len());
+    }
+}
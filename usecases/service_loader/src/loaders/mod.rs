@@ -1,5 +1,6 @@
 //!
 
+pub mod cached;
 mod openapi;
 
 use std::{collections::HashMap, io};
@@ -7,22 +8,23 @@ use std::{collections::HashMap, io};
 use crate::Fetcher;
 
 use super::{constants, error};
+use crate::credentials::Profiles;
 use core_entities::service::{
     ServiceManifest, ServiceResource, SwaggerOverrides, VersionedServiceTree,
 };
-use credential_entities::credentials::Authentication;
 
 ///
 pub fn load_configuration<R: io::Read>(
     fetcher: &dyn Fetcher<R>,
 ) -> error::Result<SwaggerOverrides> {
     let config = fetcher.fetch(constants::CONFIG_LOCATION)?;
-    let config: HashMap<String, String> = serde_json::from_reader(config)?;
+    // Values are usually strings, anything else (numbers, booleans, lists) is passed through as is
+    let config: HashMap<String, serde_json::Value> = serde_json::from_reader(config)?;
 
     let mut root = serde_json::Value::Object(serde_json::Map::new());
     for (key, value) in config {
         let parts: Vec<_> = key.split('.').collect();
-        traverse_map(&mut root, &parts, &value)?;
+        traverse_map(&mut root, &parts, value)?;
     }
 
     let config = serde_json::to_string(&root)?;
@@ -32,7 +34,11 @@ pub fn load_configuration<R: io::Read>(
 }
 
 ///
-fn traverse_map(current: &mut serde_json::Value, parts: &[&str], value: &str) -> error::Result<()> {
+fn traverse_map(
+    current: &mut serde_json::Value,
+    parts: &[&str],
+    value: serde_json::Value,
+) -> error::Result<()> {
     if let Some(next) = parts.first() {
         if let &mut serde_json::Value::Object(ref mut current) = current {
             let key = (*next).to_owned();
@@ -49,18 +55,17 @@ fn traverse_map(current: &mut serde_json::Value, parts: &[&str], value: &str) ->
             ))
         }
     } else {
-        *current = serde_json::Value::String(value.to_owned());
+        *current = value;
         Ok(())
     }
 }
 
 ///
-pub fn load_credentials<R: io::Read>(fetcher: &dyn Fetcher<R>) -> error::Result<Authentication> {
+pub fn load_credentials<R: io::Read>(fetcher: &dyn Fetcher<R>) -> error::Result<Profiles> {
     let creds = fetcher.fetch(constants::CREDENTIALS_LOCATION)?;
     let creds = io::read_to_string(creds)?;
-    let creds: Authentication = protobuf_json_mapping::parse_from_str(&creds)?;
 
-    Ok(creds)
+    Profiles::parse(&creds)
 }
 
 ///
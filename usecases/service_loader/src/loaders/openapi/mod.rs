@@ -2,12 +2,15 @@
 
 mod utils;
 
+extern crate alloc;
+use alloc::collections::BTreeMap;
+
 use std::{
     collections::{HashMap, HashSet},
     io,
 };
 
-use crate::{error, Fetcher};
+use crate::{constants, environments, error, Fetcher};
 use core_entities::service;
 
 use self::utils::{default_field, handle_reference, optional_field, required_field};
@@ -29,9 +32,19 @@ pub fn handle<R: io::Read>(
 
     // Convert spec to common api
     let mut api = service::CommonApi::new();
-    let server = required_field(&spec, "servers")?;
-    let server = get_server(&server)?;
-    api.set_basePath(server);
+    let servers = required_field(&spec, "servers")?;
+    api.servers = get_servers(&servers)?;
+    let server = api
+        .servers
+        .first()
+        .ok_or(error::ServiceLoader::NotFound("Server".into()))?;
+    // Only the defaults until `config.json` is merged in
+    let defaults: [&HashMap<String, String>; 0] = [];
+    api.set_basePath(environments::server_url(
+        &server.url,
+        Some(server),
+        &defaults,
+    )?);
 
     if let Some(info) = spec.get("info") {
         if let Some(description) = optional_field::<String>(info, "description")? {
@@ -60,13 +73,34 @@ pub fn handle<R: io::Read>(
     Ok(protobuf::MessageField::some(api))
 }
 
-///
-fn get_server(server: &serde_json::Value) -> error::Result<String> {
-    // server.get(0).map(|s| s.url.clone()).ok_or(error::ServiceLoaderError::NotFound("Server".to_string()))
-    let server = server
-        .get(0)
-        .ok_or(error::ServiceLoader::NotFound("Server".into()))?;
-    required_field(server, "url")
+/// Every server along with its variables, the first one is used unless an environment picks another.
+fn get_servers(servers: &serde_json::Value) -> error::Result<Vec<service::ServerWithVariables>> {
+    let servers = servers.as_array().ok_or(error::ServiceLoader::WrongType {
+        field: "servers".into(),
+        expected: "array".into(),
+    })?;
+
+    let mut result = vec![];
+    for server in servers {
+        let mut common_server = service::ServerWithVariables::new();
+        common_server.url = required_field(server, "url")?;
+        common_server.description = default_field(server, "description")?;
+
+        let variables: BTreeMap<String, serde_json::Value> = default_field(server, "variables")?;
+        for (name, variable) in variables {
+            let common_variable = service::server_with_variables::Variable {
+                default: required_field(&variable, "default")?,
+                description: default_field(&variable, "description")?,
+                enum_: default_field(&variable, "enum")?,
+                ..Default::default()
+            };
+            common_server.variables.insert(name, common_variable);
+        }
+
+        result.push(common_server);
+    }
+
+    Ok(result)
 }
 
 ///
@@ -295,8 +329,7 @@ fn handle_pagination(
     source: &serde_json::Value,
     sink: &mut service::Pagination,
 ) -> error::Result<()> {
-    let mut results_path = service::pagination::ExtendedPath::new();
-    results_path.set_jmesPath(default_field(source, "resultsPath")?);
+    let results_path = handle_extended_path(source.get("resultsPath"), "resultsPath")?;
 
     if let Some(page_offset) = source.get("pageOffset") {
         let mut common_page_offset = service::pagination::PageOffset::new();
@@ -308,6 +341,7 @@ fn handle_pagination(
         common_page_offset.maxLimit =
             protobuf::MessageField::some(default_field::<i32>(page_offset, "maxLimit")?.into());
         common_page_offset.resultsPath = protobuf::MessageField::some(results_path);
+        common_page_offset.errorOnPathNotFound = error_on_path_not_found(source, page_offset)?;
 
         sink.set_pageOffset(common_page_offset);
     } else if let Some(offset) = source.get("offset") {
@@ -318,44 +352,142 @@ fn handle_pagination(
         common_offset.maxLimit =
             protobuf::MessageField::some(default_field::<i32>(offset, "maxLimit")?.into());
         common_offset.resultsPath = protobuf::MessageField::some(results_path);
+        common_offset.errorOnPathNotFound = error_on_path_not_found(source, offset)?;
 
         sink.set_offset(common_offset);
     } else if let Some(next_url) = source.get("nextUrl") {
         let mut common_next_url = service::pagination::NextUrl::new();
 
-        let mut next_url_path = service::pagination::ExtendedPath::new();
-        next_url_path.set_jmesPath(default_field(next_url, "nextUrlPath")?);
-
-        common_next_url.nextUrlPath = protobuf::MessageField::some(next_url_path);
+        common_next_url.nextUrlPath = protobuf::MessageField::some(handle_extended_path(
+            next_url.get("nextUrlPath"),
+            "nextUrlPath",
+        )?);
         common_next_url.limitParam = default_field(next_url, "limitParam")?;
         common_next_url.maxLimit =
             protobuf::MessageField::some(default_field::<i32>(next_url, "maxLimit")?.into());
         common_next_url.resultsPath = protobuf::MessageField::some(results_path);
+        common_next_url.errorOnPathNotFound = error_on_path_not_found(source, next_url)?;
 
         sink.set_nextUrl(common_next_url);
     } else if let Some(cursor) = source.get("cursor") {
         let mut common_cursor = service::pagination::MultiCursor::new();
 
-        let mut cursor_path = service::pagination::ExtendedPath::new();
-        cursor_path.set_jmesPath(default_field(cursor, "cursorPath")?);
-
-        common_cursor.cursorsPath = vec![cursor_path];
-        common_cursor.cursorsParam = vec![default_field::<String>(cursor, "cursorParam")?];
+        // A single `cursorPath`/`cursorParam` pair is shorthand for one element lists
+        common_cursor.cursorsPath = match cursor.get("cursorsPath") {
+            Some(&serde_json::Value::Array(ref paths)) => paths
+                .iter()
+                .map(|path| handle_extended_path(Some(path), "cursorsPath"))
+                .collect::<error::Result<Vec<_>>>()?,
+            Some(_) => {
+                return Err(error::ServiceLoader::WrongType {
+                    field: "cursorsPath".into(),
+                    expected: "array".into(),
+                })
+            }
+            None => vec![handle_extended_path(
+                cursor.get("cursorPath"),
+                "cursorPath",
+            )?],
+        };
+        common_cursor.cursorsParam = match optional_field(cursor, "cursorsParam")? {
+            Some(params) => params,
+            None => vec![default_field::<String>(cursor, "cursorParam")?],
+        };
         common_cursor.limitParam = default_field(cursor, "limitParam")?;
         common_cursor.maxLimit =
             protobuf::MessageField::some(default_field::<i32>(cursor, "maxLimit")?.into());
         common_cursor.resultsPath = protobuf::MessageField::some(results_path);
+        common_cursor.errorOnPathNotFound = error_on_path_not_found(source, cursor)?;
 
         sink.set_multiCursor(common_cursor);
+    } else if let Some(link_header) = source.get("linkHeader") {
+        let mut common_link_header = service::pagination::LinkHeader::new();
+
+        common_link_header.rel = optional_field(link_header, "rel")?
+            .unwrap_or_else(|| constants::DEFAULT_LINK_REL.to_owned());
+        common_link_header.limitParam = default_field(link_header, "limitParam")?;
+        common_link_header.maxLimit =
+            protobuf::MessageField::some(default_field::<i32>(link_header, "maxLimit")?.into());
+        common_link_header.resultsPath = protobuf::MessageField::some(results_path);
+        common_link_header.errorOnPathNotFound = error_on_path_not_found(source, link_header)?;
+
+        sink.set_linkHeader(common_link_header);
     } else {
         let mut common_unpaginaged = service::pagination::Unpaginated::new();
         common_unpaginaged.resultsPath = protobuf::MessageField::some(results_path);
+        common_unpaginaged.errorOnPathNotFound = error_on_path_not_found(source, source)?;
         sink.set_unpaginated(common_unpaginaged);
     }
 
     Ok(())
 }
 
+/// Runtime expressions (i.e. `$response.body#/items`) and JSON pointers (`/items`, or `items`
+/// and `data/items` without the leading slash) become a `columnPath`, anything using `JMESPath`
+/// syntax is taken to be a `JMESPath` expression. An object can also name which one it is.
+fn handle_extended_path(
+    source: Option<&serde_json::Value>,
+    field: &str,
+) -> error::Result<service::pagination::ExtendedPath> {
+    let mut path = service::pagination::ExtendedPath::new();
+
+    match source {
+        Some(&serde_json::Value::String(ref expression)) if is_column_path(expression) => {
+            path.set_columnPath(expression.clone());
+        }
+        Some(&serde_json::Value::String(ref expression)) => {
+            path.set_jmesPath(jmes_path(expression, field)?);
+        }
+        Some(source @ &serde_json::Value::Object(_)) => {
+            if let Some(expression) = optional_field::<String>(source, "jmesPath")? {
+                path.set_jmesPath(jmes_path(&expression, field)?);
+            } else {
+                path.set_columnPath(default_field(source, "columnPath")?);
+            }
+        }
+        Some(_) => {
+            return Err(error::ServiceLoader::WrongType {
+                field: field.into(),
+                expected: "string".into(),
+            });
+        }
+        None => path.set_columnPath(String::new()),
+    }
+
+    Ok(path)
+}
+
+/// Whether a pagination path is a runtime expression or JSON pointer rather than `JMESPath`.
+fn is_column_path(expression: &str) -> bool {
+    expression.is_empty()
+        || expression.starts_with(constants::RUNTIME_EXPRESSION_PREFIX)
+        || expression.starts_with(constants::JSON_POINTER_PREFIX)
+        || !expression.contains(constants::JMESPATH_SYNTAX)
+}
+
+/// Compiled here so a bad expression fails the load instead of every call.
+fn jmes_path(expression: &str, field: &str) -> error::Result<String> {
+    jmespath::compile(expression).map_err(|err| error::ServiceLoader::InvalidJmesPath {
+        field: field.into(),
+        message: err.to_string(),
+    })?;
+
+    Ok(expression.to_owned())
+}
+
+/// Can be set on the pagination style or once for the whole `x-pagination` block.
+fn error_on_path_not_found(
+    source: &serde_json::Value,
+    style: &serde_json::Value,
+) -> error::Result<protobuf::MessageField<protobuf::well_known_types::wrappers::BoolValue>> {
+    let value = match optional_field::<bool>(style, "errorOnPathNotFound")? {
+        Some(value) => value,
+        None => default_field(source, "errorOnPathNotFound")?,
+    };
+
+    Ok(protobuf::MessageField::some(value.into()))
+}
+
 ///
 fn handle_parameter<R: io::Read>(
     source: &serde_json::Value,
@@ -439,7 +571,39 @@ fn handle_response<R: io::Read>(
         sink.content.insert(key.to_string(), common_media_type);
     }
 
-    // NOTE: Response Headers aren't included in the protobuf
+    let headers: HashMap<String, serde_json::Value> = default_field(source, "headers")?;
+    for (key, value) in &headers {
+        let mut common_header = service::api_response::Header::new();
+        handle_response_header(value, &mut common_header, root, fetcher, cache, schemas)?;
+        sink.headers.insert(key.clone(), common_header);
+    }
+
+    Ok(())
+}
+
+///
+fn handle_response_header<R: io::Read>(
+    source: &serde_json::Value,
+    sink: &mut service::api_response::Header,
+    root: &serde_json::Value,
+    fetcher: &dyn Fetcher<R>,
+    cache: &mut HashMap<String, serde_json::Value>,
+    schemas: &mut HashMap<String, service::Schema>,
+) -> error::Result<()> {
+    let reference = handle_reference(source, root, fetcher, cache, &mut HashSet::new())?;
+    let source = reference.as_ref().map_or(source, |&(_, ref item)| item);
+
+    if let Some(description) = optional_field(source, "description")? {
+        sink.description = description;
+    }
+
+    sink.required = default_field(source, "required")?;
+
+    if let Some(schema) = source.get("schema") {
+        let mut common_schema = service::Schema::new();
+        handle_schema(schema, &mut common_schema, root, fetcher, cache, schemas)?;
+        sink.schema = protobuf::MessageField::some(common_schema);
+    }
 
     Ok(())
 }
@@ -665,6 +829,24 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_multiple_servers() -> error::Result<()> {
+        let doc = include_str!("stubs/multiple_servers.yaml");
+
+        let fetcher = SimpleFetcher::new().with("main", doc);
+        let root = handle(&fetcher, "main")?;
+
+        assert_eq!("https://us.example.com/v1", root.basePath());
+        assert_eq!(2, root.servers.len());
+        assert_eq!("Staging", root.servers[1].description);
+        assert_eq!(
+            vec!["us".to_owned(), "eu".to_owned()],
+            root.servers[0].variables["region"].enum_
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_basic_path() -> error::Result<()> {
         let doc = include_str!("stubs/basic_path.yaml");
@@ -776,6 +958,9 @@ mod test {
         let ok_response = op.apiResponses.apiResponses.get("200").unwrap();
         assert_eq!(1, ok_response.content.len());
 
+        let header = ok_response.headers.get("X-API").unwrap();
+        assert_eq!("API Thing", header.description);
+
         Ok(())
     }
 
@@ -809,11 +994,119 @@ mod test {
         let page = page.offset();
 
         assert_eq!(100, page.maxLimit.value);
-        assert_eq!("$response.body#/", page.resultsPath.jmesPath());
+        assert_eq!("$response.body#/", page.resultsPath.columnPath());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_item_link_header_pagination() -> error::Result<()> {
+        let doc = include_str!("stubs/path_item_link_header_pagination.yaml");
+
+        let fetcher = SimpleFetcher::new().with("main", doc);
+        let root = handle(&fetcher, "main")?;
+
+        let op = root.operations.get("list_hellos").unwrap();
+
+        let page = &op.pagination;
+        assert!(page.has_linkHeader());
+
+        let page = page.linkHeader();
+
+        assert_eq!("next", page.rel);
+        assert_eq!("per_page", page.limitParam);
+        assert_eq!(50, page.maxLimit.value);
+        assert_eq!("$response.body#/items", page.resultsPath.columnPath());
 
         Ok(())
     }
 
+    #[test]
+    fn test_path_item_next_url_pagination() -> error::Result<()> {
+        let doc = include_str!("stubs/path_item_next_url_pagination.yaml");
+
+        let fetcher = SimpleFetcher::new().with("main", doc);
+        let root = handle(&fetcher, "main")?;
+
+        let op = root.operations.get("list_hellos").unwrap();
+
+        let page = &op.pagination;
+        assert!(page.has_nextUrl());
+
+        let page = page.nextUrl();
+
+        assert_eq!("$response.body#/links/next", page.nextUrlPath.columnPath());
+        assert_eq!("limit", page.limitParam);
+        assert_eq!(25, page.maxLimit.value);
+        assert!(page.errorOnPathNotFound.value);
+        assert_eq!("$response.body#/data", page.resultsPath.columnPath());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_item_multi_cursor_pagination() -> error::Result<()> {
+        let doc = include_str!("stubs/path_item_multi_cursor_pagination.yaml");
+
+        let fetcher = SimpleFetcher::new().with("main", doc);
+        let root = handle(&fetcher, "main")?;
+
+        let op = root.operations.get("list_hellos").unwrap();
+
+        let page = &op.pagination;
+        assert!(page.has_multiCursor());
+
+        let page = page.multiCursor();
+
+        assert_eq!(2, page.cursorsPath.len());
+        assert_eq!(
+            "$response.body#/nextPageToken",
+            page.cursorsPath[0].columnPath()
+        );
+        assert_eq!("sync.token", page.cursorsPath[1].jmesPath());
+        assert_eq!(vec!["pageToken", "syncToken"], page.cursorsParam);
+        assert_eq!("items[?enabled]", page.resultsPath.jmesPath());
+        assert!(page.errorOnPathNotFound.value);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_results_path_pointers() -> error::Result<()> {
+        for pointer in [
+            "/items",
+            "/data/items",
+            "data/items",
+            "items",
+            "$response.body#/items",
+        ] {
+            let path = handle_extended_path(Some(&pointer.into()), "resultsPath")?;
+            assert_eq!(pointer, path.columnPath(), "{pointer}");
+        }
+
+        let path = handle_extended_path(Some(&"data[?active].id".into()), "resultsPath")?;
+        assert_eq!("data[?active].id", path.jmesPath());
+
+        let path = handle_extended_path(None, "resultsPath")?;
+        assert_eq!("", path.columnPath());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_jmes_path() {
+        for source in [
+            serde_json::json!("items[?"),
+            serde_json::json!({ "jmesPath": "data[" }),
+        ] {
+            let path = handle_extended_path(Some(&source), "resultsPath");
+            assert!(
+                matches!(path, Err(error::ServiceLoader::InvalidJmesPath { ref field, .. }) if field == "resultsPath"),
+                "{source}"
+            );
+        }
+    }
+
     #[test]
     fn test_basic_schema() -> error::Result<()> {
         let doc = include_str!("stubs/basic_schema.yaml");
@@ -106,7 +106,8 @@ fn fetch_and_cache<'cache, R: io::Read>(
     let result = match cache.entry(source.to_owned()) {
         Entry::Vacant(vacant) => {
             let result = fetcher.fetch(source)?;
-            let result: serde_json::Value = serde_yaml::from_reader(result)?;
+            let mut result: serde_json::Value = serde_yaml::from_reader(result)?;
+            rebase(&mut result, source);
             vacant.insert(result)
         }
         Entry::Occupied(occupied) => occupied.into_mut(),
@@ -115,6 +116,105 @@ fn fetch_and_cache<'cache, R: io::Read>(
     Ok(result)
 }
 
+/// `$ref`s in a fetched document are relative to that document. They're rewritten to be
+/// relative to the connector so they still resolve once the content is inlined elsewhere.
+fn rebase(value: &mut serde_json::Value, source: &str) {
+    match value {
+        &mut serde_json::Value::Object(ref mut map) => {
+            for (key, child) in map.iter_mut() {
+                match child {
+                    &mut serde_json::Value::String(ref mut reference) if key == REF_KEY => {
+                        *reference = rebase_reference(reference, source);
+                    }
+                    &mut (serde_json::Value::Object(_) | serde_json::Value::Array(_)) => {
+                        rebase(child, source);
+                    }
+                    &mut (serde_json::Value::Null
+                    | serde_json::Value::Bool(_)
+                    | serde_json::Value::Number(_)
+                    | serde_json::Value::String(_)) => {}
+                }
+            }
+        }
+        &mut serde_json::Value::Array(ref mut items) => {
+            for item in items {
+                rebase(item, source);
+            }
+        }
+        &mut (serde_json::Value::Null
+        | serde_json::Value::Bool(_)
+        | serde_json::Value::Number(_)
+        | serde_json::Value::String(_)) => {}
+    }
+}
+
+///
+fn rebase_reference(reference: &str, source: &str) -> String {
+    let (target, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    let target = if target.is_empty() {
+        source.to_owned()
+    } else {
+        resolve_location(source, target)
+    };
+
+    format!("{target}#{fragment}")
+}
+
+/// Resolves `target` against the location of the document that refers to it, which
+/// is either a URL or a path within the connector.
+pub fn resolve_location(base: &str, target: &str) -> String {
+    if target.contains("://") {
+        return target.to_owned();
+    }
+
+    if let Some((scheme, rest)) = base.split_once("://") {
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let path = match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_owned(),
+            None => join(path, target),
+        };
+
+        return format!("{scheme}://{authority}/{}", normalize(&path));
+    }
+
+    let normalized = normalize(&join(base, target));
+
+    // Keeps locations looking like the ones from the manifest
+    if base.starts_with("./") {
+        format!("./{normalized}")
+    } else {
+        normalized
+    }
+}
+
+/// `target` in the same directory as `base`.
+fn join(base: &str, target: &str) -> String {
+    match base.rsplit_once('/') {
+        Some((dir, _)) if !dir.is_empty() => format!("{dir}/{target}"),
+        Some(_) | None => target.to_owned(),
+    }
+}
+
+/// Drops `.` and folds `..` into its parent.
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = vec![];
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.last().is_none_or(|last| *last == "..") {
+                    parts.push(part);
+                } else {
+                    parts.pop();
+                }
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    parts.join("/")
+}
+
 ///
 #[derive(Debug)]
 struct Reference {
@@ -196,4 +296,53 @@ mod test {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_resolve_location() {
+        assert_eq!(
+            "https://example.com/specs/common.yaml",
+            resolve_location("https://example.com/specs/api.yaml", "./common.yaml")
+        );
+        assert_eq!(
+            "https://example.com/shared/common.yaml",
+            resolve_location(
+                "https://example.com/specs/api.yaml",
+                "../shared/common.yaml"
+            )
+        );
+        assert_eq!(
+            "https://example.com/common.yaml",
+            resolve_location("https://example.com/specs/api.yaml", "/common.yaml")
+        );
+        assert_eq!(
+            "https://other.example.com/common.yaml",
+            resolve_location("./spec.yaml", "https://other.example.com/common.yaml")
+        );
+        assert_eq!(
+            "./schemas/pet.yaml",
+            resolve_location("./schemas/common.yaml", "pet.yaml")
+        );
+        assert_eq!(
+            "./params.yaml",
+            resolve_location("./spec.yaml", "./params.yaml")
+        );
+    }
+
+    #[test]
+    fn test_rebase_nested_references() {
+        let mut doc = serde_json::json!({
+            "Pet": { "$ref": "#/Animal" },
+            "Owner": { "properties": { "pets": { "items": { "$ref": "./pets.yaml#/Pet" } } } },
+        });
+        rebase(&mut doc, "https://example.com/specs/common.yaml");
+
+        assert_eq!(
+            "https://example.com/specs/common.yaml#/Animal",
+            doc["Pet"]["$ref"]
+        );
+        assert_eq!(
+            "https://example.com/specs/pets.yaml#/Pet",
+            doc["Owner"]["properties"]["pets"]["items"]["$ref"]
+        );
+    }
 }
@@ -0,0 +1,114 @@
+//! Routes each location to the fetcher for where it lives, so a connector on disk can
+//! reference components that are served remotely.
+
+use std::io;
+
+use crate::Fetcher;
+
+/// Lets fetchers with different readers sit side by side.
+struct Erased<'fetcher, R>(&'fetcher dyn Fetcher<R>);
+
+impl<'fetcher, R: io::Read + 'fetcher> Fetcher<Box<dyn io::Read + 'fetcher>>
+    for Erased<'fetcher, R>
+{
+    #[inline]
+    fn fetch(&self, location: &str) -> io::Result<Box<dyn io::Read + 'fetcher>> {
+        let reader = self.0.fetch(location)?;
+        Ok(Box::new(reader))
+    }
+}
+
+/// Any fetcher once its reader is boxed.
+type Routed<'fetcher> = Box<dyn Fetcher<Box<dyn io::Read + 'fetcher>> + 'fetcher>;
+
+/// The first route whose prefix matches a location fetches it, anything else goes to the default.
+pub struct CompositeFetcher<'fetcher> {
+    ///
+    default: Routed<'fetcher>,
+
+    ///
+    routes: Vec<(String, Routed<'fetcher>)>,
+}
+
+impl<'fetcher> CompositeFetcher<'fetcher> {
+    ///
+    #[must_use]
+    #[inline]
+    pub fn new<R: io::Read + 'fetcher>(default: &'fetcher dyn Fetcher<R>) -> Self {
+        Self {
+            default: Box::new(Erased(default)),
+            routes: vec![],
+        }
+    }
+
+    /// i.e. `https://` to a fetcher that makes HTTP requests.
+    #[must_use]
+    #[inline]
+    pub fn route<R: io::Read + 'fetcher>(
+        mut self,
+        prefix: &str,
+        fetcher: &'fetcher dyn Fetcher<R>,
+    ) -> Self {
+        self.routes
+            .push((prefix.to_owned(), Box::new(Erased(fetcher))));
+        self
+    }
+}
+
+impl<'fetcher> Fetcher<Box<dyn io::Read + 'fetcher>> for CompositeFetcher<'fetcher> {
+    #[inline]
+    fn fetch(&self, location: &str) -> io::Result<Box<dyn io::Read + 'fetcher>> {
+        let fetcher = self
+            .routes
+            .iter()
+            .find(|&&(ref prefix, _)| location.starts_with(prefix.as_str()))
+            .map_or(&self.default, |&(_, ref fetcher)| fetcher);
+
+        fetcher.fetch(location)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::restriction, clippy::pedantic)]
+
+    use super::*;
+    use std::io::Read;
+
+    struct Named(&'static str);
+
+    impl Fetcher<io::Cursor<String>> for Named {
+        fn fetch(&self, location: &str) -> io::Result<io::Cursor<String>> {
+            Ok(io::Cursor::new(format!("{} {location}", self.0)))
+        }
+    }
+
+    fn fetch(fetcher: &CompositeFetcher, location: &str) -> String {
+        let mut content = String::new();
+        fetcher
+            .fetch(location)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_routes_by_prefix() {
+        let local = Named("local");
+        let remote = Named("remote");
+        let fetcher = CompositeFetcher::new(&local)
+            .route("https://", &remote)
+            .route("http://", &remote);
+
+        assert_eq!("local ./spec.yaml", fetch(&fetcher, "./spec.yaml"));
+        assert_eq!(
+            "remote https://example.com/common.yaml",
+            fetch(&fetcher, "https://example.com/common.yaml")
+        );
+        assert_eq!(
+            "remote http://localhost/common.yaml",
+            fetch(&fetcher, "http://localhost/common.yaml")
+        );
+    }
+}
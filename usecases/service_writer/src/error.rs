@@ -30,6 +30,14 @@ pub enum ServiceWriter {
         source: protobuf_json_mapping::PrintError,
     },
 
+    ///
+    #[error(transparent)]
+    Loader {
+        ///
+        #[from]
+        source: service_loader::error::ServiceLoader,
+    },
+
     ///
     #[error(transparent)]
     Io {
@@ -17,11 +17,15 @@
 
 //!
 
+extern crate alloc;
+use alloc::collections::BTreeMap;
+
 use std::{collections::HashMap, io};
 
 use core_entities::{service, service::VersionedServiceTree};
 use credential_entities::credentials::Authentication;
 use protobuf::EnumFull as _;
+use service_loader::credentials::Profiles;
 
 pub mod error;
 
@@ -97,6 +101,24 @@ impl ServiceWriter {
 
         Ok(())
     }
+
+    /// Writes every profile to `credentials.json`, a lone default profile is written the way
+    /// [`ServiceWriter::store_credentials`] would.
+    ///
+    /// # Errors
+    #[inline]
+    pub fn store_profiles<W: io::Write>(
+        &self,
+        profiles: &Profiles,
+        storage: &dyn Storage<W>,
+    ) -> error::Result<()> {
+        let creds = serde_json::to_string_pretty(&profiles.to_json()?)?;
+
+        let mut location = storage.store("./credentials.json")?;
+        location.write_all(creds.as_bytes())?;
+
+        Ok(())
+    }
 }
 
 impl Default for ServiceWriter {
@@ -106,6 +128,40 @@ impl Default for ServiceWriter {
     }
 }
 
+/// A server along with its variables, as it was in the spec.
+fn handle_server(message: &service::ServerWithVariables) -> serde_json::Value {
+    let mut server = serde_json::Map::new();
+    server.insert("url".into(), message.url.clone().into());
+
+    if !message.description.is_empty() {
+        server.insert("description".into(), message.description.clone().into());
+    }
+
+    if !message.variables.is_empty() {
+        let variables: BTreeMap<_, _> = message
+            .variables
+            .iter()
+            .map(|(name, variable)| {
+                let mut value = serde_json::Map::new();
+                value.insert("default".into(), variable.default.clone().into());
+                if !variable.description.is_empty() {
+                    value.insert("description".into(), variable.description.clone().into());
+                }
+                if !variable.enum_.is_empty() {
+                    value.insert("enum".into(), variable.enum_.clone().into());
+                }
+                (name.clone(), serde_json::Value::from(value))
+            })
+            .collect();
+        server.insert(
+            "variables".into(),
+            serde_json::Map::from_iter(variables).into(),
+        );
+    }
+
+    server.into()
+}
+
 ///
 fn handle_openapi<W: io::Write>(
     storage: &dyn Storage<W>,
@@ -115,9 +171,14 @@ fn handle_openapi<W: io::Write>(
 ) -> error::Result<()> {
     let mut root = serde_json::Map::new();
 
-    let mut server = serde_json::Map::new();
-    server.insert("url".into(), message.basePath().into());
-    root.insert("servers".into(), vec![server].into());
+    let servers = if message.servers.is_empty() {
+        let mut server = serde_json::Map::new();
+        server.insert("url".into(), message.basePath().into());
+        vec![server.into()]
+    } else {
+        message.servers.iter().map(handle_server).collect()
+    };
+    root.insert("servers".into(), servers.into());
 
     if !message.description.is_empty() || !message.title.is_empty() {
         let mut info = serde_json::Map::new();
@@ -283,6 +344,38 @@ fn handle_response(
         sink.insert("content".into(), content.into());
     }
 
+    if !source.headers.is_empty() {
+        let mut headers = serde_json::Map::new();
+        for (name, common_header) in &source.headers {
+            let mut header = serde_json::Map::new();
+            handle_response_header(&mut header, common_header)?;
+            headers.insert(name.clone(), header.into());
+        }
+        sink.insert("headers".into(), headers.into());
+    }
+
+    Ok(())
+}
+
+///
+fn handle_response_header(
+    sink: &mut serde_json::Map<String, serde_json::Value>,
+    source: &service::api_response::Header,
+) -> error::Result<()> {
+    if !source.description.is_empty() {
+        sink.insert("description".into(), source.description.clone().into());
+    }
+
+    if source.required {
+        sink.insert("required".into(), source.required.into());
+    }
+
+    if let &Some(ref common_schema) = &source.schema.0 {
+        let mut schema = serde_json::Map::new();
+        handle_schema(&mut schema, common_schema)?;
+        sink.insert("schema".into(), schema.into());
+    }
+
     Ok(())
 }
 